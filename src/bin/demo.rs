@@ -2,7 +2,9 @@
 // Demo showcasing Psy Integration + SDKey Verification + Market Validation
 
 use spectre_protocol::*;
-use tokio::time::{sleep, Duration};
+use std::sync::Arc;
+use futures::StreamExt;
+use tokio::time::{sleep, timeout, Duration};
 use colored::*;
 
 #[tokio::main]
@@ -18,16 +20,30 @@ async fn main() {
     
     // 2. Initialize Psy Client
     println!("\n{}", "🔗 PSY PROTOCOL INTEGRATION".bold().cyan());
-    let mut psy_client = PsyClient::new();
-    println!("Connected to: {}", psy_client.testnet_url);
+    // Set PSY_RPC_URL to run against a real node instead of the local simulator
+    let psy_client: Arc<dyn PsyChain> = match std::env::var(PSY_RPC_URL_ENV) {
+        Ok(url) => {
+            println!("Connected to: {}", url);
+            let client = Arc::new(PsyClient::with_endpoint(&url));
+            client.spawn_status_poller(Duration::from_millis(100));
+            client
+        }
+        Err(_) => {
+            println!("Connected to: local Psy simulator");
+            let simulator = Arc::new(PsySimulator::new());
+            simulator.spawn_block_production();
+            simulator
+        }
+    };
     
     // 3. Initialize SDKey Registry
     println!("\n{}", "🔐 SDKEY VERIFICATION SYSTEM".bold().cyan());
     let mut sdkey_registry = SDKeyRegistry::new();
     
-    // Register some test agents
-    sdkey_registry.register_agent("agent_001".to_string(), "LLaMA-3-70B".to_string(), 5000).unwrap();
-    sdkey_registry.register_agent("agent_002".to_string(), "GPT-4-Turbo".to_string(), 3000).unwrap();
+    // Register some test agents, each with its own signing keypair
+    let agent_keys = [generate_agent_keypair(), generate_agent_keypair()];
+    sdkey_registry.register_agent("agent_001".to_string(), "LLaMA-3-70B".to_string(), 5000, agent_keys[0].verifying_key()).unwrap();
+    sdkey_registry.register_agent("agent_002".to_string(), "GPT-4-Turbo".to_string(), 3000, agent_keys[1].verifying_key()).unwrap();
     println!("✅ Registered 2 agents with verified capabilities");
     
     // 4. Simulate Agent Transactions
@@ -37,8 +53,12 @@ async fn main() {
         let agent_id = format!("agent_{:03}", (i % 2) + 1);
         let model_type = if i % 2 == 0 { "LLaMA-3-70B" } else { "GPT-4-Turbo" };
         
-        // Generate SDKey proof
-        let proof = generate_mock_proof(agent_id.clone(), model_type);
+        // Generate signed SDKey proof
+        let proof = ProofBuilder::new(&agent_keys[i % 2], agent_id.clone(), model_type)
+            .expect("demo models are in the built-in catalog")
+            .job(i as u64)
+            .result(format!("inference result {}", i).as_bytes())
+            .build();
         
         // Verify with SDKey system
         match sdkey_registry.verify_sdkey_proof(&proof, 4000) {
@@ -54,13 +74,22 @@ async fn main() {
                     Ok(tx_id) => {
                         println!("📡 Submitted to Psy PARTH: {}", tx_id.blue());
                         
-                        // Check status after delay
-                        sleep(Duration::from_millis(100)).await;
-                        if let Some(status) = psy_client.check_transaction_status(&tx_id).await {
-                            println!("🎯 Transaction status: {:?}", status);
+                        // Follow the transaction until it is final
+                        match psy_client.watch(&tx_id).await {
+                            Ok(mut updates) => {
+                                let followed = timeout(Duration::from_secs(3), async {
+                                    while let Some(update) = updates.next().await {
+                                        println!("🎯 Transaction status: {:?}", update.status);
+                                    }
+                                }).await;
+                                if followed.is_err() {
+                                    println!("⏳ Transaction {} not final yet", tx_id);
+                                }
+                            }
+                            Err(e) => println!("❌ Status check failed: {}", e.to_string().red()),
                         }
                     },
-                    Err(e) => println!("❌ Psy submission failed: {}", e.red()),
+                    Err(e) => println!("❌ Psy submission failed: {}", e.to_string().red()),
                 }
             },
             Ok(false) => println!("❌ {} verification failed", agent_id.red()),
@@ -72,11 +101,17 @@ async fn main() {
     
     // 5. Show Network Stats
     println!("\n{}", "📈 PSY NETWORK STATS".bold().cyan());
-    let stats = psy_client.get_network_stats();
-    println!("Active Agents: {}", stats.active_agents);
-    println!("Current TPS: {}", stats.tps);
-    println!("Avg Latency: {}ms", stats.avg_latency_ms);
-    println!("PARTH Depth: {}", stats.parth_depth);
+    match psy_client.network_stats().await {
+        Ok(stats) => {
+            println!("Active Agents: {}", stats.active_agents);
+            println!("Current TPS: {}", stats.tps);
+            println!("Avg Latency: {}ms", stats.avg_latency_ms);
+            println!("Latency p50/p95/p99: {}/{}/{}ms", stats.latency_p50_ms, stats.latency_p95_ms, stats.latency_p99_ms);
+            println!("Failure Rate: {:.1}%", stats.failure_rate * 100.0);
+            println!("PARTH Depth: {}", stats.parth_depth);
+        }
+        Err(e) => println!("❌ Network stats unavailable: {}", e.to_string().red()),
+    }
     
     // 6. Show Latency Benchmarks
     println!("\n{}", "⏱️  LATENCY BENCHMARKS".bold().cyan());
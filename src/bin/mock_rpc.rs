@@ -0,0 +1,28 @@
+// src/bin/mock_rpc.rs
+// Local Psy JSON-RPC node backed by the in-process simulator
+
+use spectre_protocol::*;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+#[tokio::main]
+async fn main() {
+    let addr: SocketAddr = std::env::var("PSY_MOCK_RPC_ADDR")
+        .unwrap_or_else(|_| "127.0.0.1:8545".to_string())
+        .parse()
+        .expect("PSY_MOCK_RPC_ADDR must be host:port");
+
+    let simulator = Arc::new(PsySimulator::new());
+    let _blocks = simulator.spawn_block_production();
+    let server = match MockPsyRpcServer::start_on(simulator, addr) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(1);
+        }
+    };
+    println!("🛰️  Mock Psy RPC listening on {}", server.url());
+    println!("   Run the demo against it with {}={}", PSY_RPC_URL_ENV, server.url());
+
+    tokio::signal::ctrl_c().await.expect("failed to listen for ctrl-c");
+}
@@ -1,120 +1,206 @@
 // src/bin/swarm.rs
-// --- SPECTRE SWARM SERVER V3: CHAOS EDITION (STABLE) ---
+// --- SPECTRE SWARM SERVER V4: EVENT-SOURCED EDITION ---
 
 use tokio::time::{sleep, Duration};
 use rand::Rng;
+use spectre_protocol::{
+    generate_agent_keypair, EventLog, FeeUrgency, JobRequest, JobSettlement, PsyChain, PsySimulator,
+    PsyTransaction, SDKeyRegistry, SigningKey, SpectreMarket, SpectreResult,
+};
+use std::sync::{Arc, Mutex};
 use colored::*;
 use warp::Filter;
-use serde::{Serialize}; 
-use tokio::sync::broadcast;
-
-#[derive(Serialize, Clone, Debug)]
-struct AgentEvent {
-    agent_id: u32,
-    status: String, 
-    task: String,     
-    tx_hash: String,  
-    reward: u64,
-    latency: u64,
-    timestamp: u64,
+use tokio::sync::broadcast::error::RecvError;
+
+const AGENT_COUNT: usize = 1000;
+const AGENT_STAKE: u64 = 5_000;
+const TREASURY: &str = "swarm_treasury";
+// Enough for the swarm to post jobs for a very long time
+const TREASURY_FUNDS: u64 = 1_000_000_000_000;
+// The feed only streams the log, so keep just the recent tail in memory
+const EVENT_RETENTION: usize = 10_000;
+// A model build no catalog accepts: cheating agents claim it and get slashed
+const FORGED_MODEL_HASH: &str = "0xdeadbeef0000";
+
+const MODELS: [&str; 4] = ["LLaMA-3-70B", "GPT-4-Turbo", "Claude-3-Opus", "Mistral-Large"];
+
+struct SwarmAgent {
+    id: String,
+    model: &'static str,
+    key: SigningKey,
 }
 
 #[tokio::main]
 async fn main() {
-    println!("{}", "--- SPECTRE V3: CRYPTO-HASH ENGINE ACTIVE ---".bold().purple());
+    println!("{}", "--- SPECTRE V4: EVENT-SOURCED ENGINE ACTIVE ---".bold().purple());
 
-    let (tx, _rx) = broadcast::channel::<AgentEvent>(100);
-    let tx_clone = tx.clone();
+    let registry = SDKeyRegistry::builder()
+        .event_log(EventLog::with_retention(EVENT_RETENTION))
+        .build();
+    let mut market = SpectreMarket::with_registry(registry);
+    let agents = register_agents(&mut market);
+    let events = market.events().clone();
+    println!("{}", format!("[SYSTEM] {} agents registered and staked", agents.len()).green());
 
     // Spawn the Engine
+    let market = Arc::new(Mutex::new(market));
     tokio::spawn(async move {
-        run_swarm_engine(tx_clone).await;
+        run_swarm_engine(market, agents).await;
     });
 
-    // Start Server
+    // Start Server: every client gets the market's own event log, live
     let ws_route = warp::path("spectre")
         .and(warp::ws())
         .map(move |ws: warp::ws::Ws| {
-            let tx = tx.clone();
-            ws.on_upgrade(move |socket| handle_connection(socket, tx))
+            let events = events.clone();
+            ws.on_upgrade(move |socket| handle_connection(socket, events))
         });
 
     println!("{}", "[SYSTEM] WebSocket Server Active on ws://127.0.0.1:3030/spectre".green());
     warp::serve(ws_route).run(([127, 0, 0, 1], 3030)).await;
 }
 
-async fn handle_connection(ws: warp::ws::WebSocket, tx: broadcast::Sender<AgentEvent>) {
+async fn handle_connection(ws: warp::ws::WebSocket, events: EventLog) {
     use futures::{SinkExt, StreamExt};
     let (mut user_ws_tx, _user_ws_rx) = ws.split();
-    let mut rx = tx.subscribe();
+    let mut rx = events.subscribe();
 
-    while let Ok(event) = rx.recv().await {
-        let json = serde_json::to_string(&event).unwrap();
+    loop {
+        let record = match rx.recv().await {
+            Ok(record) => record,
+            // A slow client skips what it missed instead of being dropped
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => break,
+        };
+        let json = serde_json::to_string(&record).unwrap();
         if let Err(_e) = user_ws_tx.send(warp::ws::Message::text(json)).await {
-            break; 
+            break;
         }
     }
 }
 
-// --- THE CHAOS ENGINE (FIXED) ---
-async fn run_swarm_engine(tx: broadcast::Sender<AgentEvent>) {
-    let agent_count = 1000;
-    
-    let models = vec!["LLaMA-3-70B", "GPT-4-Turbo", "Claude-3-Opus", "Mistral-Large"];
-    let pairs = vec!["SOL/USDC", "SUI/USDT", "ETH/BTC", "PSY/USDC"];
-    let sites = vec!["Twitter", "Reddit", "Bloomberg", "Discord"];
-    
+// Fund the treasury that posts jobs and bond every agent's stake
+fn register_agents(market: &mut SpectreMarket) -> Vec<SwarmAgent> {
+    market.mint(TREASURY, TREASURY_FUNDS).expect("fresh ledger");
+    (0..AGENT_COUNT)
+        .map(|i| {
+            let agent = SwarmAgent {
+                id: format!("agent_{:03}", i),
+                model: MODELS[i % MODELS.len()],
+                key: generate_agent_keypair(),
+            };
+            market.mint(&agent.id, AGENT_STAKE).expect("treasury funds stay far below u64::MAX");
+            market
+                .register_agent(agent.id.clone(), vec![agent.model.to_string()], AGENT_STAKE, agent.key.verifying_key())
+                .expect("built-in models are always available");
+            agent
+        })
+        .collect()
+}
+
+// Post a job for the agent's model and claim it with a fresh SDKey proof;
+// a job nobody could claim is cancelled so its escrow goes back
+fn post_and_claim(market: &mut SpectreMarket, agent: &SwarmAgent, job_id: u64, reward: u64) -> SpectreResult<()> {
+    market.post_job_request(JobRequest::new(job_id, TREASURY.to_string(), agent.model.to_string(), reward))?;
+    let proof = market.registry.proof_builder(&agent.key, agent.id.clone(), agent.model)?
+        .job(job_id)
+        .build();
+    if let Err(e) = market.attempt_job_execution(job_id, &proof) {
+        let _ = market.cancel_job(job_id);
+        return Err(e);
+    }
+    Ok(())
+}
+
+// Submit the agent's result (under a forged model build when it cheats),
+// verify it and settle the escrow either way
+fn deliver(market: &mut SpectreMarket, agent: &SwarmAgent, job_id: u64, output: &[u8], cheat: bool) -> SpectreResult<JobSettlement> {
+    let mut proof = market.registry.proof_builder(&agent.key, agent.id.clone(), agent.model)?
+        .job(job_id)
+        .result(output);
+    if cheat {
+        proof = proof.model_hash(FORGED_MODEL_HASH);
+    }
+    market.submit_result(job_id, proof.build())?;
+    let verdict = market.verify_result(job_id);
+    let settlement = market.settle_job(job_id)?;
+    verdict.map(|_| settlement)
+}
+
+// --- THE CHAOS ENGINE: every job runs through the real market ---
+async fn run_swarm_engine(market: Arc<Mutex<SpectreMarket>>, agents: Vec<SwarmAgent>) {
+    let pairs = ["SOL/USDC", "SUI/USDT", "ETH/BTC", "PSY/USDC"];
+    let sites = ["Twitter", "Reddit", "Bloomberg", "Discord"];
+
+    // Fees are quoted and paid on a local Psy chain
+    let chain = Arc::new(PsySimulator::new());
+    chain.spawn_block_production();
+
+    let mut next_job_id = 1;
     loop {
-        // STEP 1: GENERATE DATA (Sync Block)
+        // STEP 1: ROLL THE DICE (Sync Block)
         // We do all math here so 'rng' is dropped before we ever await.
-        let (event, delay_ms, log_msg, _is_error) = {
+        let (agent, task_detail, reward, urgency, latency_ms, cheat, delay_ms) = {
             let mut rng = rand::thread_rng();
-
-            // Fake Hash
-            let tx_hash: String = (0..8).map(|_| format!("{:x}", rng.gen::<u8>())).collect::<String>();
-            let short_hash = format!("0x{}...", &tx_hash[0..6]);
+            let agent = &agents[rng.gen_range(0..agents.len())];
 
             // Task Details
             let job_type = rng.gen_range(0..4);
             let task_detail = match job_type {
-                0 => format!("Inference [{}]", models[rng.gen_range(0..models.len())]),
-                1 => format!("Gen-Z Proof [{}]", short_hash), 
+                0 => format!("Inference [{}]", agent.model),
+                1 => format!("Gen-Z Proof [0x{:06x}...]", rng.gen::<u32>() & 0xff_ffff),
                 2 => format!("Scrape [{}]", sites[rng.gen_range(0..sites.len())]),
                 _ => format!("Arb Swap [{}]", pairs[rng.gen_range(0..pairs.len())]),
             };
-
-            // Status
-            let is_failure = rng.gen_range(0..100) < 3; 
-            let status = if is_failure { "FAILED".to_string() } else { "VERIFIED".to_string() };
-            let reward = rng.gen_range(5..1200);
-            let fee = rng.gen_range(1..5);
-            let delay = rng.gen_range(20..150); // Calculate delay HERE
-
-            let event = AgentEvent {
-                agent_id: rng.gen_range(0..agent_count),
-                status: status.clone(),
-                task: task_detail.clone(),
-                tx_hash: short_hash,
-                reward,
-                latency: rng.gen_range(12..450),
-                timestamp: 0,
+            // Arbitrage races for inclusion; scraping can wait
+            let urgency = match job_type {
+                0 => FeeUrgency::Normal,
+                1 => FeeUrgency::High,
+                2 => FeeUrgency::Low,
+                _ => FeeUrgency::Instant,
             };
 
-            // Prepare log string to avoid printing inside the block
-            let log = if is_failure {
-                format!("{} {} | {}", "✖".red(), status.red(), task_detail)
-            } else {
-                format!("{} {} | {} | Fee: ${}", "✔".green(), "CONFIRMED".green(), task_detail, fee)
-            };
-
-            (event, delay, log, is_failure)
+            let cheat = rng.gen_range(0..100) < 3;
+            (agent, task_detail, rng.gen_range(5..1200), urgency, rng.gen_range(12..450), cheat, rng.gen_range(20..150))
         }; // <--- RNG DIES HERE. Safe to await now.
+        let job_id = next_job_id;
+        next_job_id += 1;
+
+        // STEP 2: POST & CLAIM (the market lock is never held across an await)
+        let claimed = post_and_claim(&mut market.lock().unwrap(), agent, job_id, reward);
+        if let Err(e) = claimed {
+            println!("{} {} | {} | {}", "✖".red(), "REFUSED".red(), task_detail, e);
+            sleep(Duration::from_millis(delay_ms)).await;
+            continue;
+        }
 
-        // STEP 2: BROADCAST & LOG
-        let _ = tx.send(event);
-        println!("{}", log_msg);
+        // STEP 3: THE AGENT WORKS
+        sleep(Duration::from_millis(latency_ms)).await;
+
+        // STEP 4: SUBMIT, VERIFY, SETTLE
+        let outcome = deliver(&mut market.lock().unwrap(), agent, job_id, task_detail.as_bytes(), cheat);
+
+        // STEP 5: PAY FOR INCLUSION at the going rate for this urgency
+        let mut psy_tx = PsyTransaction::new(agent.id.clone(), task_detail.clone(), reward);
+        let fee = match chain.fee_estimate().await {
+            Ok(estimate) => {
+                estimate.apply(&mut psy_tx, urgency);
+                format!(" | Fee: {} base + {} tip /gas", estimate.base_fee, psy_tx.priority_fee)
+            }
+            Err(_) => String::new(),
+        };
+        let _ = chain.submit_transaction(psy_tx).await;
+
+        // STEP 6: LOG (the WebSocket feed gets the market's events directly)
+        match outcome {
+            Ok(JobSettlement::Paid { payout, .. }) => {
+                println!("{} {} | {} | {} paid {}{}", "✔".green(), "CONFIRMED".green(), task_detail, agent.id, payout, fee)
+            }
+            Ok(JobSettlement::Refunded { .. }) => println!("{} {} | {}", "✖".red(), "REFUNDED".red(), task_detail),
+            Err(e) => println!("{} {} | {} | {}", "✖".red(), "FAILED".red(), task_detail, e.code()),
+        }
 
-        // STEP 3: SLEEP (Safe now because delay_ms is just a number)
-        sleep(Duration::from_millis(delay_ms)).await; 
+        // STEP 7: SLEEP
+        sleep(Duration::from_millis(delay_ms)).await;
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,52 @@
+// src/clock.rs
+// Time source abstraction so proof freshness and job timing are testable
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Source of the current Unix time in seconds.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> u64;
+}
+
+/// Wall-clock time from the operating system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+}
+
+/// Manually driven clock for deterministic tests.
+///
+/// Clones share the same underlying time, so a test can keep a handle and
+/// advance the clock seen by a registry or market.
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    now: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    pub fn new(start: u64) -> Self {
+        ManualClock { now: Arc::new(AtomicU64::new(start)) }
+    }
+
+    pub fn set(&self, now: u64) {
+        self.now.store(now, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, secs: u64) {
+        self.now.fetch_add(secs, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> u64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
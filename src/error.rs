@@ -0,0 +1,392 @@
+// src/error.rs
+// Crate-wide error type shared by the market, SDKey registry and Psy client
+
+use crate::lifecycle::JobStatus;
+use crate::psy_integration::PsyTxStatus;
+use std::fmt;
+
+/// Result alias used by every public Spectre API.
+pub type SpectreResult<T> = Result<T, SpectreError>;
+
+/// Typed failure reasons for Spectre Protocol operations.
+///
+/// Every variant carries the context needed to act on it (which agent, which
+/// job, what was expected) and maps to a stable machine-readable code via
+/// [`SpectreError::code`], so callers can decide whether to slash, retry or
+/// drop without matching on message text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpectreError {
+    AgentNotRegistered {
+        agent_id: String,
+    },
+    AgentAlreadyRegistered {
+        agent_id: String,
+    },
+    JobNotFound {
+        job_id: u64,
+    },
+    CapabilityMismatch {
+        agent_id: String,
+        required_model: String,
+    },
+    JobAlreadyTaken {
+        job_id: u64,
+    },
+    DuplicateJob {
+        job_id: u64,
+    },
+    JobExpired {
+        job_id: u64,
+        expired_at: u64,
+    },
+    InvalidJobTransition {
+        job_id: u64,
+        from: JobStatus,
+        to: JobStatus,
+    },
+    NotJobAssignee {
+        job_id: u64,
+        agent_id: String,
+    },
+    JobNotDisputable {
+        job_id: u64,
+        status: JobStatus,
+    },
+    JobAlreadyDisputed {
+        job_id: u64,
+    },
+    HashMismatch {
+        agent_id: String,
+        expected: String,
+        actual: String,
+    },
+    CapacityExceeded {
+        agent_id: String,
+        requested: u32,
+        max_tokens: u32,
+    },
+    LowReputation {
+        agent_id: String,
+        score: f64,
+        threshold: f64,
+    },
+    InsufficientStake {
+        agent_id: String,
+        staked: u64,
+        required: u64,
+    },
+    InvalidProof {
+        agent_id: String,
+        reason: String,
+    },
+    StaleProof {
+        agent_id: String,
+        timestamp: u64,
+        now: u64,
+        max_age_secs: u64,
+    },
+    FutureProof {
+        agent_id: String,
+        timestamp: u64,
+        now: u64,
+        max_skew_secs: u64,
+    },
+    ReplayedProof {
+        agent_id: String,
+        nonce: u32,
+    },
+    UnknownModel {
+        model_type: String,
+    },
+    ModelDeprecated {
+        model_type: String,
+    },
+    InvalidCatalog {
+        reason: String,
+    },
+    InvalidRetryPolicy {
+        reason: String,
+    },
+    InvalidSimulatorConfig {
+        reason: String,
+    },
+    InsufficientFunds {
+        account: String,
+        balance: u64,
+        required: u64,
+    },
+    EscrowNotFound {
+        job_id: u64,
+    },
+    LedgerInvariantViolated {
+        total_supply: u64,
+        accounted: u64,
+    },
+    SupplyOverflow {
+        total_supply: u64,
+        minted: u64,
+    },
+    NetworkFailure {
+        endpoint: String,
+        reason: String,
+    },
+    RpcError {
+        method: String,
+        code: i64,
+        message: String,
+        /// The chain's own error code when it refused the call on its merits
+        chain_code: Option<String>,
+    },
+    TransactionConflict {
+        tx_id: String,
+    },
+    InvalidTransactionSignature {
+        tx_id: String,
+        reason: String,
+    },
+    NonceAlreadyUsed {
+        account: String,
+        nonce: u64,
+    },
+    ReplacementUnderpriced {
+        tx_id: String,
+        min_priority_fee: u64,
+    },
+    ReplacementMismatch {
+        tx_id: String,
+        reason: String,
+    },
+    MempoolFull {
+        capacity: usize,
+        min_priority_fee: u64,
+    },
+    TransactionNotReplaceable {
+        tx_id: String,
+        status: Option<PsyTxStatus>,
+    },
+    TransactionDropped {
+        tx_id: String,
+        status: PsyTxStatus,
+    },
+    SettlementTxFailed {
+        job_id: u64,
+        tx_id: String,
+        status: PsyTxStatus,
+    },
+    InvalidStateProof {
+        key: String,
+        reason: String,
+    },
+    StorageFailure {
+        path: String,
+        reason: String,
+    },
+    ReplayDiverged {
+        seq: u64,
+        reason: String,
+    },
+    UnbondingOverflow {
+        now: u64,
+        unbonding_period_secs: u64,
+    },
+    EventLogIncomplete {
+        expected_seq: u64,
+        found_seq: u64,
+    },
+}
+
+impl SpectreError {
+    /// Stable identifier for this error kind. Safe to persist, log and match on.
+    pub fn code(&self) -> &'static str {
+        match self {
+            SpectreError::AgentNotRegistered { .. } => "AGENT_NOT_REGISTERED",
+            SpectreError::AgentAlreadyRegistered { .. } => "AGENT_ALREADY_REGISTERED",
+            SpectreError::JobNotFound { .. } => "JOB_NOT_FOUND",
+            SpectreError::CapabilityMismatch { .. } => "CAPABILITY_MISMATCH",
+            SpectreError::JobAlreadyTaken { .. } => "JOB_ALREADY_TAKEN",
+            SpectreError::DuplicateJob { .. } => "DUPLICATE_JOB",
+            SpectreError::JobExpired { .. } => "JOB_EXPIRED",
+            SpectreError::InvalidJobTransition { .. } => "INVALID_JOB_TRANSITION",
+            SpectreError::NotJobAssignee { .. } => "NOT_JOB_ASSIGNEE",
+            SpectreError::JobNotDisputable { .. } => "JOB_NOT_DISPUTABLE",
+            SpectreError::JobAlreadyDisputed { .. } => "JOB_ALREADY_DISPUTED",
+            SpectreError::HashMismatch { .. } => "HASH_MISMATCH",
+            SpectreError::CapacityExceeded { .. } => "CAPACITY_EXCEEDED",
+            SpectreError::LowReputation { .. } => "LOW_REPUTATION",
+            SpectreError::InsufficientStake { .. } => "INSUFFICIENT_STAKE",
+            SpectreError::InvalidProof { .. } => "INVALID_PROOF",
+            SpectreError::StaleProof { .. } => "STALE_PROOF",
+            SpectreError::FutureProof { .. } => "FUTURE_PROOF",
+            SpectreError::ReplayedProof { .. } => "REPLAYED_PROOF",
+            SpectreError::UnknownModel { .. } => "UNKNOWN_MODEL",
+            SpectreError::ModelDeprecated { .. } => "MODEL_DEPRECATED",
+            SpectreError::InvalidCatalog { .. } => "INVALID_CATALOG",
+            SpectreError::InvalidRetryPolicy { .. } => "INVALID_RETRY_POLICY",
+            SpectreError::InvalidSimulatorConfig { .. } => "INVALID_SIMULATOR_CONFIG",
+            SpectreError::InsufficientFunds { .. } => "INSUFFICIENT_FUNDS",
+            SpectreError::EscrowNotFound { .. } => "ESCROW_NOT_FOUND",
+            SpectreError::LedgerInvariantViolated { .. } => "LEDGER_INVARIANT_VIOLATED",
+            SpectreError::SupplyOverflow { .. } => "SUPPLY_OVERFLOW",
+            SpectreError::NetworkFailure { .. } => "NETWORK_FAILURE",
+            SpectreError::RpcError { .. } => "RPC_ERROR",
+            SpectreError::TransactionConflict { .. } => "TRANSACTION_CONFLICT",
+            SpectreError::InvalidTransactionSignature { .. } => "INVALID_TRANSACTION_SIGNATURE",
+            SpectreError::NonceAlreadyUsed { .. } => "NONCE_ALREADY_USED",
+            SpectreError::ReplacementUnderpriced { .. } => "REPLACEMENT_UNDERPRICED",
+            SpectreError::ReplacementMismatch { .. } => "REPLACEMENT_MISMATCH",
+            SpectreError::MempoolFull { .. } => "MEMPOOL_FULL",
+            SpectreError::TransactionNotReplaceable { .. } => "TRANSACTION_NOT_REPLACEABLE",
+            SpectreError::TransactionDropped { .. } => "TRANSACTION_DROPPED",
+            SpectreError::SettlementTxFailed { .. } => "SETTLEMENT_TX_FAILED",
+            SpectreError::InvalidStateProof { .. } => "INVALID_STATE_PROOF",
+            SpectreError::StorageFailure { .. } => "STORAGE_FAILURE",
+            SpectreError::ReplayDiverged { .. } => "REPLAY_DIVERGED",
+            SpectreError::UnbondingOverflow { .. } => "UNBONDING_OVERFLOW",
+            SpectreError::EventLogIncomplete { .. } => "EVENT_LOG_INCOMPLETE",
+        }
+    }
+}
+
+impl fmt::Display for SpectreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpectreError::AgentNotRegistered { agent_id } => {
+                write!(f, "Agent {} not registered in SDKey system", agent_id)
+            }
+            SpectreError::AgentAlreadyRegistered { agent_id } => {
+                write!(f, "Agent {} is already registered in SDKey system", agent_id)
+            }
+            SpectreError::JobNotFound { job_id } => write!(f, "Job {} not found", job_id),
+            SpectreError::CapabilityMismatch { agent_id, required_model } => write!(
+                f,
+                "Agent {} capability verification failed: {} not supported",
+                agent_id, required_model
+            ),
+            SpectreError::JobAlreadyTaken { job_id } => write!(f, "Job {} already taken", job_id),
+            SpectreError::DuplicateJob { job_id } => write!(f, "Job {} already exists", job_id),
+            SpectreError::JobExpired { job_id, expired_at } => {
+                write!(f, "Job {} expired at {}", job_id, expired_at)
+            }
+            SpectreError::InvalidJobTransition { job_id, from, to } => {
+                write!(f, "Job {} cannot move from {:?} to {:?}", job_id, from, to)
+            }
+            SpectreError::NotJobAssignee { job_id, agent_id } => {
+                write!(f, "Agent {} is not assigned to job {}", agent_id, job_id)
+            }
+            SpectreError::JobNotDisputable { job_id, status } => {
+                write!(f, "Job {} can't be disputed while {:?}; only delivered results can", job_id, status)
+            }
+            SpectreError::JobAlreadyDisputed { job_id } => write!(f, "Job {} has already been disputed", job_id),
+            SpectreError::HashMismatch { agent_id, expected, actual } => write!(
+                f,
+                "Model hash mismatch for agent {} (expected {}, got {}) - potential model substitution attack",
+                agent_id, expected, actual
+            ),
+            SpectreError::CapacityExceeded { agent_id, requested, max_tokens } => write!(
+                f,
+                "Task of {} tokens exceeds agent {}'s verified capacity of {}",
+                requested, agent_id, max_tokens
+            ),
+            SpectreError::LowReputation { agent_id, score, threshold } => write!(
+                f,
+                "Agent {} reputation {:.2} below threshold {:.2}",
+                agent_id, score, threshold
+            ),
+            SpectreError::InsufficientStake { agent_id, staked, required } => write!(
+                f,
+                "Agent {} has {} staked, {} required for task verification",
+                agent_id, staked, required
+            ),
+            SpectreError::InvalidProof { agent_id, reason } => {
+                write!(f, "Invalid execution proof from agent {}: {}", agent_id, reason)
+            }
+            SpectreError::StaleProof { agent_id, timestamp, now, max_age_secs } => write!(
+                f,
+                "Proof from agent {} is stale: issued at {}, now {}, max age {}s",
+                agent_id, timestamp, now, max_age_secs
+            ),
+            SpectreError::FutureProof { agent_id, timestamp, now, max_skew_secs } => write!(
+                f,
+                "Proof from agent {} is future-dated: issued at {}, now {}, max skew {}s",
+                agent_id, timestamp, now, max_skew_secs
+            ),
+            SpectreError::ReplayedProof { agent_id, nonce } => {
+                write!(f, "Proof nonce {} from agent {} was already used", nonce, agent_id)
+            }
+            SpectreError::UnknownModel { model_type } => write!(f, "Unknown model type {}", model_type),
+            SpectreError::ModelDeprecated { model_type } => {
+                write!(f, "Model {} is deprecated and closed to new agents and jobs", model_type)
+            }
+            SpectreError::InvalidCatalog { reason } => write!(f, "Invalid model catalog: {}", reason),
+            SpectreError::InvalidRetryPolicy { reason } => write!(f, "Invalid retry policy: {}", reason),
+            SpectreError::InvalidSimulatorConfig { reason } => write!(f, "Invalid simulator config: {}", reason),
+            SpectreError::InsufficientFunds { account, balance, required } => write!(
+                f,
+                "Account {} has {} tokens, {} required",
+                account, balance, required
+            ),
+            SpectreError::EscrowNotFound { job_id } => write!(f, "No escrow held for job {}", job_id),
+            SpectreError::LedgerInvariantViolated { total_supply, accounted } => write!(
+                f,
+                "Ledger invariant violated: total supply {} but {} accounted for",
+                total_supply, accounted
+            ),
+            SpectreError::SupplyOverflow { total_supply, minted } => {
+                write!(f, "Minting {} tokens would overflow the total supply of {}", minted, total_supply)
+            }
+            SpectreError::NetworkFailure { endpoint, reason } => {
+                write!(f, "Psy network request to {} failed: {}", endpoint, reason)
+            }
+            SpectreError::RpcError { method, code, message, .. } => {
+                write!(f, "Psy node rejected {} (JSON-RPC error {}): {}", method, code, message)
+            }
+            SpectreError::TransactionConflict { tx_id } => {
+                write!(f, "Transaction id {} was already used for a different transaction", tx_id)
+            }
+            SpectreError::InvalidTransactionSignature { tx_id, reason } => {
+                write!(f, "Transaction {} has an invalid signature: {}", tx_id, reason)
+            }
+            SpectreError::NonceAlreadyUsed { account, nonce } => {
+                write!(f, "Nonce {} of account {} was already used", nonce, account)
+            }
+            SpectreError::ReplacementUnderpriced { tx_id, min_priority_fee } => {
+                write!(f, "Replacing pending transaction {} needs a priority fee of at least {}", tx_id, min_priority_fee)
+            }
+            SpectreError::ReplacementMismatch { tx_id, reason } => {
+                write!(f, "Transaction can't replace pending transaction {}: {}", tx_id, reason)
+            }
+            SpectreError::MempoolFull { capacity, min_priority_fee } => {
+                write!(f, "Mempool is full ({} transactions); a priority fee above {} is needed to enter", capacity, min_priority_fee)
+            }
+            SpectreError::TransactionNotReplaceable { tx_id, status: Some(status) } => {
+                write!(f, "Transaction {} can't be replaced while {:?}; only pending ones can", tx_id, status)
+            }
+            SpectreError::TransactionNotReplaceable { tx_id, status: None } => {
+                write!(f, "Transaction {} can't be replaced; it is unknown", tx_id)
+            }
+            SpectreError::TransactionDropped { tx_id, status } => {
+                write!(f, "Transaction {} left the mempool without being included ({:?}); submit it under a new id", tx_id, status)
+            }
+            SpectreError::SettlementTxFailed { job_id, tx_id, status } => {
+                write!(f, "Settlement transaction {} for job {} ended {:?}", tx_id, job_id, status)
+            }
+            SpectreError::InvalidStateProof { key, reason } => {
+                write!(f, "State proof for {} does not verify: {}", key, reason)
+            }
+            SpectreError::StorageFailure { path, reason } => {
+                write!(f, "Storage at {} failed: {}", path, reason)
+            }
+            SpectreError::ReplayDiverged { seq, reason } => {
+                write!(f, "Replaying log record {} did not reproduce it: {}", seq, reason)
+            }
+            SpectreError::UnbondingOverflow { now, unbonding_period_secs } => {
+                write!(f, "Unbonding period of {}s from {} runs past the end of time", unbonding_period_secs, now)
+            }
+            SpectreError::EventLogIncomplete { expected_seq, found_seq } => {
+                write!(f, "Event log skips from record {} to {}; rebuilding needs every record from the first", expected_seq, found_seq)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SpectreError {}
@@ -0,0 +1,198 @@
+// src/events.rs
+// Domain events: a sequenced, replayable record of every market and registry change
+
+use crate::error::SpectreResult;
+use crate::reputation::ReputationEvent;
+use crate::sdkey_verification::{AgentCapability, SDKeyProof};
+use crate::staking::SlashEvent;
+use crate::Job;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+// Records a subscriber can fall behind by before it starts missing some
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// Something that happened to a [`SpectreMarket`](crate::SpectreMarket) or
+/// its [`SDKeyRegistry`](crate::SDKeyRegistry).
+///
+/// Events are emitted once the change they describe has succeeded, and
+/// carry what is needed to apply that change again, so
+/// [`SpectreMarket::from_events`](crate::SpectreMarket::from_events) can
+/// rebuild state from the log alone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DomainEvent {
+    /// New tokens credited to an account
+    Minted { account: String, amount: u64 },
+    Transferred { from: String, to: String, amount: u64 },
+    /// Agent registered with `stake` bonded. `on_ledger` is set when a
+    /// market moved the stake out of the agent's balance, and clear when the
+    /// registry was called on its own and no tokens moved.
+    AgentRegistered { agent_id: String, capability: AgentCapability, stake: u64, on_ledger: bool },
+    /// More stake bonded; `on_ledger` as for `AgentRegistered`
+    StakeDeposited { agent_id: String, amount: u64, on_ledger: bool },
+    UnstakeRequested { agent_id: String, amount: u64, release_at: u64 },
+    /// Unbonded stake released; `on_ledger` when it was paid back to the
+    /// agent's balance
+    StakeWithdrawn { agent_id: String, amount: u64, on_ledger: bool },
+    /// A proof passed verification, using up its nonce
+    ProofAccepted { agent_id: String, nonce: u32, timestamp: u64 },
+    ReputationRecorded { event: ReputationEvent },
+    /// `on_ledger` when the slashed tokens were paid out of the stake vault
+    AgentSlashed { event: SlashEvent, on_ledger: bool },
+    /// Job posted with its reward locked in escrow
+    JobPosted { job: Job },
+    JobClaimed { job_id: u64, agent_id: String },
+    ResultSubmitted { job_id: u64, proof: SDKeyProof },
+    ResultVerified { job_id: u64, agent_id: String },
+    /// `reason` is the code of the error the proof was rejected with
+    ResultRejected { job_id: u64, agent_id: String, reason: String },
+    JobDisputed { job_id: u64, agent_id: String },
+    /// Escrow paid out to the agent, less the protocol fee
+    AgentRewarded { job_id: u64, agent_id: String, payout: u64, fee: u64 },
+    /// Escrow returned to the requester
+    RequesterRefunded { job_id: u64, requester: String, amount: u64 },
+    JobSettled { job_id: u64 },
+    JobCancelled { job_id: u64 },
+    JobExpired { job_id: u64 },
+    SettlementTxAttached { job_id: u64, tx_id: String },
+    /// The settlement transaction ended without confirming and was detached
+    SettlementTxFailed { job_id: u64, tx_id: String },
+}
+
+/// A [`DomainEvent`] with its place in the log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventRecord {
+    /// Position in the log, counting from 1 without gaps
+    pub seq: u64,
+    /// Market time the event happened at
+    pub at: u64,
+    pub event: DomainEvent,
+}
+
+/// Append-only log of [`DomainEvent`]s, shared by a market and its registry.
+///
+/// Clones share the same log. [`subscribe`](EventLog::subscribe) streams
+/// every record appended afterwards; [`since`](EventLog::since) fills in
+/// what came before. Every record is kept unless
+/// [`with_retention`](EventLog::with_retention) caps the log; a capped log
+/// that has dropped records can no longer rebuild state from scratch, and
+/// [`SpectreMarket::from_events`](crate::SpectreMarket::from_events) refuses it.
+#[derive(Clone)]
+pub struct EventLog {
+    state: Arc<Mutex<LogState>>,
+    updates: broadcast::Sender<EventRecord>,
+}
+
+struct LogState {
+    records: VecDeque<EventRecord>,
+    last_seq: u64,
+    retention: Option<usize>,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventLog {
+    pub fn new() -> Self {
+        Self::build(None)
+    }
+
+    /// Log that keeps only the most recent `records` records, for a process
+    /// that persists its state some other way (e.g. a [`PersistentMarket`](crate::PersistentMarket))
+    pub fn with_retention(records: usize) -> Self {
+        Self::build(Some(records))
+    }
+
+    fn build(retention: Option<usize>) -> Self {
+        EventLog {
+            state: Arc::new(Mutex::new(LogState { records: VecDeque::new(), last_seq: 0, retention })),
+            updates: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+        }
+    }
+
+    /// Sequence number of the last record appended, 0 if none
+    pub fn last_seq(&self) -> u64 {
+        self.state.lock().unwrap().last_seq
+    }
+
+    /// Records currently retained
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every retained record, oldest first
+    pub fn records(&self) -> Vec<EventRecord> {
+        self.since(0)
+    }
+
+    /// Retained records after `seq`, oldest first
+    pub fn since(&self, seq: u64) -> Vec<EventRecord> {
+        let state = self.state.lock().unwrap();
+        state.records.iter().filter(|record| record.seq > seq).cloned().collect()
+    }
+
+    /// Stream of records appended from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<EventRecord> {
+        self.updates.subscribe()
+    }
+
+    // Append `event`, returning its sequence number
+    pub(crate) fn emit(&self, at: u64, event: DomainEvent) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        self.append(&mut state, at, event)
+    }
+
+    // Make `change` and append the events it returns as one step, so no other
+    // record can land between a change and its own. `change` must not emit.
+    pub(crate) fn emit_with<T>(&self, at: u64, change: impl FnOnce() -> SpectreResult<(T, Vec<DomainEvent>)>) -> SpectreResult<T> {
+        let mut state = self.state.lock().unwrap();
+        let (value, events) = change()?;
+        for event in events {
+            self.append(&mut state, at, event);
+        }
+        Ok(value)
+    }
+
+    fn append(&self, state: &mut LogState, at: u64, event: DomainEvent) -> u64 {
+        let record = EventRecord { seq: state.last_seq + 1, at, event };
+        state.append(record.clone());
+        let _ = self.updates.send(record);
+        state.last_seq
+    }
+
+    // Append records replayed from another log, keeping their sequence numbers
+    pub(crate) fn import(&self, records: &[EventRecord]) {
+        let mut state = self.state.lock().unwrap();
+        for record in records {
+            state.append(record.clone());
+        }
+    }
+
+    // Continue numbering after `seq`, for state restored from a snapshot taken there
+    pub(crate) fn resume_after(&self, seq: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.last_seq = state.last_seq.max(seq);
+    }
+}
+
+impl LogState {
+    fn append(&mut self, record: EventRecord) {
+        self.last_seq = record.seq;
+        self.records.push_back(record);
+        if let Some(retention) = self.retention {
+            while self.records.len() > retention {
+                self.records.pop_front();
+            }
+        }
+    }
+}
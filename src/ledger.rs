@@ -0,0 +1,311 @@
+// src/ledger.rs
+// Token Ledger: balances, job escrow and protocol fees for SpectreMarket settlement
+
+use crate::error::{SpectreError, SpectreResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Account that collects protocol fees on every settled job.
+pub const TREASURY_ACCOUNT: &str = "spectre_treasury";
+
+/// Default protocol fee: 1% of every released escrow, in basis points.
+pub const DEFAULT_PROTOCOL_FEE_BPS: u64 = 100;
+
+/// Account holding every agent's bonded and unbonding stake.
+pub const STAKE_VAULT_ACCOUNT: &str = "spectre_stake_vault";
+
+/// Sink for burned (slashed) stake. Nothing is ever paid out of it, so
+/// burned tokens leave circulation without breaking supply conservation.
+pub const BURN_ACCOUNT: &str = "spectre_burn";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LedgerEntryKind {
+    Mint,
+    TransferIn,
+    TransferOut,
+    EscrowLock,
+    EscrowRelease,
+    EscrowRefund,
+    ProtocolFee,
+    StakeBond,
+    StakeUnbond,
+    SlashPayout,
+    SlashBurn,
+}
+
+/// A single balance movement on one account.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub seq: u64,
+    pub account: String,
+    pub kind: LedgerEntryKind,
+    pub amount: u64,
+    pub job_id: Option<u64>,
+    pub balance_after: u64,
+}
+
+/// Tokens a requester has locked against a job until it settles.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Escrow {
+    pub job_id: u64,
+    pub depositor: String,
+    pub amount: u64,
+}
+
+/// Outcome of releasing an escrow to the agent that did the work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Settlement {
+    pub payout: u64,
+    pub fee: u64,
+}
+
+/// Balance ledger backing job settlement.
+///
+/// Tokens only enter circulation through [`Ledger::mint`]; every other
+/// operation moves tokens between accounts and escrows, so
+/// `sum(balances) + sum(escrows) == total_supply` always holds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ledger {
+    balances: HashMap<String, u64>,
+    escrows: HashMap<u64, Escrow>,
+    history: Vec<LedgerEntry>,
+    fee_bps: u64,
+    total_supply: u64,
+}
+
+impl Default for Ledger {
+    fn default() -> Self {
+        Self::new(DEFAULT_PROTOCOL_FEE_BPS)
+    }
+}
+
+impl Ledger {
+    pub fn new(fee_bps: u64) -> Self {
+        Ledger {
+            balances: HashMap::new(),
+            escrows: HashMap::new(),
+            history: Vec::new(),
+            fee_bps: fee_bps.min(10_000),
+            total_supply: 0,
+        }
+    }
+
+    pub fn fee_bps(&self) -> u64 {
+        self.fee_bps
+    }
+
+    pub fn total_supply(&self) -> u64 {
+        self.total_supply
+    }
+
+    pub fn balance(&self, account: &str) -> u64 {
+        self.balances.get(account).copied().unwrap_or(0)
+    }
+
+    /// Every account with a balance entry, in no particular order
+    pub fn balances(&self) -> impl Iterator<Item = (&str, u64)> {
+        self.balances.iter().map(|(account, balance)| (account.as_str(), *balance))
+    }
+
+    pub fn escrow(&self, job_id: u64) -> Option<&Escrow> {
+        self.escrows.get(&job_id)
+    }
+
+    pub fn total_escrowed(&self) -> u64 {
+        self.escrows.values().map(|e| e.amount).sum()
+    }
+
+    /// All balance movements on `account`, oldest first.
+    pub fn history(&self, account: &str) -> Vec<&LedgerEntry> {
+        self.history.iter().filter(|e| e.account == account).collect()
+    }
+
+    /// Issue new tokens to an account (genesis allocation / faucet).
+    /// This is the only operation that changes total supply, and it refuses
+    /// to push the supply past `u64::MAX`, so no balance can overflow either.
+    pub fn mint(&mut self, account: &str, amount: u64) -> SpectreResult<()> {
+        self.total_supply = self.total_supply.checked_add(amount)
+            .ok_or(SpectreError::SupplyOverflow { total_supply: self.total_supply, minted: amount })?;
+        self.credit(account, amount, LedgerEntryKind::Mint, None);
+        Ok(())
+    }
+
+    pub fn transfer(&mut self, from: &str, to: &str, amount: u64) -> SpectreResult<()> {
+        self.debit(from, amount, LedgerEntryKind::TransferOut, None)?;
+        self.credit(to, amount, LedgerEntryKind::TransferIn, None);
+        Ok(())
+    }
+
+    /// Move `amount` from the depositor's balance into escrow for `job_id`.
+    pub fn lock_escrow(&mut self, job_id: u64, depositor: &str, amount: u64) -> SpectreResult<()> {
+        if self.escrows.contains_key(&job_id) {
+            return Err(SpectreError::DuplicateJob { job_id });
+        }
+        self.debit(depositor, amount, LedgerEntryKind::EscrowLock, Some(job_id))?;
+        self.escrows.insert(job_id, Escrow {
+            job_id,
+            depositor: depositor.to_string(),
+            amount,
+        });
+        Ok(())
+    }
+
+    /// Pay out a job's escrow to `recipient`, minus the protocol fee.
+    pub fn release_escrow(&mut self, job_id: u64, recipient: &str) -> SpectreResult<Settlement> {
+        let escrow = self.escrows.remove(&job_id)
+            .ok_or(SpectreError::EscrowNotFound { job_id })?;
+
+        let fee = protocol_fee(escrow.amount, self.fee_bps);
+        let payout = escrow.amount - fee;
+        self.credit(recipient, payout, LedgerEntryKind::EscrowRelease, Some(job_id));
+        if fee > 0 {
+            self.credit(TREASURY_ACCOUNT, fee, LedgerEntryKind::ProtocolFee, Some(job_id));
+        }
+        Ok(Settlement { payout, fee })
+    }
+
+    /// Return a job's escrow in full to the original depositor.
+    pub fn refund_escrow(&mut self, job_id: u64) -> SpectreResult<u64> {
+        let escrow = self.escrows.remove(&job_id)
+            .ok_or(SpectreError::EscrowNotFound { job_id })?;
+        self.credit(&escrow.depositor, escrow.amount, LedgerEntryKind::EscrowRefund, Some(job_id));
+        Ok(escrow.amount)
+    }
+
+    /// Move `amount` from an agent's balance into the stake vault.
+    pub fn bond_stake(&mut self, agent_id: &str, amount: u64) -> SpectreResult<()> {
+        self.debit(agent_id, amount, LedgerEntryKind::StakeBond, None)?;
+        self.credit(STAKE_VAULT_ACCOUNT, amount, LedgerEntryKind::StakeBond, None);
+        Ok(())
+    }
+
+    /// Return unbonded stake from the vault to the agent.
+    pub fn unbond_stake(&mut self, agent_id: &str, amount: u64) -> SpectreResult<()> {
+        self.debit(STAKE_VAULT_ACCOUNT, amount, LedgerEntryKind::StakeUnbond, None)?;
+        self.credit(agent_id, amount, LedgerEntryKind::StakeUnbond, None);
+        Ok(())
+    }
+
+    /// Pay out slashed stake: `to_requester` compensates the harmed requester,
+    /// `burned` goes to [`BURN_ACCOUNT`].
+    pub fn apply_slash(&mut self, job_id: Option<u64>, requester: Option<&str>, to_requester: u64, burned: u64) -> SpectreResult<()> {
+        let slashed = to_requester.checked_add(burned).ok_or_else(|| SpectreError::InsufficientFunds {
+            account: STAKE_VAULT_ACCOUNT.to_string(),
+            balance: self.balance(STAKE_VAULT_ACCOUNT),
+            required: u64::MAX,
+        })?;
+        self.debit(STAKE_VAULT_ACCOUNT, slashed, LedgerEntryKind::SlashBurn, job_id)?;
+        let burned = match requester {
+            Some(requester) if to_requester > 0 => {
+                self.credit(requester, to_requester, LedgerEntryKind::SlashPayout, job_id);
+                burned
+            }
+            _ => burned + to_requester,
+        };
+        if burned > 0 {
+            self.credit(BURN_ACCOUNT, burned, LedgerEntryKind::SlashBurn, job_id);
+        }
+        Ok(())
+    }
+
+    /// Check that no tokens were created or destroyed outside of minting.
+    pub fn check_invariants(&self) -> SpectreResult<()> {
+        let accounted = self.balances.values().sum::<u64>() + self.total_escrowed();
+        if accounted != self.total_supply {
+            return Err(SpectreError::LedgerInvariantViolated {
+                total_supply: self.total_supply,
+                accounted,
+            });
+        }
+        Ok(())
+    }
+
+    fn credit(&mut self, account: &str, amount: u64, kind: LedgerEntryKind, job_id: Option<u64>) {
+        let balance = self.balances.entry(account.to_string()).or_insert(0);
+        *balance += amount;
+        let balance_after = *balance;
+        self.record(account, kind, amount, job_id, balance_after);
+    }
+
+    fn debit(&mut self, account: &str, amount: u64, kind: LedgerEntryKind, job_id: Option<u64>) -> SpectreResult<()> {
+        let balance = self.balance(account);
+        if balance < amount {
+            return Err(SpectreError::InsufficientFunds {
+                account: account.to_string(),
+                balance,
+                required: amount,
+            });
+        }
+        let balance_after = balance - amount;
+        self.balances.insert(account.to_string(), balance_after);
+        self.record(account, kind, amount, job_id, balance_after);
+        Ok(())
+    }
+
+    fn record(&mut self, account: &str, kind: LedgerEntryKind, amount: u64, job_id: Option<u64>, balance_after: u64) {
+        self.history.push(LedgerEntry {
+            seq: self.history.len() as u64,
+            account: account.to_string(),
+            kind,
+            amount,
+            job_id,
+            balance_after,
+        });
+    }
+}
+
+// `fee_bps` of `amount`, computed wide so large escrows can't overflow
+pub(crate) fn protocol_fee(amount: u64, fee_bps: u64) -> u64 {
+    (amount as u128 * fee_bps.min(10_000) as u128 / 10_000) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escrow_release_conserves_supply() {
+        let mut ledger = Ledger::new(250);
+        ledger.mint("requester", 1_000).unwrap();
+        ledger.lock_escrow(7, "requester", 400).unwrap();
+        assert_eq!(ledger.balance("requester"), 600);
+        ledger.check_invariants().unwrap();
+
+        let settlement = ledger.release_escrow(7, "agent").unwrap();
+        assert_eq!(settlement, Settlement { payout: 390, fee: 10 });
+        assert_eq!(ledger.balance("agent"), 390);
+        assert_eq!(ledger.balance(TREASURY_ACCOUNT), 10);
+        assert_eq!(ledger.total_supply(), 1_000);
+        ledger.check_invariants().unwrap();
+    }
+
+    #[test]
+    fn test_refund_and_insufficient_funds() {
+        let mut ledger = Ledger::default();
+        ledger.mint("requester", 100).unwrap();
+        let err = ledger.lock_escrow(1, "requester", 500).unwrap_err();
+        assert_eq!(err.code(), "INSUFFICIENT_FUNDS");
+
+        ledger.lock_escrow(1, "requester", 100).unwrap();
+        assert_eq!(ledger.refund_escrow(1).unwrap(), 100);
+        assert_eq!(ledger.balance("requester"), 100);
+        assert!(ledger.refund_escrow(1).is_err());
+        assert_eq!(ledger.history("requester").len(), 3);
+        ledger.check_invariants().unwrap();
+    }
+
+    #[test]
+    fn test_large_amounts_do_not_overflow() {
+        let mut ledger = Ledger::new(250);
+        ledger.mint("requester", u64::MAX).unwrap();
+        let err = ledger.mint("requester", 1).unwrap_err();
+        assert_eq!(err.code(), "SUPPLY_OVERFLOW");
+        assert_eq!(ledger.total_supply(), u64::MAX);
+
+        ledger.lock_escrow(1, "requester", u64::MAX).unwrap();
+        let settlement = ledger.release_escrow(1, "agent").unwrap();
+        assert_eq!(settlement.fee, (u64::MAX as u128 * 250 / 10_000) as u64);
+        assert_eq!(settlement.payout + settlement.fee, u64::MAX);
+        ledger.check_invariants().unwrap();
+    }
+}
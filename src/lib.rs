@@ -16,80 +16,376 @@
 //! #[tokio::main]
 //! async fn main() {
 //!     let mut registry = SDKeyRegistry::new();
-//!     registry.register_agent("agent_001".to_string(), "LLaMA-3-70B".to_string(), 5000).unwrap();
+//!     let agent_key = generate_agent_keypair();
+//!     registry.register_agent(
+//!         "agent_001".to_string(),
+//!         "LLaMA-3-70B".to_string(),
+//!         5000,
+//!         agent_key.verifying_key(),
+//!     ).unwrap();
+//! 
+//!     let proof = ProofBuilder::new(&agent_key, "agent_001".to_string(), "LLaMA-3-70B")
+//!         .unwrap()
+//!         .job(1)
+//!         .result(b"inference output")
+//!         .build();
+//!     registry.verify_sdkey_proof(&proof, 4000).unwrap();
 //! }
 //! ```
 
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 
 // Enhanced functionality modules
+pub mod clock;
+pub mod error;
+pub mod events;
+pub mod ledger;
+pub mod lifecycle;
+pub mod market_handle;
+pub mod merkle;
+pub mod model_catalog;
+pub mod parth;
+pub mod proof_verifier;
+pub mod reputation;
+pub mod psy_accounts;
+pub mod psy_batch;
+pub mod psy_fees;
 pub mod psy_integration;
+pub mod psy_retry;
+pub mod psy_rpc;
+pub mod psy_simulator;
+pub mod psy_stats;
 pub mod sdkey_verification;
+pub mod staking;
+pub mod storage;
 pub mod market_validation;
 
+pub use clock::*;
+pub use error::*;
+pub use events::*;
+pub use ledger::*;
+pub use lifecycle::*;
+pub use market_handle::*;
+pub use merkle::*;
+pub use model_catalog::*;
+pub use parth::*;
+pub use proof_verifier::*;
+pub use reputation::*;
+pub use psy_accounts::*;
+pub use psy_batch::*;
+pub use psy_fees::*;
 pub use psy_integration::*;
+pub use psy_retry::*;
+pub use psy_rpc::*;
+pub use psy_simulator::*;
+pub use psy_stats::*;
 pub use sdkey_verification::*;
+pub use staking::*;
+pub use storage::*;
 pub use market_validation::*;
 
-/// Software Defined Key (SDKey) - Agent Identity System
-/// 
-/// Represents a cryptographic proof of an agent's capabilities.
-/// In production, this would be a ZK circuit on Psy Protocol.
+/// Job Contract - Unit of Work for Agents
 /// 
-/// # Fields
-/// - `agent_id`: Unique identifier for the agent
-/// - `reputation_score`: 0-100 score based on past performance
-/// - `verified_algorithms`: List of AI models this agent can run
-#[derive(Debug, Clone, PartialEq)]
-pub struct SDKey {
-    pub agent_id: String,
-    pub reputation_score: u32,
-    pub verified_algorithms: Vec<String>,
+/// Represents a task that needs to be executed by an agent.
+/// Could be inference, data scraping, arbitrage, etc.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: u64,
+    pub requester: String,
+    pub required_algo: String,
+    pub reward_tokens: u64,
+    /// Tokens of work the job needs; checked against the agent's model capacity
+    pub task_complexity: u32,
+    pub status: JobStatus,
+    /// Agent that claimed the job, once it leaves `Open`
+    pub assigned_agent: Option<String>,
+    /// Proof submitted alongside the agent's result
+    pub result_proof: Option<SDKeyProof>,
+    /// Unix timestamp (seconds) after which the job can no longer be taken
+    pub expires_at: Option<u64>,
+    pub created_at: u64,
+    /// Every state change the job has gone through, oldest first
+    pub transitions: Vec<JobTransition>,
+    /// Psy transaction whose confirmation settles the job, if one is attached
+    pub settlement_tx: Option<String>,
+    /// Whether the requester has disputed the delivered result
+    #[serde(default)]
+    pub disputed: bool,
 }
 
-impl SDKey {
-    /// Verify if agent has capability to execute a specific algorithm
-    /// 
-    /// # Arguments
-    /// - `required_algo`: The algorithm/model required for the job
-    /// 
-    /// # Returns
-    /// `true` if agent has the capability and sufficient reputation
-    pub fn verify_capability(&self, required_algo: &str) -> bool {
-        self.verified_algorithms.contains(&required_algo.to_string()) 
-            && self.reputation_score > 50
+impl Job {
+    // A freshly posted job on `request`'s terms
+    fn from_request(request: JobRequest, now: u64) -> Self {
+        Job {
+            id: request.id,
+            requester: request.requester,
+            required_algo: request.required_algo,
+            reward_tokens: request.reward_tokens,
+            task_complexity: request.task_complexity,
+            status: JobStatus::Open,
+            assigned_agent: None,
+            result_proof: None,
+            expires_at: request.expires_at,
+            created_at: now,
+            transitions: Vec::new(),
+            settlement_tx: None,
+            disputed: false,
+        }
+    }
+
+    /// When the job entered `status`, if it ever did
+    pub fn entered_at(&self, status: JobStatus) -> Option<u64> {
+        if status == JobStatus::Open {
+            return Some(self.created_at);
+        }
+        self.transitions.iter().find(|t| t.to == status).map(|t| t.at)
+    }
+
+    fn is_expired_at(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|at| at <= now)
+    }
+
+    // Move to `to` if the lifecycle allows it, recording when it happened
+    fn transition(&mut self, to: JobStatus, at: u64) -> SpectreResult<()> {
+        self.ensure_transition(to)?;
+        self.transitions.push(JobTransition { from: self.status, to, at });
+        self.status = to;
+        Ok(())
+    }
+
+    // Check the job may move to `to`, before doing work that depends on it
+    fn ensure_transition(&self, to: JobStatus) -> SpectreResult<()> {
+        if !self.status.can_transition_to(to) {
+            return Err(SpectreError::InvalidJobTransition {
+                job_id: self.id,
+                from: self.status,
+                to,
+            });
+        }
+        Ok(())
+    }
+
+    // Check the job can still be claimed at `now`
+    fn ensure_claimable(&self, now: u64) -> SpectreResult<()> {
+        match self.status {
+            JobStatus::Open if self.is_expired_at(now) => Err(SpectreError::JobExpired {
+                job_id: self.id,
+                expired_at: self.expires_at.unwrap_or(now),
+            }),
+            JobStatus::Open => Ok(()),
+            JobStatus::Cancelled | JobStatus::Expired => Err(SpectreError::InvalidJobTransition {
+                job_id: self.id,
+                from: self.status,
+                to: JobStatus::Claimed,
+            }),
+            _ => Err(SpectreError::JobAlreadyTaken { job_id: self.id }),
+        }
+    }
+
+    // Assign the job to an eligible agent, locking part of its stake
+    // against the reward (Open → Claimed)
+    fn claim(&mut self, registry: &mut SDKeyRegistry, agent_id: &str, now: u64) -> SpectreResult<()> {
+        registry.check_eligibility(agent_id, &self.required_algo, self.task_complexity)?;
+        self.ensure_claimable(now)?;
+        registry.stakes.lock_for_job(agent_id, self.id, self.reward_tokens)?;
+        self.transition(JobStatus::Claimed, now)?;
+        self.assigned_agent = Some(agent_id.to_string());
+        Ok(())
+    }
+
+    // Check the submitted proof (ResultSubmitted → Verified/Rejected). A
+    // failure only counts against the agent, on its reputation and with the
+    // returned offense to slash, when the agent signed the proof for this
+    // job; the caller slashes it with slash_agent_stake against its ledger.
+    fn verify_submission(&mut self, registry: &mut SDKeyRegistry, now: u64) -> (SpectreResult<()>, Option<SlashOffense>) {
+        let proof = match (self.status, &self.result_proof) {
+            (JobStatus::ResultSubmitted, Some(proof)) => proof,
+            _ => {
+                let error = SpectreError::InvalidJobTransition { job_id: self.id, from: self.status, to: JobStatus::Verified };
+                return (Err(error), None);
+            }
+        };
+
+        let attributable = ensure_proof_for_job(proof, self.id)
+            .and_then(|_| registry.authenticate(proof))
+            .is_ok();
+        let verdict = ensure_proof_for_job(proof, self.id)
+            .and_then(|_| registry.verify_evidence(proof, &self.required_algo, self.task_complexity));
+        let agent_id = proof.agent_id.clone();
+        match verdict {
+            Ok(_) => {
+                if let Err(e) = self.transition(JobStatus::Verified, now) {
+                    return (Err(e), None);
+                }
+                registry.events().emit(now, DomainEvent::ResultVerified { job_id: self.id, agent_id: agent_id.clone() });
+                let latency_ms = self.entered_at(JobStatus::Claimed).map(|at| now.saturating_sub(at) * 1000);
+                registry.record_outcome(&agent_id, &self.required_algo, ReputationOutcome::Success, latency_ms);
+                (Ok(()), None)
+            }
+            Err(e) => {
+                if let Err(e) = self.transition(JobStatus::Rejected, now) {
+                    return (Err(e), None);
+                }
+                registry.events().emit(now, DomainEvent::ResultRejected {
+                    job_id: self.id,
+                    agent_id: agent_id.clone(),
+                    reason: e.code().to_string(),
+                });
+                if attributable {
+                    registry.record_outcome(&agent_id, &self.required_algo, ReputationOutcome::Failure, None);
+                }
+                let offense = SlashOffense::from_error(&e).filter(|_| attributable);
+                (Err(e), offense)
+            }
+        }
+    }
+
+    // Reject anyone but the agent that claimed the job
+    fn ensure_assignee(&self, agent_id: &str) -> SpectreResult<()> {
+        if self.assigned_agent.as_deref() != Some(agent_id) {
+            return Err(SpectreError::NotJobAssignee {
+                job_id: self.id,
+                agent_id: agent_id.to_string(),
+            });
+        }
+        Ok(())
     }
 }
 
-/// Job Contract - Unit of Work for Agents
-/// 
-/// Represents a task that needs to be executed by an agent.
-/// Could be inference, data scraping, arbitrage, etc.
-#[derive(Debug, Clone)]
-pub struct Job {
+/// Terms for a new job, passed to [`SpectreMarket::post_job_request`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRequest {
     pub id: u64,
     pub requester: String,
     pub required_algo: String,
     pub reward_tokens: u64,
-    pub is_fulfilled: bool,
+    pub task_complexity: u32,
+    pub expires_at: Option<u64>,
+}
+
+impl JobRequest {
+    pub fn new(id: u64, requester: String, algo: String, reward: u64) -> Self {
+        JobRequest {
+            id,
+            requester,
+            required_algo: algo,
+            reward_tokens: reward,
+            task_complexity: 0,
+            expires_at: None,
+        }
+    }
+
+    /// Require agents to have capacity for `tokens` of work
+    pub fn with_complexity(mut self, tokens: u32) -> Self {
+        self.task_complexity = tokens;
+        self
+    }
+
+    /// Refund the requester if the job isn't completed by `expires_at` (Unix seconds)
+    pub fn expires_at(mut self, expires_at: u64) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
 }
 
 /// Spectre Market - Core State Management
 /// 
-/// Manages jobs and agent registry with parallel execution support.
-/// State is split by Job ID to leverage Psy's PARTH architecture.
-/// This ensures Agent A's transactions never block Agent B's.
+/// Manages jobs and agent registry for a single owner (`&mut self`). For
+/// state split by job and agent id in the style of Psy's PARTH architecture,
+/// so Agent A's claims never block Agent B's, use [`ParthMarket`]; to share
+/// one market across tokio tasks, clone a [`MarketHandle`].
+///
+/// Job rewards are held in escrow on the market's [`Ledger`] from the moment
+/// a job is posted until it is paid out or refunded. Jobs move through the
+/// [`JobStatus`] lifecycle: an agent claims a job, submits a result with an
+/// [`SDKeyProof`], the proof is verified, and only then is the job settled.
+///
+/// Agent identity lives in the [`SDKeyRegistry`]: jobs are matched against
+/// each agent's registered models, capacity, stake and reputation.
+///
+/// Agent stake is real collateral: it is bonded from the agent's ledger
+/// balance into [`STAKE_VAULT_ACCOUNT`], partly locked while the agent works
+/// a job, and slashed (burned or paid to the requester) when the agent
+/// submits a bad proof or misses a deadline.
+///
+/// Every change made through the market's methods is recorded as a
+/// [`DomainEvent`] on the registry's [`EventLog`], from which
+/// [`from_events`](SpectreMarket::from_events) rebuilds the market. Writes
+/// straight to the public fields are not recorded.
 pub struct SpectreMarket {
     pub jobs: HashMap<u64, Job>,
-    pub agent_registry: HashMap<String, SDKey>,
+    pub registry: SDKeyRegistry,
+    pub ledger: Ledger,
+}
+
+impl Default for SpectreMarket {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SpectreMarket {
     pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// Create a market whose job timestamps and proof checks read time from `clock`
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self::with_registry(SDKeyRegistry::with_clock(clock))
+    }
+
+    /// Create a market on top of a pre-configured registry (verifiers, clock, freshness window)
+    pub fn with_registry(registry: SDKeyRegistry) -> Self {
         SpectreMarket {
             jobs: HashMap::new(),
-            agent_registry: HashMap::new(),
+            registry,
+            ledger: Ledger::default(),
+        }
+    }
+
+    /// Rebuild a market by replaying `records` on top of a freshly built `registry`
+    ///
+    /// The registry supplies configuration (catalog, clock, policies) and
+    /// should hold no state of its own. Its event log takes over `records`
+    /// and continues numbering after them. `records` must be the whole log,
+    /// numbered from 1 without gaps: a log that has dropped records (one
+    /// built [`with_retention`](EventLog::with_retention)) is refused with
+    /// [`SpectreError::EventLogIncomplete`] rather than rebuilt wrong.
+    pub fn from_events(registry: SDKeyRegistry, records: &[EventRecord]) -> SpectreResult<Self> {
+        let mut market = Self::with_registry(registry);
+        for (expected_seq, record) in (1..).zip(records) {
+            if record.seq != expected_seq {
+                return Err(SpectreError::EventLogIncomplete { expected_seq, found_seq: record.seq });
+            }
+            market.apply_event(record)?;
         }
+        market.registry.events().import(records);
+        Ok(market)
+    }
+
+    /// Domain events for every change made through the market and its registry
+    pub fn events(&self) -> &EventLog {
+        self.registry.events()
+    }
+
+    /// Credit new tokens to `account`
+    pub fn mint(&mut self, account: &str, amount: u64) -> SpectreResult<()> {
+        self.ledger.mint(account, amount)?;
+        self.registry.events().emit(self.registry.now(), DomainEvent::Minted { account: account.to_string(), amount });
+        Ok(())
+    }
+
+    /// Move tokens between two accounts' balances
+    pub fn transfer(&mut self, from: &str, to: &str, amount: u64) -> SpectreResult<()> {
+        self.ledger.transfer(from, to, amount)?;
+        self.registry.events().emit(self.registry.now(), DomainEvent::Transferred {
+            from: from.to_string(),
+            to: to.to_string(),
+            amount,
+        });
+        Ok(())
     }
 
     /// Register a new AI agent with verified capabilities
@@ -97,69 +393,455 @@ impl SpectreMarket {
     /// # Arguments
     /// - `agent_id`: Unique identifier for the agent
     /// - `algos`: List of algorithms/models the agent can execute
-    pub fn register_agent(&mut self, agent_id: String, algos: Vec<String>) {
-        let new_identity = SDKey {
-            agent_id: agent_id.clone(),
-            reputation_score: 100, // Start with perfect score
-            verified_algorithms: algos,
-        };
-        self.agent_registry.insert(agent_id, new_identity);
+    /// - `stake`: Tokens bonded from the agent's balance as collateral
+    /// - `public_key`: Ed25519 key the agent signs its SDKey proofs with
+    pub fn register_agent(&mut self, agent_id: String, algos: Vec<String>, stake: u64, public_key: VerifyingKey) -> SpectreResult<()> {
+        // Bonding first would lock up a second stake the registry then refuses
+        if self.registry.verified_agents.contains_key(&agent_id) {
+            return Err(SpectreError::AgentAlreadyRegistered { agent_id });
+        }
+        self.ledger.bond_stake(&agent_id, stake)?;
+        let registered = self.registry.enroll(agent_id.clone(), algos, stake, public_key, true);
+        if registered.is_err() {
+            self.ledger.unbond_stake(&agent_id, stake)?;
+        }
+        registered
+    }
+
+    /// Bond more of a registered agent's balance as stake (top-up)
+    pub fn deposit_stake(&mut self, agent_id: &str, amount: u64) -> SpectreResult<()> {
+        if !self.registry.verified_agents.contains_key(agent_id) {
+            return Err(SpectreError::AgentNotRegistered { agent_id: agent_id.to_string() });
+        }
+        self.ledger.bond_stake(agent_id, amount)?;
+        self.registry.add_stake(agent_id, amount, true)
+    }
+
+    /// Start unbonding free (unlocked) stake
+    ///
+    /// # Returns
+    /// The time at which the stake can be withdrawn; until then it can still be slashed
+    pub fn request_unstake(&mut self, agent_id: &str, amount: u64) -> SpectreResult<u64> {
+        self.registry.request_unstake(agent_id, amount)
+    }
+
+    /// Pay out every unbonding entry whose period has elapsed back to the agent
+    ///
+    /// # Returns
+    /// The number of tokens returned to the agent's balance
+    pub fn withdraw_unstaked(&mut self, agent_id: &str) -> SpectreResult<u64> {
+        // Pay first, so a ledger that can't leaves the stake still claimable
+        let now = self.registry.now();
+        self.ledger.unbond_stake(agent_id, self.registry.stakes.claimable(agent_id, now))?;
+        Ok(self.registry.release_unstaked(agent_id, now, true))
     }
 
     /// Post a new job to the marketplace
     /// 
+    /// The reward is locked into escrow from the requester's balance.
+    /// 
     /// # Arguments
     /// - `id`: Unique job identifier
     /// - `requester`: Address of the job poster
     /// - `algo`: Required algorithm/model
     /// - `reward`: Payment in tokens
-    pub fn post_job(&mut self, id: u64, requester: String, algo: String, reward: u64) {
-        let job = Job {
-            id,
-            requester,
-            required_algo: algo,
-            reward_tokens: reward,
-            is_fulfilled: false,
+    pub fn post_job(&mut self, id: u64, requester: String, algo: String, reward: u64) -> SpectreResult<()> {
+        self.post_job_request(JobRequest::new(id, requester, algo, reward))
+    }
+
+    /// Post a new job with full terms (capacity requirement, expiry)
+    pub fn post_job_request(&mut self, request: JobRequest) -> SpectreResult<()> {
+        let id = request.id;
+        let now = self.registry.now();
+        if self.jobs.contains_key(&id) {
+            return Err(SpectreError::DuplicateJob { job_id: id });
+        }
+        self.registry.catalog.available(&request.required_algo, now)?;
+        self.ledger.lock_escrow(id, &request.requester, request.reward_tokens)?;
+        let job = Job::from_request(request, now);
+        self.jobs.insert(id, job.clone());
+        self.registry.events().emit(now, DomainEvent::JobPosted { job });
+        Ok(())
+    }
+
+    /// Claim an open job for an agent (Open → Claimed)
+    /// 
+    /// The agent must hold the job's required model with enough capacity,
+    /// stake and reputation, and enough free stake to lock against the job's
+    /// reward until it is settled.
+    pub fn claim_job(&mut self, job_id: u64, agent_id: &str) -> SpectreResult<()> {
+        let now = self.registry.now();
+        let job = self.jobs.get_mut(&job_id)
+            .ok_or(SpectreError::JobNotFound { job_id })?;
+        job.claim(&mut self.registry, agent_id, now)?;
+        self.registry.events().emit(now, DomainEvent::JobClaimed { job_id, agent_id: agent_id.to_string() });
+        Ok(())
+    }
+
+    /// Submit the claiming agent's result with its SDKey proof (Claimed → ResultSubmitted)
+    ///
+    /// The proof must be signed by the agent for this job, so that whatever
+    /// verification later finds wrong with it is the agent's own doing. It
+    /// must also be fresh and unused now: [`verify_result`](Self::verify_result)
+    /// only judges the evidence, so a requester who verifies late can't turn
+    /// an honest result stale.
+    pub fn submit_result(&mut self, job_id: u64, proof: SDKeyProof) -> SpectreResult<()> {
+        let now = self.registry.now();
+        let job = self.jobs.get_mut(&job_id)
+            .ok_or(SpectreError::JobNotFound { job_id })?;
+        job.ensure_assignee(&proof.agent_id)?;
+        ensure_proof_for_job(&proof, job_id)?;
+        job.ensure_transition(JobStatus::ResultSubmitted)?;
+        self.registry.admit_proof(&proof)?;
+
+        job.transition(JobStatus::ResultSubmitted, now)?;
+        job.result_proof = Some(proof.clone());
+        self.registry.events().emit(now, DomainEvent::ResultSubmitted { job_id, proof });
+        Ok(())
+    }
+
+    /// Check the submitted proof against the SDKey registry (ResultSubmitted → Verified/Rejected)
+    /// 
+    /// A hash mismatch or invalid proof slashes the agent's stake according
+    /// to its [`SlashPolicy`], compensating the requester.
+    /// 
+    /// # Returns
+    /// - `Ok(())`: the job is now `Verified` and can be paid
+    /// - `Err(SpectreError)`: the job is now `Rejected` (or was not awaiting verification)
+    pub fn verify_result(&mut self, job_id: u64) -> SpectreResult<()> {
+        let now = self.registry.now();
+        let job = self.jobs.get_mut(&job_id)
+            .ok_or(SpectreError::JobNotFound { job_id })?;
+        let (verdict, offense) = job.verify_submission(&mut self.registry, now);
+        if let (Some(offense), Some(proof)) = (offense, &job.result_proof) {
+            let ledger = &mut self.ledger;
+            slash_agent_stake(&mut self.registry, &proof.agent_id, offense, job_id, &job.requester, |to_requester, burned| {
+                ledger.apply_slash(Some(job_id), Some(&job.requester), to_requester, burned)
+            })?;
+        }
+        verdict
+    }
+
+    /// Resolve a verified or rejected job's escrow (→ Settled)
+    /// 
+    /// Verified jobs pay the agent; rejected jobs refund the requester.
+    pub fn settle_job(&mut self, job_id: u64) -> SpectreResult<JobSettlement> {
+        let now = self.registry.now();
+        let job = self.jobs.get_mut(&job_id)
+            .ok_or(SpectreError::JobNotFound { job_id })?;
+        if !job.status.can_transition_to(JobStatus::Settled) {
+            return Err(SpectreError::InvalidJobTransition {
+                job_id,
+                from: job.status,
+                to: JobStatus::Settled,
+            });
+        }
+
+        if let Some(agent_id) = &job.assigned_agent {
+            self.registry.stakes.release_job(agent_id, job_id);
+        }
+        let (settlement, event) = match (job.status, &job.assigned_agent) {
+            (JobStatus::Verified, Some(agent_id)) => {
+                let paid = self.ledger.release_escrow(job_id, agent_id)?;
+                (
+                    JobSettlement::Paid { agent_id: agent_id.clone(), payout: paid.payout, fee: paid.fee },
+                    DomainEvent::AgentRewarded { job_id, agent_id: agent_id.clone(), payout: paid.payout, fee: paid.fee },
+                )
+            }
+            _ => {
+                let amount = self.ledger.refund_escrow(job_id)?;
+                (
+                    JobSettlement::Refunded { requester: job.requester.clone(), amount },
+                    DomainEvent::RequesterRefunded { job_id, requester: job.requester.clone(), amount },
+                )
+            }
+        };
+        job.transition(JobStatus::Settled, now)?;
+        let events = self.registry.events();
+        events.emit(now, event);
+        events.emit(now, DomainEvent::JobSettled { job_id });
+        Ok(settlement)
+    }
+
+    /// Settle a verified or rejected job once Psy confirms `tx_id`
+    /// 
+    /// Feed the chain's status updates (e.g. [`PsyChain::subscribe_all`]) to
+    /// [`apply_tx_update`](SpectreMarket::apply_tx_update) to act on them.
+    pub fn settle_on_confirmation(&mut self, job_id: u64, tx_id: String) -> SpectreResult<()> {
+        let job = self.jobs.get_mut(&job_id)
+            .ok_or(SpectreError::JobNotFound { job_id })?;
+        if !job.status.can_transition_to(JobStatus::Settled) {
+            return Err(SpectreError::InvalidJobTransition {
+                job_id,
+                from: job.status,
+                to: JobStatus::Settled,
+            });
+        }
+        job.settlement_tx = Some(tx_id.clone());
+        self.registry.events().emit(self.registry.now(), DomainEvent::SettlementTxAttached { job_id, tx_id });
+        Ok(())
+    }
+
+    /// React to a Psy transaction status change
+    /// 
+    /// When the settlement transaction of a job confirms, the job is settled.
+    /// When it ends any other way the job stays unsettled, its transaction is
+    /// detached and [`SpectreError::SettlementTxFailed`] is returned so the
+    /// caller can attach a new one.
+    /// 
+    /// # Returns
+    /// `None` unless `update` finalizes some job's settlement transaction
+    pub fn apply_tx_update(&mut self, update: &PsyTxUpdate) -> Option<(u64, SpectreResult<JobSettlement>)> {
+        if !update.status.is_terminal() {
+            return None;
+        }
+        let job = self.jobs.values_mut().find(|job| {
+            job.settlement_tx.as_deref() == Some(update.tx_id.as_str()) && job.status.can_transition_to(JobStatus::Settled)
+        })?;
+        let job_id = job.id;
+        if update.status != PsyTxStatus::Confirmed {
+            job.settlement_tx = None;
+            self.registry.events().emit(self.registry.now(), DomainEvent::SettlementTxFailed {
+                job_id,
+                tx_id: update.tx_id.clone(),
+            });
+            return Some((job_id, Err(SpectreError::SettlementTxFailed {
+                job_id,
+                tx_id: update.tx_id.clone(),
+                status: update.status.clone(),
+            })));
+        }
+        Some((job_id, self.settle_job(job_id)))
+    }
+
+    /// Withdraw an unclaimed job and refund its escrow to the requester (Open → Cancelled)
+    /// 
+    /// # Returns
+    /// The number of tokens refunded
+    pub fn cancel_job(&mut self, job_id: u64) -> SpectreResult<u64> {
+        let now = self.registry.now();
+        let job = self.jobs.get_mut(&job_id)
+            .ok_or(SpectreError::JobNotFound { job_id })?;
+        if job.status == JobStatus::Claimed || job.status == JobStatus::ResultSubmitted {
+            return Err(SpectreError::JobAlreadyTaken { job_id });
+        }
+
+        job.transition(JobStatus::Cancelled, now)?;
+        let amount = self.ledger.refund_escrow(job_id)?;
+        let events = self.registry.events();
+        events.emit(now, DomainEvent::JobCancelled { job_id });
+        events.emit(now, DomainEvent::RequesterRefunded { job_id, requester: job.requester.clone(), amount });
+        Ok(amount)
+    }
+
+    /// Expire and refund every open or claimed job whose expiry is at or before `now`
+    /// 
+    /// An agent that claimed a job and let it expire is slashed for
+    /// [`SlashOffense::MissedDeadline`].
+    /// 
+    /// # Returns
+    /// The jobs that were expired, and any the sweep failed on with why. A job
+    /// that couldn't move to `Expired` is left untouched and not refunded; an
+    /// expired job whose refund or slash failed still has its stake unlocked.
+    pub fn expire_jobs(&mut self, now: u64) -> ExpirySweep {
+        let mut sweep = ExpirySweep::default();
+        for job in self.jobs.values_mut() {
+            if !job.is_expired_at(now) || !job.status.can_transition_to(JobStatus::Expired) {
+                continue;
+            }
+            if let Err(e) = job.transition(JobStatus::Expired, now) {
+                sweep.failed.push((job.id, e));
+                continue;
+            }
+            sweep.expired.push(job.id);
+            let events = self.registry.events();
+            events.emit(now, DomainEvent::JobExpired { job_id: job.id });
+            match self.ledger.refund_escrow(job.id) {
+                Ok(amount) => {
+                    events.emit(now, DomainEvent::RequesterRefunded { job_id: job.id, requester: job.requester.clone(), amount });
+                }
+                Err(e) => sweep.failed.push((job.id, e)),
+            }
+            if let Some(agent_id) = &job.assigned_agent {
+                self.registry.stakes.release_job(agent_id, job.id);
+                self.registry.record_outcome(agent_id, &job.required_algo, ReputationOutcome::Failure, None);
+                let ledger = &mut self.ledger;
+                let slashed = slash_agent_stake(&mut self.registry, agent_id, SlashOffense::MissedDeadline, job.id, &job.requester, |to_requester, burned| {
+                    ledger.apply_slash(Some(job.id), Some(&job.requester), to_requester, burned)
+                });
+                if let Err(e) = slashed {
+                    sweep.failed.push((job.id, e));
+                }
+            }
+        }
+        sweep.expired.sort_unstable();
+        sweep.failed.sort_unstable_by_key(|(job_id, _)| *job_id);
+        sweep
+    }
+
+    /// Record that the requester disputes a delivered (verified) result
+    /// 
+    /// Disputes weigh against the agent's reputation on the job's model;
+    /// a job can only be disputed once.
+    pub fn dispute_job(&mut self, job_id: u64) -> SpectreResult<()> {
+        let job = self.jobs.get_mut(&job_id)
+            .ok_or(SpectreError::JobNotFound { job_id })?;
+        let agent_id = match (&job.assigned_agent, job.entered_at(JobStatus::Verified)) {
+            (Some(agent_id), Some(_)) => agent_id.clone(),
+            _ => return Err(SpectreError::JobNotDisputable { job_id, status: job.status }),
         };
-        self.jobs.insert(id, job);
+        if job.disputed {
+            return Err(SpectreError::JobAlreadyDisputed { job_id });
+        }
+        job.disputed = true;
+        let model_type = job.required_algo.clone();
+        self.registry.events().emit(self.registry.now(), DomainEvent::JobDisputed { job_id, agent_id: agent_id.clone() });
+        self.registry.record_outcome(&agent_id, &model_type, ReputationOutcome::Dispute, None);
+        Ok(())
     }
 
-    /// Atomic job execution with identity verification
+    /// Atomic job claim with identity verification
     /// 
     /// This is the core innovation: verify agent identity BEFORE payment.
     /// Prevents model substitution attacks and ensures quality.
+    /// The agent's SDKey proof is checked against the job's required model
+    /// and capacity; the reward stays in escrow until the job is settled.
     /// 
     /// # Arguments
     /// - `job_id`: The job to execute
-    /// - `agent_id`: The agent attempting execution
+    /// - `proof`: SDKey proof from the agent attempting execution
     /// 
     /// # Returns
-    /// - `Ok(String)`: Success message with payment details
-    /// - `Err(String)`: Failure reason (capability mismatch, job taken, etc.)
-    pub fn attempt_job_execution(&mut self, job_id: u64, agent_id: String) -> Result<String, String> {
-        // 1. Get the Agent's SDKey
-        let agent_key = self.agent_registry.get(&agent_id)
-            .ok_or("Agent not registered in SDKey system")?;
-
-        // 2. Get the Job
-        let job = self.jobs.get_mut(&job_id)
-            .ok_or("Job not found")?;
+    /// - `Ok(String)`: Success message with reward details
+    /// - `Err(SpectreError)`: Failure reason (capability mismatch, job taken, etc.)
+    pub fn attempt_job_execution(&mut self, job_id: u64, proof: &SDKeyProof) -> SpectreResult<String> {
+        let job = self.jobs.get(&job_id)
+            .ok_or(SpectreError::JobNotFound { job_id })?;
+        job.ensure_claimable(self.registry.now())?;
+        ensure_proof_for_job(proof, job_id)?;
+        self.registry.verify_proof_for_model(proof, &job.required_algo, job.task_complexity)?;
 
-        // 3. Verify Constraints (The "ZK Proof" simulation)
-        if !agent_key.verify_capability(&job.required_algo) {
-            return Err("Agent capability verification failed".to_string());
-        }
+        let agent_id = &proof.agent_id;
+        self.claim_job(job_id, agent_id)?;
+        Ok(claimed_message(agent_id, &self.jobs[&job_id]))
+    }
 
-        if job.is_fulfilled {
-            return Err("Job already taken".to_string());
+    // Apply an event replayed from a log through the same primitives the
+    // live operation used, at the time it happened, without emitting it again
+    fn apply_event(&mut self, record: &EventRecord) -> SpectreResult<()> {
+        let at = record.at;
+        self.registry.apply_event(record)?;
+        match &record.event {
+            DomainEvent::Minted { account, amount } => self.ledger.mint(account, *amount)?,
+            DomainEvent::Transferred { from, to, amount } => self.ledger.transfer(from, to, *amount)?,
+            // Tokens only moved if the market moved them; a bare registry call didn't
+            DomainEvent::AgentRegistered { agent_id, stake: amount, on_ledger: true, .. }
+            | DomainEvent::StakeDeposited { agent_id, amount, on_ledger: true } => self.ledger.bond_stake(agent_id, *amount)?,
+            DomainEvent::StakeWithdrawn { agent_id, amount, on_ledger: true } => self.ledger.unbond_stake(agent_id, *amount)?,
+            DomainEvent::AgentSlashed { event, on_ledger: true } => {
+                self.ledger.apply_slash(event.job_id, event.requester.as_deref(), event.to_requester, event.burned)?;
+            }
+            DomainEvent::JobPosted { job } => {
+                self.ledger.lock_escrow(job.id, &job.requester, job.reward_tokens)?;
+                self.jobs.insert(job.id, job.clone());
+            }
+            DomainEvent::JobClaimed { job_id, agent_id } => {
+                let job = self.jobs.get_mut(job_id).ok_or(SpectreError::JobNotFound { job_id: *job_id })?;
+                self.registry.stakes.lock_for_job(agent_id, job.id, job.reward_tokens)?;
+                job.transition(JobStatus::Claimed, at)?;
+                job.assigned_agent = Some(agent_id.clone());
+            }
+            DomainEvent::ResultSubmitted { job_id, proof } => {
+                let job = self.jobs.get_mut(job_id).ok_or(SpectreError::JobNotFound { job_id: *job_id })?;
+                job.transition(JobStatus::ResultSubmitted, at)?;
+                job.result_proof = Some(proof.clone());
+            }
+            DomainEvent::ResultVerified { job_id, .. } => self.transition_job(*job_id, JobStatus::Verified, at)?,
+            DomainEvent::ResultRejected { job_id, .. } => self.transition_job(*job_id, JobStatus::Rejected, at)?,
+            DomainEvent::AgentRewarded { job_id, agent_id, .. } => {
+                self.ledger.release_escrow(*job_id, agent_id)?;
+            }
+            DomainEvent::RequesterRefunded { job_id, .. } => {
+                self.ledger.refund_escrow(*job_id)?;
+            }
+            DomainEvent::JobSettled { job_id } => {
+                let job = self.jobs.get_mut(job_id).ok_or(SpectreError::JobNotFound { job_id: *job_id })?;
+                if let Some(agent_id) = &job.assigned_agent {
+                    self.registry.stakes.release_job(agent_id, job.id);
+                }
+                job.transition(JobStatus::Settled, at)?;
+            }
+            DomainEvent::JobCancelled { job_id } => self.transition_job(*job_id, JobStatus::Cancelled, at)?,
+            DomainEvent::JobExpired { job_id } => {
+                let job = self.jobs.get_mut(job_id).ok_or(SpectreError::JobNotFound { job_id: *job_id })?;
+                if let Some(agent_id) = &job.assigned_agent {
+                    self.registry.stakes.release_job(agent_id, job.id);
+                }
+                job.transition(JobStatus::Expired, at)?;
+            }
+            DomainEvent::SettlementTxAttached { job_id, tx_id } => {
+                let job = self.jobs.get_mut(job_id).ok_or(SpectreError::JobNotFound { job_id: *job_id })?;
+                job.settlement_tx = Some(tx_id.clone());
+            }
+            DomainEvent::SettlementTxFailed { job_id, .. } => {
+                let job = self.jobs.get_mut(job_id).ok_or(SpectreError::JobNotFound { job_id: *job_id })?;
+                job.settlement_tx = None;
+            }
+            DomainEvent::JobDisputed { job_id, .. } => {
+                let job = self.jobs.get_mut(job_id).ok_or(SpectreError::JobNotFound { job_id: *job_id })?;
+                job.disputed = true;
+            }
+            // Registry-only changes, already applied above
+            DomainEvent::AgentRegistered { .. }
+            | DomainEvent::StakeDeposited { .. }
+            | DomainEvent::StakeWithdrawn { .. }
+            | DomainEvent::AgentSlashed { .. }
+            | DomainEvent::UnstakeRequested { .. }
+            | DomainEvent::ProofAccepted { .. }
+            | DomainEvent::ReputationRecorded { .. } => {}
         }
+        Ok(())
+    }
 
-        // 4. Execute Swap
-        job.is_fulfilled = true;
-        
-        Ok(format!(
-            "SUCCESS: Agent {} verified via SDKey and took Job {} for {} tokens.",
-            agent_id, job_id, job.reward_tokens
-        ))
+    fn transition_job(&mut self, job_id: u64, to: JobStatus, at: u64) -> SpectreResult<()> {
+        self.jobs.get_mut(&job_id)
+            .ok_or(SpectreError::JobNotFound { job_id })?
+            .transition(to, at)
     }
-}
\ No newline at end of file
+}
+
+// Slash an agent's stake on the registry and move the slashed tokens out of
+// the stake vault with `pay_out(to_requester, burned)`, as one step: the
+// payout runs first, on the registry's preview of the slash, so if it fails
+// the stake is left untouched and registry and ledger still agree
+pub(crate) fn slash_agent_stake(
+    registry: &mut SDKeyRegistry,
+    agent_id: &str,
+    offense: SlashOffense,
+    job_id: u64,
+    requester: &str,
+    pay_out: impl FnOnce(u64, u64) -> SpectreResult<()>,
+) -> SpectreResult<SlashEvent> {
+    let (to_requester, burned) = registry.stakes.preview_slash(agent_id, offense, Some(requester))?;
+    pay_out(to_requester, burned)?;
+    registry.apply_slash(agent_id, offense, Some(job_id), Some(requester), true)
+}
+
+// Receipt returned by a successful attempt_job_execution
+fn claimed_message(agent_id: &str, job: &Job) -> String {
+    format!(
+        "SUCCESS: Agent {} verified via SDKey and claimed Job {} ({} tokens held in escrow).",
+        agent_id, job.id, job.reward_tokens
+    )
+}
+
+// A signed proof is bound to one job; refuse to accept it for any other
+fn ensure_proof_for_job(proof: &SDKeyProof, job_id: u64) -> SpectreResult<()> {
+    if proof.job_id != job_id {
+        return Err(SpectreError::InvalidProof {
+            agent_id: proof.agent_id.clone(),
+            reason: format!("proof is bound to job {}, not job {}", proof.job_id, job_id),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,93 @@
+// src/lifecycle.rs
+// Job Lifecycle: explicit states and legal transitions for market jobs
+
+use crate::error::SpectreError;
+use serde::{Deserialize, Serialize};
+
+/// Where a job is in its lifecycle.
+///
+/// ```text
+/// Open → Claimed → ResultSubmitted → Verified ─┐
+///   │       │                      └→ Rejected ─┴→ Settled
+///   │       └→ Expired
+///   ├→ Expired
+///   └→ Cancelled
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum JobStatus {
+    Open,
+    Claimed,
+    ResultSubmitted,
+    Verified,
+    Rejected,
+    Settled,
+    Cancelled,
+    Expired,
+}
+
+impl JobStatus {
+    /// Whether moving from `self` to `next` is a legal lifecycle step
+    pub fn can_transition_to(self, next: JobStatus) -> bool {
+        use JobStatus::*;
+        matches!(
+            (self, next),
+            (Open, Claimed)
+                | (Open, Cancelled)
+                | (Open, Expired)
+                | (Claimed, ResultSubmitted)
+                | (Claimed, Expired)
+                | (ResultSubmitted, Verified)
+                | (ResultSubmitted, Rejected)
+                | (Verified, Settled)
+                | (Rejected, Settled)
+        )
+    }
+
+    /// Terminal states never transition again
+    pub fn is_terminal(self) -> bool {
+        matches!(self, JobStatus::Settled | JobStatus::Cancelled | JobStatus::Expired)
+    }
+}
+
+/// A recorded state change, with the Unix time (seconds) it happened.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobTransition {
+    pub from: JobStatus,
+    pub to: JobStatus,
+    pub at: u64,
+}
+
+/// How a job's escrow was resolved when it settled.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JobSettlement {
+    /// Verified work: escrow released to the agent minus the protocol fee
+    Paid { agent_id: String, payout: u64, fee: u64 },
+    /// Rejected work: escrow returned to the requester
+    Refunded { requester: String, amount: u64 },
+}
+
+/// What one sweep of [`expire_jobs`](crate::SpectreMarket::expire_jobs) did.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExpirySweep {
+    /// Jobs expired and refunded, by id
+    pub expired: Vec<u64>,
+    /// Jobs the sweep hit a problem with, by id: one left unexpired, or an
+    /// expired job whose agent couldn't be slashed
+    pub failed: Vec<(u64, SpectreError)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legal_transitions() {
+        assert!(JobStatus::Open.can_transition_to(JobStatus::Claimed));
+        assert!(JobStatus::ResultSubmitted.can_transition_to(JobStatus::Rejected));
+        assert!(JobStatus::Rejected.can_transition_to(JobStatus::Settled));
+        assert!(!JobStatus::Open.can_transition_to(JobStatus::Settled));
+        assert!(!JobStatus::Claimed.can_transition_to(JobStatus::Cancelled));
+        assert!(!JobStatus::Settled.can_transition_to(JobStatus::Open));
+        assert!(JobStatus::Expired.is_terminal());
+    }
+}
@@ -0,0 +1,127 @@
+// src/market_handle.rs
+// Cloneable async handle for sharing one market across tokio tasks
+
+use crate::error::SpectreResult;
+use crate::lifecycle::{ExpirySweep, JobSettlement};
+use crate::parth::ParthMarket;
+use crate::psy_integration::PsyTxUpdate;
+use crate::sdkey_verification::{SDKeyProof, VerifyingKey};
+use crate::{Job, JobRequest};
+use std::sync::Arc;
+
+/// Shared handle to a [`ParthMarket`], cheap to clone into every task.
+///
+/// The async methods mirror the market's own. The market blocks on its
+/// shard locks, so each operation runs on tokio's blocking pool and the
+/// calling task just awaits the outcome: a contended shard never stalls a
+/// runtime worker, no lock is ever held across an `.await`, and a cancelled
+/// future never leaves a half-applied operation (the operation still runs
+/// to completion). Two agents racing for one job serialize on the job's shard:
+/// exactly one claim succeeds and the others get `JobAlreadyTaken`.
+#[derive(Clone, Default)]
+pub struct MarketHandle {
+    market: Arc<ParthMarket>,
+}
+
+impl From<ParthMarket> for MarketHandle {
+    fn from(market: ParthMarket) -> Self {
+        MarketHandle { market: Arc::new(market) }
+    }
+}
+
+impl MarketHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The shared market, for reads and operations without an async wrapper
+    pub fn market(&self) -> &ParthMarket {
+        &self.market
+    }
+
+    pub async fn register_agent(&self, agent_id: String, algos: Vec<String>, stake: u64, public_key: VerifyingKey) -> SpectreResult<()> {
+        self.run(move |market| market.register_agent(agent_id, algos, stake, public_key)).await
+    }
+
+    pub async fn deposit_stake(&self, agent_id: &str, amount: u64) -> SpectreResult<()> {
+        let agent_id = agent_id.to_string();
+        self.run(move |market| market.deposit_stake(&agent_id, amount)).await
+    }
+
+    pub async fn request_unstake(&self, agent_id: &str, amount: u64) -> SpectreResult<u64> {
+        let agent_id = agent_id.to_string();
+        self.run(move |market| market.request_unstake(&agent_id, amount)).await
+    }
+
+    pub async fn withdraw_unstaked(&self, agent_id: &str) -> SpectreResult<u64> {
+        let agent_id = agent_id.to_string();
+        self.run(move |market| market.withdraw_unstaked(&agent_id)).await
+    }
+
+    pub async fn post_job(&self, id: u64, requester: String, algo: String, reward: u64) -> SpectreResult<()> {
+        self.run(move |market| market.post_job(id, requester, algo, reward)).await
+    }
+
+    pub async fn post_job_request(&self, request: JobRequest) -> SpectreResult<()> {
+        self.run(move |market| market.post_job_request(request)).await
+    }
+
+    pub async fn job(&self, job_id: u64) -> Option<Job> {
+        self.run(move |market| market.job(job_id)).await
+    }
+
+    pub async fn claim_job(&self, job_id: u64, agent_id: &str) -> SpectreResult<()> {
+        let agent_id = agent_id.to_string();
+        self.run(move |market| market.claim_job(job_id, &agent_id)).await
+    }
+
+    pub async fn attempt_job_execution(&self, job_id: u64, proof: &SDKeyProof) -> SpectreResult<String> {
+        let proof = proof.clone();
+        self.run(move |market| market.attempt_job_execution(job_id, &proof)).await
+    }
+
+    pub async fn submit_result(&self, job_id: u64, proof: SDKeyProof) -> SpectreResult<()> {
+        self.run(move |market| market.submit_result(job_id, proof)).await
+    }
+
+    pub async fn verify_result(&self, job_id: u64) -> SpectreResult<()> {
+        self.run(move |market| market.verify_result(job_id)).await
+    }
+
+    pub async fn settle_job(&self, job_id: u64) -> SpectreResult<JobSettlement> {
+        self.run(move |market| market.settle_job(job_id)).await
+    }
+
+    pub async fn settle_on_confirmation(&self, job_id: u64, tx_id: String) -> SpectreResult<()> {
+        self.run(move |market| market.settle_on_confirmation(job_id, tx_id)).await
+    }
+
+    pub async fn apply_tx_update(&self, update: &PsyTxUpdate) -> Option<(u64, SpectreResult<JobSettlement>)> {
+        let update = update.clone();
+        self.run(move |market| market.apply_tx_update(&update)).await
+    }
+
+    pub async fn dispute_job(&self, job_id: u64) -> SpectreResult<()> {
+        self.run(move |market| market.dispute_job(job_id)).await
+    }
+
+    pub async fn cancel_job(&self, job_id: u64) -> SpectreResult<u64> {
+        self.run(move |market| market.cancel_job(job_id)).await
+    }
+
+    pub async fn expire_jobs(&self, now: u64) -> ExpirySweep {
+        self.run(move |market| market.expire_jobs(now)).await
+    }
+
+    // Run `op` against the market on the blocking pool, passing on its panic if it has one
+    async fn run<R: Send + 'static>(&self, op: impl FnOnce(&ParthMarket) -> R + Send + 'static) -> R {
+        let market = self.market.clone();
+        match tokio::task::spawn_blocking(move || op(&market)).await {
+            Ok(result) => result,
+            Err(e) => match e.try_into_panic() {
+                Ok(payload) => std::panic::resume_unwind(payload),
+                Err(e) => panic!("market operation did not finish: {}", e),
+            },
+        }
+    }
+}
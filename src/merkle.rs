@@ -0,0 +1,502 @@
+// src/merkle.rs
+// Merkle commitments over market state: state roots, inclusion and exclusion proofs
+
+use crate::error::{SpectreError, SpectreResult};
+use crate::parth::{ParthMarket, ShardVersions};
+use crate::sdkey_verification::AgentCapability;
+use crate::{Job, SpectreMarket};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+
+pub type MerkleHash = [u8; 32];
+
+/// Root of a tree with no entries
+pub const EMPTY_ROOT: MerkleHash = [0; 32];
+
+// Domain separators so a leaf, a branch and the state root can never be
+// passed off as one another
+const LEAF_TAG: u8 = 0;
+const BRANCH_TAG: u8 = 1;
+const STATE_TAG: u8 = 2;
+
+fn tagged_hash(tag: u8, parts: &[&MerkleHash]) -> MerkleHash {
+    let mut hasher = Sha256::new();
+    hasher.update([tag]);
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+/// Position of `key` in a tree: the bits of its SHA-256, most significant first
+pub fn merkle_key(key: &str) -> MerkleHash {
+    Sha256::digest(key.as_bytes()).into()
+}
+
+/// Commitment to a state entry: SHA-256 of its JSON encoding
+pub fn merkle_value<T: Serialize>(value: &T) -> MerkleHash {
+    Sha256::digest(serde_json::to_vec(value).expect("state entries serialize to JSON")).into()
+}
+
+fn leaf_hash(key: &MerkleHash, value: &MerkleHash) -> MerkleHash {
+    tagged_hash(LEAF_TAG, &[key, value])
+}
+
+fn branch_hash(left: &MerkleHash, right: &MerkleHash) -> MerkleHash {
+    tagged_hash(BRANCH_TAG, &[left, right])
+}
+
+fn bit(key: &MerkleHash, depth: usize) -> bool {
+    key[depth / 8] >> (7 - depth % 8) & 1 == 1
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Empty,
+    Leaf { key: MerkleHash, value: MerkleHash },
+    Branch { hash: MerkleHash, left: Box<Node>, right: Box<Node> },
+}
+
+impl Node {
+    fn hash(&self) -> MerkleHash {
+        match self {
+            Node::Empty => EMPTY_ROOT,
+            Node::Leaf { key, value } => leaf_hash(key, value),
+            Node::Branch { hash, .. } => *hash,
+        }
+    }
+
+    // Join two subtrees, collapsing a lone leaf into its parent's place
+    fn join(left: Node, right: Node) -> Node {
+        match (&left, &right) {
+            (Node::Empty, Node::Empty) => Node::Empty,
+            (Node::Leaf { .. }, Node::Empty) => left,
+            (Node::Empty, Node::Leaf { .. }) => right,
+            _ => Node::Branch { hash: branch_hash(&left.hash(), &right.hash()), left: Box::new(left), right: Box::new(right) },
+        }
+    }
+
+    // Children of this node at `depth`, expanding a leaf into the side its key falls on
+    fn split(self, depth: usize) -> (Node, Node) {
+        match self {
+            Node::Empty => (Node::Empty, Node::Empty),
+            Node::Leaf { key, .. } if bit(&key, depth) => (Node::Empty, self),
+            Node::Leaf { .. } => (self, Node::Empty),
+            Node::Branch { left, right, .. } => (*left, *right),
+        }
+    }
+
+    fn insert(self, depth: usize, key: MerkleHash, value: MerkleHash) -> Node {
+        match self {
+            Node::Empty => Node::Leaf { key, value },
+            Node::Leaf { key: existing, .. } if existing == key => Node::Leaf { key, value },
+            node => {
+                let (left, right) = node.split(depth);
+                if bit(&key, depth) {
+                    Node::join(left, right.insert(depth + 1, key, value))
+                } else {
+                    Node::join(left.insert(depth + 1, key, value), right)
+                }
+            }
+        }
+    }
+
+    fn remove(self, depth: usize, key: &MerkleHash) -> Node {
+        match self {
+            Node::Empty => Node::Empty,
+            Node::Leaf { key: existing, .. } if existing == *key => Node::Empty,
+            leaf @ Node::Leaf { .. } => leaf,
+            Node::Branch { left, right, .. } if bit(key, depth) => Node::join(*left, right.remove(depth + 1, key)),
+            Node::Branch { left, right, .. } => Node::join(left.remove(depth + 1, key), *right),
+        }
+    }
+}
+
+/// Leaf a [`MerkleProof`]'s path ends at.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofLeaf {
+    pub key: MerkleHash,
+    pub value: MerkleHash,
+}
+
+/// Path from the root to where a key lives (or would live) in a
+/// [`SparseMerkleTree`].
+///
+/// The path ends at the key's own leaf (inclusion), at an empty subtree or
+/// at another key's leaf sharing the path so far (exclusion).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    /// Sibling hashes from the root downwards
+    pub siblings: Vec<MerkleHash>,
+    pub leaf: Option<ProofLeaf>,
+}
+
+impl MerkleProof {
+    /// Root this proof leads to for `key`, with the value committed for
+    /// `key` if the proof shows it included
+    pub fn root_for(&self, key: &MerkleHash) -> Result<(MerkleHash, Option<MerkleHash>), String> {
+        if self.siblings.len() > 256 {
+            return Err("path is longer than a key".to_string());
+        }
+        let (mut hash, value) = match &self.leaf {
+            None => (EMPTY_ROOT, None),
+            Some(leaf) if leaf.key == *key => (leaf_hash(&leaf.key, &leaf.value), Some(leaf.value)),
+            Some(leaf) => {
+                // Another key's leaf only excludes this one where both keys share the path
+                if (0..self.siblings.len()).any(|depth| bit(&leaf.key, depth) != bit(key, depth)) {
+                    return Err("terminal leaf is not on the key's path".to_string());
+                }
+                (leaf_hash(&leaf.key, &leaf.value), None)
+            }
+        };
+        for (depth, sibling) in self.siblings.iter().enumerate().rev() {
+            hash = if bit(key, depth) { branch_hash(sibling, &hash) } else { branch_hash(&hash, sibling) };
+        }
+        Ok((hash, value))
+    }
+}
+
+/// Sparse Merkle tree over string keys, updated in place.
+///
+/// Keys sit on the path given by the bits of their hash, and a subtree
+/// holding a single entry is replaced by that entry's leaf, so proofs are
+/// about log2(entries) hashes long and an update rehashes only the path to
+/// the changed key. The root depends only on the set of entries, not on the
+/// order they were written in.
+#[derive(Debug, Clone)]
+pub struct SparseMerkleTree {
+    root: Node,
+    values: HashMap<String, MerkleHash>,
+}
+
+impl Default for SparseMerkleTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SparseMerkleTree {
+    pub fn new() -> Self {
+        SparseMerkleTree { root: Node::Empty, values: HashMap::new() }
+    }
+
+    pub fn root(&self) -> MerkleHash {
+        self.root.hash()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Value hash committed for `key`
+    pub fn get(&self, key: &str) -> Option<MerkleHash> {
+        self.values.get(key).copied()
+    }
+
+    /// Commit `value` for `key`; returns whether anything changed
+    pub fn insert(&mut self, key: &str, value: MerkleHash) -> bool {
+        if self.values.get(key) == Some(&value) {
+            return false;
+        }
+        self.values.insert(key.to_string(), value);
+        let root = std::mem::replace(&mut self.root, Node::Empty);
+        self.root = root.insert(0, merkle_key(key), value);
+        true
+    }
+
+    /// Drop `key`; returns whether it was present
+    pub fn remove(&mut self, key: &str) -> bool {
+        if self.values.remove(key).is_none() {
+            return false;
+        }
+        let root = std::mem::replace(&mut self.root, Node::Empty);
+        self.root = root.remove(0, &merkle_key(key));
+        true
+    }
+
+    /// Drop every key not in `keep`
+    pub fn retain(&mut self, keep: &HashSet<String>) {
+        let stale: Vec<String> = self.values.keys().filter(|key| !keep.contains(*key)).cloned().collect();
+        for key in stale {
+            self.remove(&key);
+        }
+    }
+
+    pub fn prove(&self, key: &str) -> MerkleProof {
+        let key = merkle_key(key);
+        let mut siblings = Vec::new();
+        let mut node = &self.root;
+        loop {
+            match node {
+                Node::Empty => return MerkleProof { siblings, leaf: None },
+                Node::Leaf { key, value } => return MerkleProof { siblings, leaf: Some(ProofLeaf { key: *key, value: *value }) },
+                Node::Branch { left, right, .. } => {
+                    let (next, sibling) = if bit(&key, siblings.len()) { (right, left) } else { (left, right) };
+                    siblings.push(sibling.hash());
+                    node = next;
+                }
+            }
+        }
+    }
+}
+
+/// Top-level branches of the state tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StateNamespace {
+    /// Keyed by decimal job id, committing to the [`Job`]
+    Jobs,
+    /// Keyed by agent id, committing to its [`AgentCapability`]
+    Agents,
+    /// Keyed by account, committing to its balance as a `u64`
+    Balances,
+}
+
+impl StateNamespace {
+    pub const ALL: [StateNamespace; 3] = [StateNamespace::Jobs, StateNamespace::Agents, StateNamespace::Balances];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Commitment to the whole market: one [`SparseMerkleTree`] per
+/// [`StateNamespace`], joined under a single state root.
+///
+/// [`commit_market`](StateTree::commit_market) (or
+/// [`commit_parth`](StateTree::commit_parth)) after each batch of operations
+/// brings the tree up to date and returns the new root. `commit_market`
+/// rehashes every entry to spot changes; `commit_parth` rehashes only the
+/// shards that changed since its last commit. Either way only changed
+/// entries touch the tree. Proofs from [`prove`](StateTree::prove) verify
+/// against the root alone.
+#[derive(Debug, Clone, Default)]
+pub struct StateTree {
+    trees: [SparseMerkleTree; 3],
+    // Where the last commit_parth left the market's shards, and the keys
+    // each shard held then, per namespace
+    parth_versions: ShardVersions,
+    shard_keys: [HashMap<usize, HashSet<String>>; 3],
+}
+
+impl StateTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn root(&self) -> MerkleHash {
+        state_root(&self.namespace_roots())
+    }
+
+    pub fn namespace_roots(&self) -> [MerkleHash; 3] {
+        [self.trees[0].root(), self.trees[1].root(), self.trees[2].root()]
+    }
+
+    pub fn tree(&self, namespace: StateNamespace) -> &SparseMerkleTree {
+        &self.trees[namespace.index()]
+    }
+
+    pub fn update_job(&mut self, job: &Job) -> bool {
+        self.forget_parth();
+        self.trees[StateNamespace::Jobs.index()].insert(&job.id.to_string(), merkle_value(job))
+    }
+
+    pub fn update_agent(&mut self, agent_id: &str, capability: &AgentCapability) -> bool {
+        self.forget_parth();
+        self.trees[StateNamespace::Agents.index()].insert(agent_id, merkle_value(capability))
+    }
+
+    pub fn update_balance(&mut self, account: &str, balance: u64) -> bool {
+        self.forget_parth();
+        self.trees[StateNamespace::Balances.index()].insert(account, merkle_value(&balance))
+    }
+
+    pub fn remove(&mut self, namespace: StateNamespace, key: &str) -> bool {
+        self.forget_parth();
+        self.trees[namespace.index()].remove(key)
+    }
+
+    /// Bring the tree in line with `market` and return the new state root
+    pub fn commit_market(&mut self, market: &SpectreMarket) -> MerkleHash {
+        self.forget_parth();
+        self.sync(
+            market.jobs.values(),
+            market.registry.verified_agents.iter().map(|(agent_id, capability)| (agent_id.as_str(), capability)),
+            market.ledger.balances(),
+        )
+    }
+
+    /// Bring the tree in line with a sharded market and return the new state root
+    ///
+    /// Jobs, agents and balances are read at one instant, and only the
+    /// shards that changed since this tree's last `commit_parth` of the same
+    /// market are rehashed.
+    pub fn commit_parth(&mut self, market: &ParthMarket) -> MerkleHash {
+        let changes = market.changes_since(&self.parth_versions);
+        if changes.full {
+            self.shard_keys = Default::default();
+        }
+        for (shard, jobs) in &changes.jobs {
+            let entries = jobs.iter().map(|job| (job.id.to_string(), merkle_value(job)));
+            self.sync_shard(StateNamespace::Jobs, *shard, entries);
+        }
+        for (shard, agents) in &changes.agents {
+            let entries = agents.iter().map(|(agent_id, capability)| (agent_id.clone(), merkle_value(capability)));
+            self.sync_shard(StateNamespace::Agents, *shard, entries);
+        }
+        for (shard, balances) in &changes.balances {
+            let entries = balances.iter().map(|(account, balance)| (account.clone(), merkle_value(balance)));
+            self.sync_shard(StateNamespace::Balances, *shard, entries);
+        }
+        if changes.full {
+            // Drop whatever the tree held from elsewhere
+            for (tree, shards) in self.trees.iter_mut().zip(&self.shard_keys) {
+                tree.retain(&shards.values().flatten().cloned().collect());
+            }
+        }
+        self.parth_versions = changes.versions;
+        self.root()
+    }
+
+    /// Proof of `key`'s entry in `namespace`, or of its absence
+    pub fn prove(&self, namespace: StateNamespace, key: &str) -> StateProof {
+        StateProof {
+            namespace,
+            key: key.to_string(),
+            proof: self.trees[namespace.index()].prove(key),
+            namespace_roots: self.namespace_roots(),
+        }
+    }
+
+    // Make one shard's entries in `namespace` exactly `entries`
+    fn sync_shard(&mut self, namespace: StateNamespace, shard: usize, entries: impl Iterator<Item = (String, MerkleHash)>) {
+        let tree = &mut self.trees[namespace.index()];
+        let mut keys = HashSet::new();
+        for (key, value) in entries {
+            tree.insert(&key, value);
+            keys.insert(key);
+        }
+        let previous = self.shard_keys[namespace.index()].insert(shard, keys).unwrap_or_default();
+        let current = &self.shard_keys[namespace.index()][&shard];
+        for key in previous.difference(current) {
+            tree.remove(key);
+        }
+    }
+
+    // Changed outside commit_parth, so its next run must look at every shard
+    fn forget_parth(&mut self) {
+        self.parth_versions = ShardVersions::default();
+    }
+
+    fn sync<'a>(
+        &mut self,
+        jobs: impl Iterator<Item = &'a Job>,
+        agents: impl Iterator<Item = (&'a str, &'a AgentCapability)>,
+        balances: impl Iterator<Item = (&'a str, u64)>,
+    ) -> MerkleHash {
+        let mut present: [HashSet<String>; 3] = Default::default();
+        for job in jobs {
+            self.update_job(job);
+            present[StateNamespace::Jobs.index()].insert(job.id.to_string());
+        }
+        for (agent_id, capability) in agents {
+            self.update_agent(agent_id, capability);
+            present[StateNamespace::Agents.index()].insert(agent_id.to_string());
+        }
+        for (account, balance) in balances {
+            self.update_balance(account, balance);
+            present[StateNamespace::Balances.index()].insert(account.to_string());
+        }
+        for (tree, keep) in self.trees.iter_mut().zip(&present) {
+            tree.retain(keep);
+        }
+        self.root()
+    }
+}
+
+fn state_root(namespace_roots: &[MerkleHash; 3]) -> MerkleHash {
+    tagged_hash(STATE_TAG, &[&namespace_roots[0], &namespace_roots[1], &namespace_roots[2]])
+}
+
+/// Evidence of what the state held under one key, checkable offline
+/// against a state root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateProof {
+    pub namespace: StateNamespace,
+    pub key: String,
+    pub proof: MerkleProof,
+    /// Roots of every namespace tree, joined into the state root
+    pub namespace_roots: [MerkleHash; 3],
+}
+
+impl StateProof {
+    /// Check the proof against `state_root`, returning the committed value
+    /// hash if the key is included and `None` if it is proven absent
+    pub fn verify(&self, state_root: &MerkleHash) -> SpectreResult<Option<MerkleHash>> {
+        let (namespace_root, value) = self.proof.root_for(&merkle_key(&self.key)).map_err(|reason| self.invalid(reason))?;
+        if namespace_root != self.namespace_roots[self.namespace.index()] {
+            return Err(self.invalid(format!("path does not lead to the {:?} root", self.namespace)));
+        }
+        if crate::merkle::state_root(&self.namespace_roots) != *state_root {
+            return Err(self.invalid(format!("namespace roots do not join into state root {}", hex::encode(state_root))));
+        }
+        Ok(value)
+    }
+
+    /// Check that the state held exactly `value` under the key
+    pub fn verify_inclusion<T: Serialize>(&self, state_root: &MerkleHash, value: &T) -> SpectreResult<()> {
+        match self.verify(state_root)? {
+            Some(committed) if committed == merkle_value(value) => Ok(()),
+            Some(_) => Err(self.invalid("the state holds a different value".to_string())),
+            None => Err(self.invalid("the key is absent".to_string())),
+        }
+    }
+
+    /// Check that the state held nothing under the key
+    pub fn verify_exclusion(&self, state_root: &MerkleHash) -> SpectreResult<()> {
+        match self.verify(state_root)? {
+            None => Ok(()),
+            Some(_) => Err(self.invalid("the key is present".to_string())),
+        }
+    }
+
+    fn invalid(&self, reason: String) -> SpectreError {
+        SpectreError::InvalidStateProof { key: format!("{:?}/{}", self.namespace, self.key), reason }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_root_is_independent_of_write_order_and_proofs_verify() {
+        let keys: Vec<String> = (0..200).map(|i| format!("key_{}", i)).collect();
+        let mut forward = SparseMerkleTree::new();
+        for key in &keys {
+            forward.insert(key, merkle_value(key));
+        }
+        let mut backward = SparseMerkleTree::new();
+        for key in keys.iter().rev().chain(["extra".to_string()].iter()) {
+            backward.insert(key, merkle_value(key));
+        }
+        backward.remove("extra");
+        assert_eq!(forward.root(), backward.root());
+        assert!(!forward.insert("key_7", merkle_value(&"key_7")));
+
+        for key in ["key_0", "key_199", "missing", "also_missing"] {
+            let (root, value) = forward.prove(key).root_for(&merkle_key(key)).unwrap();
+            assert_eq!(root, forward.root());
+            assert_eq!(value, forward.get(key));
+        }
+
+        for key in &keys {
+            forward.remove(key);
+        }
+        assert_eq!(forward.root(), EMPTY_ROOT);
+    }
+}
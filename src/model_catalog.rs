@@ -0,0 +1,300 @@
+// src/model_catalog.rs
+// Model Catalog: governed registry of AI models, their versions and accepted hashes
+
+use crate::error::{SpectreError, SpectreResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// One released build of a model, identified by its weights hash.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelVersion {
+    pub version: String,
+    pub hash: String,
+    /// Unix time (seconds) from which proofs with this hash are accepted
+    #[serde(default)]
+    pub effective_from: u64,
+    /// Unix time (seconds) from which proofs with this hash are refused
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retired_at: Option<u64>,
+}
+
+impl ModelVersion {
+    pub fn is_active_at(&self, now: u64) -> bool {
+        self.effective_from <= now && self.retired_at.is_none_or(|at| now < at)
+    }
+}
+
+/// A model type agents can register for, e.g. "LLaMA-3-70B".
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelEntry {
+    pub name: String,
+    /// Context capacity granted to agents registering for this model
+    pub max_tokens: u32,
+    pub versions: Vec<ModelVersion>,
+    /// From this time no new agents or jobs may use the model; existing
+    /// agents keep verifying until their versions are retired
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated_at: Option<u64>,
+}
+
+impl ModelEntry {
+    pub fn is_deprecated_at(&self, now: u64) -> bool {
+        self.deprecated_at.is_some_and(|at| at <= now)
+    }
+
+    /// Hashes proofs may carry at `now`
+    pub fn accepted_hashes(&self, now: u64) -> Vec<&str> {
+        self.versions.iter()
+            .filter(|v| v.is_active_at(now))
+            .map(|v| v.hash.as_str())
+            .collect()
+    }
+
+    /// The most recently effective active version at `now`
+    pub fn current_version(&self, now: u64) -> Option<&ModelVersion> {
+        self.versions.iter()
+            .filter(|v| v.is_active_at(now))
+            .max_by_key(|v| v.effective_from)
+    }
+}
+
+// On-disk shape of a catalog file: `models = [...]`
+#[derive(Serialize, Deserialize)]
+struct CatalogFile {
+    models: Vec<ModelEntry>,
+}
+
+/// Runtime catalog of known models.
+///
+/// Replaces the hard-coded model hash table: models can be added, versioned,
+/// retired and deprecated at runtime, or loaded from a JSON/TOML file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "CatalogFile", into = "CatalogFile")]
+pub struct ModelCatalog {
+    models: BTreeMap<String, ModelEntry>,
+}
+
+impl From<ModelCatalog> for CatalogFile {
+    fn from(catalog: ModelCatalog) -> Self {
+        CatalogFile { models: catalog.models.into_values().collect() }
+    }
+}
+
+impl TryFrom<CatalogFile> for ModelCatalog {
+    type Error = SpectreError;
+
+    fn try_from(file: CatalogFile) -> SpectreResult<Self> {
+        let mut catalog = ModelCatalog::empty();
+        for entry in file.models {
+            if entry.versions.is_empty() {
+                return Err(invalid_catalog(format!("model {} has no versions", entry.name)));
+            }
+            if catalog.models.contains_key(&entry.name) {
+                return Err(invalid_catalog(format!("model {} listed twice", entry.name)));
+            }
+            for version in &entry.versions {
+                catalog.ensure_hash_unused(&version.hash)?;
+            }
+            catalog.models.insert(entry.name.clone(), entry);
+        }
+        Ok(catalog)
+    }
+}
+
+impl Default for ModelCatalog {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl ModelCatalog {
+    pub fn empty() -> Self {
+        ModelCatalog { models: BTreeMap::new() }
+    }
+
+    /// The models Spectre ships with (in real system, these would be on-chain)
+    pub fn builtin() -> Self {
+        let mut catalog = Self::empty();
+        let builtin = [
+            ("LLaMA-3-70B", 8192, "0xa1b2c3d4e5f6"),
+            ("GPT-4-Turbo", 128000, "0xf6e5d4c3b2a1"),
+            ("Claude-3-Opus", 200000, "0x123456789abc"),
+            ("Mistral-Large", 32768, "0x9f8e7d6c5b4a"),
+        ];
+        for (name, max_tokens, hash) in builtin {
+            catalog.add_model(name, max_tokens, "1", hash, 0)
+                .expect("built-in catalog entries are unique");
+        }
+        catalog
+    }
+
+    pub fn from_json(json: &str) -> SpectreResult<Self> {
+        serde_json::from_str(json).map_err(|e| invalid_catalog(e.to_string()))
+    }
+
+    pub fn from_toml(toml_str: &str) -> SpectreResult<Self> {
+        toml::from_str(toml_str).map_err(|e| invalid_catalog(e.to_string()))
+    }
+
+    /// Load a catalog from a `.json` or `.toml` file
+    pub fn load(path: impl AsRef<Path>) -> SpectreResult<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| invalid_catalog(format!("{}: {}", path.display(), e)))?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::from_json(&contents),
+            Some("toml") => Self::from_toml(&contents),
+            _ => Err(invalid_catalog(format!("{}: expected a .json or .toml file", path.display()))),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("catalog serializes to JSON")
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ModelEntry> {
+        self.models.get(name)
+    }
+
+    pub fn models(&self) -> impl Iterator<Item = &ModelEntry> {
+        self.models.values()
+    }
+
+    /// Look up a model that new agents and jobs may use at `now`
+    pub fn available(&self, name: &str, now: u64) -> SpectreResult<&ModelEntry> {
+        let entry = self.models.get(name)
+            .ok_or_else(|| SpectreError::UnknownModel { model_type: name.to_string() })?;
+        if entry.is_deprecated_at(now) {
+            return Err(SpectreError::ModelDeprecated { model_type: name.to_string() });
+        }
+        Ok(entry)
+    }
+
+    pub fn is_hash_accepted(&self, name: &str, hash: &str, now: u64) -> bool {
+        self.models.get(name)
+            .is_some_and(|entry| entry.accepted_hashes(now).contains(&hash))
+    }
+
+    /// Add a new model with its first version
+    pub fn add_model(&mut self, name: &str, max_tokens: u32, version: &str, hash: &str, effective_from: u64) -> SpectreResult<()> {
+        if self.models.contains_key(name) {
+            return Err(invalid_catalog(format!("model {} already exists", name)));
+        }
+        self.ensure_hash_unused(hash)?;
+        self.models.insert(name.to_string(), ModelEntry {
+            name: name.to_string(),
+            max_tokens,
+            versions: vec![ModelVersion {
+                version: version.to_string(),
+                hash: hash.to_string(),
+                effective_from,
+                retired_at: None,
+            }],
+            deprecated_at: None,
+        });
+        Ok(())
+    }
+
+    /// Publish another accepted build of an existing model
+    pub fn add_version(&mut self, name: &str, version: &str, hash: &str, effective_from: u64) -> SpectreResult<()> {
+        self.ensure_hash_unused(hash)?;
+        let entry = self.entry_mut(name)?;
+        if entry.versions.iter().any(|v| v.version == version) {
+            return Err(invalid_catalog(format!("model {} already has version {}", name, version)));
+        }
+        entry.versions.push(ModelVersion {
+            version: version.to_string(),
+            hash: hash.to_string(),
+            effective_from,
+            retired_at: None,
+        });
+        Ok(())
+    }
+
+    /// Stop accepting proofs for one version from `at` onwards
+    pub fn retire_version(&mut self, name: &str, version: &str, at: u64) -> SpectreResult<()> {
+        let entry = self.entry_mut(name)?;
+        let found = entry.versions.iter_mut()
+            .find(|v| v.version == version)
+            .ok_or_else(|| invalid_catalog(format!("model {} has no version {}", name, version)))?;
+        found.retired_at = Some(at);
+        Ok(())
+    }
+
+    /// Close a model to new agents and jobs from `at` onwards
+    pub fn deprecate_model(&mut self, name: &str, at: u64) -> SpectreResult<()> {
+        self.entry_mut(name)?.deprecated_at = Some(at);
+        Ok(())
+    }
+
+    /// Change a model's capacity, for agents already registered on it as well as new ones
+    pub fn set_max_tokens(&mut self, name: &str, max_tokens: u32) -> SpectreResult<()> {
+        self.entry_mut(name)?.max_tokens = max_tokens;
+        Ok(())
+    }
+
+    fn entry_mut(&mut self, name: &str) -> SpectreResult<&mut ModelEntry> {
+        self.models.get_mut(name)
+            .ok_or_else(|| SpectreError::UnknownModel { model_type: name.to_string() })
+    }
+
+    // A hash identifies exactly one model build across the whole catalog
+    fn ensure_hash_unused(&self, hash: &str) -> SpectreResult<()> {
+        let owner = self.models.values()
+            .find(|entry| entry.versions.iter().any(|v| v.hash == hash));
+        match owner {
+            Some(entry) => Err(invalid_catalog(format!("hash {} already belongs to {}", hash, entry.name))),
+            None => Ok(()),
+        }
+    }
+}
+
+fn invalid_catalog(reason: String) -> SpectreError {
+    SpectreError::InvalidCatalog { reason }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_versions_by_effective_date() {
+        let mut catalog = ModelCatalog::builtin();
+        catalog.add_version("LLaMA-3-70B", "2", "0xbeef", 1_000).unwrap();
+
+        assert_eq!(catalog.get("LLaMA-3-70B").unwrap().accepted_hashes(500), vec!["0xa1b2c3d4e5f6"]);
+        assert!(catalog.is_hash_accepted("LLaMA-3-70B", "0xbeef", 1_000));
+        assert_eq!(catalog.get("LLaMA-3-70B").unwrap().current_version(1_000).unwrap().version, "2");
+
+        catalog.retire_version("LLaMA-3-70B", "1", 2_000).unwrap();
+        assert!(!catalog.is_hash_accepted("LLaMA-3-70B", "0xa1b2c3d4e5f6", 2_000));
+        assert!(catalog.add_version("GPT-4-Turbo", "9", "0xbeef", 0).is_err());
+    }
+
+    #[test]
+    fn test_load_from_toml_and_json() {
+        let toml_catalog = ModelCatalog::from_toml(r#"
+            [[models]]
+            name = "Qwen-2-72B"
+            max_tokens = 32000
+
+            [[models.versions]]
+            version = "2.0"
+            hash = "0x7177656e"
+            effective_from = 100
+        "#).unwrap();
+        let qwen = toml_catalog.get("Qwen-2-72B").unwrap();
+        assert_eq!(qwen.max_tokens, 32000);
+        assert!(toml_catalog.is_hash_accepted("Qwen-2-72B", "0x7177656e", 100));
+
+        let round_trip = ModelCatalog::from_json(&ModelCatalog::builtin().to_json()).unwrap();
+        assert_eq!(round_trip, ModelCatalog::builtin());
+
+        let duplicate = r#"{"models": [
+            {"name": "A", "max_tokens": 1, "versions": [{"version": "1", "hash": "0x1"}]},
+            {"name": "B", "max_tokens": 1, "versions": [{"version": "1", "hash": "0x1"}]}
+        ]}"#;
+        assert_eq!(ModelCatalog::from_json(duplicate).unwrap_err().code(), "INVALID_CATALOG");
+    }
+}
@@ -0,0 +1,900 @@
+// src/parth.rs
+// PARTH-style sharded market state: jobs, agents and balances in independently locked shards
+
+use crate::clock::{Clock, SystemClock};
+use crate::error::{SpectreError, SpectreResult};
+use crate::events::{DomainEvent, EventLog};
+use crate::ledger::{
+    protocol_fee, Escrow, LedgerEntry, LedgerEntryKind, Settlement, BURN_ACCOUNT, DEFAULT_PROTOCOL_FEE_BPS, STAKE_VAULT_ACCOUNT,
+    TREASURY_ACCOUNT,
+};
+use crate::lifecycle::{ExpirySweep, JobSettlement, JobStatus};
+use crate::model_catalog::ModelCatalog;
+use crate::psy_integration::{PsyTxStatus, PsyTxUpdate};
+use crate::reputation::ReputationOutcome;
+use crate::sdkey_verification::{AgentCapability, SDKeyProof, SDKeyRegistry, VerifyingKey};
+use crate::staking::{SlashEvent, SlashOffense, StakeAccount};
+use crate::{claimed_message, ensure_proof_for_job, Job, JobRequest};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+
+/// Shards per level of a [`ParthMarket`] unless configured otherwise
+pub const DEFAULT_SHARD_COUNT: usize = 64;
+
+/// State split into independently locked shards, each owning the keys that
+/// hash to it.
+///
+/// Work on keys in different shards never waits on each other. Work that
+/// spans shards locks them through [`lock_many`](Shards::lock_many), which
+/// always acquires in ascending index order, so it can't deadlock against
+/// other multi-shard work.
+///
+/// Each shard carries a version that moves every time it is locked through
+/// `lock*`, since the guard may be used to change it; the `read*` methods
+/// leave it alone and are for looking only. A shard whose version hasn't
+/// moved hasn't changed.
+pub struct Shards<T> {
+    shards: Vec<Mutex<T>>,
+    versions: Vec<AtomicU64>,
+}
+
+impl<T> Shards<T> {
+    /// `count` shards (at least one), each starting as `make()`
+    pub fn new(count: usize, mut make: impl FnMut() -> T) -> Self {
+        let count = count.max(1);
+        Shards {
+            shards: (0..count).map(|_| Mutex::new(make())).collect(),
+            versions: (0..count).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Shard that owns `key`; stable for the lifetime of the process
+    pub fn index_of<K: Hash + ?Sized>(&self, key: &K) -> usize {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        (hasher.finish() % self.shards.len() as u64) as usize
+    }
+
+    pub fn lock(&self, index: usize) -> MutexGuard<'_, T> {
+        let guard = self.shards[index].lock().unwrap();
+        // Bumped under the lock, so a reader holding it sees version and contents agree
+        self.versions[index].fetch_add(1, Ordering::SeqCst);
+        guard
+    }
+
+    /// Lock the shard that owns `key`
+    pub fn lock_key<K: Hash + ?Sized>(&self, key: &K) -> MutexGuard<'_, T> {
+        self.lock(self.index_of(key))
+    }
+
+    /// Lock several shards at once, lowest index first; duplicates are locked once
+    pub fn lock_many(&self, indices: impl IntoIterator<Item = usize>) -> BTreeMap<usize, MutexGuard<'_, T>> {
+        let indices: BTreeSet<usize> = indices.into_iter().collect();
+        indices.into_iter().map(|index| (index, self.lock(index))).collect()
+    }
+
+    /// Lock every shard, for a consistent view of the whole level
+    pub fn lock_all(&self) -> Vec<MutexGuard<'_, T>> {
+        (0..self.shards.len()).map(|index| self.lock(index)).collect()
+    }
+
+    /// Lock a shard only to look at it, leaving its version alone
+    pub fn read(&self, index: usize) -> MutexGuard<'_, T> {
+        self.shards[index].lock().unwrap()
+    }
+
+    pub fn read_key<K: Hash + ?Sized>(&self, key: &K) -> MutexGuard<'_, T> {
+        self.read(self.index_of(key))
+    }
+
+    /// Lock every shard only to look at them, for a consistent view of the whole level
+    pub fn read_all(&self) -> Vec<MutexGuard<'_, T>> {
+        (0..self.shards.len()).map(|index| self.read(index)).collect()
+    }
+
+    /// Current version of every shard, by index
+    pub fn versions(&self) -> Vec<u64> {
+        self.versions.iter().map(|version| version.load(Ordering::SeqCst)).collect()
+    }
+}
+
+// Balances and their history for the accounts hashing to one shard
+#[derive(Debug, Default)]
+struct AccountShard {
+    balances: HashMap<String, u64>,
+    history: Vec<LedgerEntry>,
+}
+
+// One account's side of a balance movement
+struct Leg<'a> {
+    account: &'a str,
+    kind: LedgerEntryKind,
+    amount: u64,
+    debit: bool,
+}
+
+impl<'a> Leg<'a> {
+    fn debit(account: &'a str, kind: LedgerEntryKind, amount: u64) -> Self {
+        Leg { account, kind, amount, debit: true }
+    }
+
+    fn credit(account: &'a str, kind: LedgerEntryKind, amount: u64) -> Self {
+        Leg { account, kind, amount, debit: false }
+    }
+}
+
+/// [`Ledger`](crate::Ledger) semantics over sharded state.
+///
+/// Balances are sharded by account and escrows by job id. An operation that
+/// touches several accounts (an escrow release paying the agent and the
+/// treasury, a slash paying the requester and the burn account) locks all of
+/// them before checking or changing anything, so it applies completely or
+/// not at all and nobody observes it half done. Escrow shards are always
+/// locked before account shards.
+pub struct ShardedLedger {
+    accounts: Shards<AccountShard>,
+    escrows: Shards<HashMap<u64, Escrow>>,
+    fee_bps: u64,
+    total_supply: AtomicU64,
+    next_seq: AtomicU64,
+}
+
+impl Default for ShardedLedger {
+    fn default() -> Self {
+        Self::new(DEFAULT_SHARD_COUNT, DEFAULT_PROTOCOL_FEE_BPS)
+    }
+}
+
+impl ShardedLedger {
+    pub fn new(shard_count: usize, fee_bps: u64) -> Self {
+        ShardedLedger {
+            accounts: Shards::new(shard_count, AccountShard::default),
+            escrows: Shards::new(shard_count, HashMap::new),
+            fee_bps: fee_bps.min(10_000),
+            total_supply: AtomicU64::new(0),
+            next_seq: AtomicU64::new(0),
+        }
+    }
+
+    pub fn fee_bps(&self) -> u64 {
+        self.fee_bps
+    }
+
+    pub fn total_supply(&self) -> u64 {
+        self.total_supply.load(Ordering::SeqCst)
+    }
+
+    pub fn balance(&self, account: &str) -> u64 {
+        self.accounts.read_key(account).balances.get(account).copied().unwrap_or(0)
+    }
+
+    /// Snapshot of every account's balance, in no particular order
+    pub fn balances(&self) -> Vec<(String, u64)> {
+        self.accounts.read_all().iter()
+            .flat_map(|shard| shard.balances.iter().map(|(account, balance)| (account.clone(), *balance)))
+            .collect()
+    }
+
+    pub fn escrow(&self, job_id: u64) -> Option<Escrow> {
+        self.escrows.read_key(&job_id).get(&job_id).cloned()
+    }
+
+    pub fn total_escrowed(&self) -> u64 {
+        self.escrows.read_all().iter().flat_map(|shard| shard.values()).map(|e| e.amount).sum()
+    }
+
+    /// All balance movements on `account`, oldest first
+    pub fn history(&self, account: &str) -> Vec<LedgerEntry> {
+        self.accounts.read_key(account).history.iter().filter(|e| e.account == account).cloned().collect()
+    }
+
+    /// Issue new tokens to an account; the only operation that changes total supply.
+    /// Total supply bounds every balance, so it is the only overflow to check.
+    pub fn mint(&self, account: &str, amount: u64) -> SpectreResult<()> {
+        let mut shard = self.accounts.lock_key(account);
+        // Raised under the account's lock so check_invariants never sees one without the other
+        self.total_supply
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |supply| supply.checked_add(amount))
+            .map_err(|total_supply| SpectreError::SupplyOverflow { total_supply, minted: amount })?;
+        self.record(&mut shard, &Leg::credit(account, LedgerEntryKind::Mint, amount), None);
+        Ok(())
+    }
+
+    pub fn transfer(&self, from: &str, to: &str, amount: u64) -> SpectreResult<()> {
+        self.apply(None, &[
+            Leg::debit(from, LedgerEntryKind::TransferOut, amount),
+            Leg::credit(to, LedgerEntryKind::TransferIn, amount),
+        ])
+    }
+
+    /// Move `amount` from the depositor's balance into escrow for `job_id`
+    pub fn lock_escrow(&self, job_id: u64, depositor: &str, amount: u64) -> SpectreResult<()> {
+        let mut escrows = self.escrows.lock_key(&job_id);
+        if escrows.contains_key(&job_id) {
+            return Err(SpectreError::DuplicateJob { job_id });
+        }
+        self.apply(Some(job_id), &[Leg::debit(depositor, LedgerEntryKind::EscrowLock, amount)])?;
+        escrows.insert(job_id, Escrow { job_id, depositor: depositor.to_string(), amount });
+        Ok(())
+    }
+
+    /// Pay out a job's escrow to `recipient`, minus the protocol fee
+    pub fn release_escrow(&self, job_id: u64, recipient: &str) -> SpectreResult<Settlement> {
+        let mut escrows = self.escrows.lock_key(&job_id);
+        let escrow = escrows.get(&job_id).ok_or(SpectreError::EscrowNotFound { job_id })?;
+        let fee = protocol_fee(escrow.amount, self.fee_bps);
+        let payout = escrow.amount - fee;
+        let mut legs = vec![Leg::credit(recipient, LedgerEntryKind::EscrowRelease, payout)];
+        if fee > 0 {
+            legs.push(Leg::credit(TREASURY_ACCOUNT, LedgerEntryKind::ProtocolFee, fee));
+        }
+        self.apply(Some(job_id), &legs)?;
+        escrows.remove(&job_id);
+        Ok(Settlement { payout, fee })
+    }
+
+    /// Return a job's escrow in full to the original depositor
+    pub fn refund_escrow(&self, job_id: u64) -> SpectreResult<u64> {
+        let mut escrows = self.escrows.lock_key(&job_id);
+        let escrow = escrows.get(&job_id).ok_or(SpectreError::EscrowNotFound { job_id })?;
+        self.apply(Some(job_id), &[Leg::credit(&escrow.depositor, LedgerEntryKind::EscrowRefund, escrow.amount)])?;
+        let amount = escrow.amount;
+        escrows.remove(&job_id);
+        Ok(amount)
+    }
+
+    /// Move `amount` from an agent's balance into the stake vault
+    pub fn bond_stake(&self, agent_id: &str, amount: u64) -> SpectreResult<()> {
+        self.apply(None, &[
+            Leg::debit(agent_id, LedgerEntryKind::StakeBond, amount),
+            Leg::credit(STAKE_VAULT_ACCOUNT, LedgerEntryKind::StakeBond, amount),
+        ])
+    }
+
+    /// Return unbonded stake from the vault to the agent
+    pub fn unbond_stake(&self, agent_id: &str, amount: u64) -> SpectreResult<()> {
+        self.apply(None, &[
+            Leg::debit(STAKE_VAULT_ACCOUNT, LedgerEntryKind::StakeUnbond, amount),
+            Leg::credit(agent_id, LedgerEntryKind::StakeUnbond, amount),
+        ])
+    }
+
+    /// Pay out slashed stake to the harmed requester and [`BURN_ACCOUNT`]
+    pub fn apply_slash(&self, job_id: Option<u64>, requester: Option<&str>, to_requester: u64, burned: u64) -> SpectreResult<()> {
+        let slashed = to_requester.checked_add(burned).ok_or_else(|| SpectreError::InsufficientFunds {
+            account: STAKE_VAULT_ACCOUNT.to_string(),
+            balance: self.balance(STAKE_VAULT_ACCOUNT),
+            required: u64::MAX,
+        })?;
+        let mut legs = vec![Leg::debit(STAKE_VAULT_ACCOUNT, LedgerEntryKind::SlashBurn, slashed)];
+        let burned = match requester {
+            Some(requester) if to_requester > 0 => {
+                legs.push(Leg::credit(requester, LedgerEntryKind::SlashPayout, to_requester));
+                burned
+            }
+            _ => slashed,
+        };
+        if burned > 0 {
+            legs.push(Leg::credit(BURN_ACCOUNT, LedgerEntryKind::SlashBurn, burned));
+        }
+        self.apply(job_id, &legs)
+    }
+
+    /// Check that no tokens were created or destroyed outside of minting,
+    /// against a consistent snapshot of every shard
+    pub fn check_invariants(&self) -> SpectreResult<()> {
+        let escrows = self.escrows.read_all();
+        let accounts = self.accounts.read_all();
+        let accounted = accounts.iter().flat_map(|shard| shard.balances.values()).sum::<u64>()
+            + escrows.iter().flat_map(|shard| shard.values()).map(|e| e.amount).sum::<u64>();
+        let total_supply = self.total_supply();
+        if accounted != total_supply {
+            return Err(SpectreError::LedgerInvariantViolated { total_supply, accounted });
+        }
+        Ok(())
+    }
+
+    // Apply every leg or none: lock all accounts involved, check each debit
+    // is covered, then move the tokens
+    fn apply(&self, job_id: Option<u64>, legs: &[Leg]) -> SpectreResult<()> {
+        let mut shards = self.accounts.lock_many(legs.iter().map(|leg| self.accounts.index_of(leg.account)));
+        for leg in legs.iter().filter(|leg| leg.debit) {
+            let balance = shards[&self.accounts.index_of(leg.account)].balances.get(leg.account).copied().unwrap_or(0);
+            if balance < leg.amount {
+                return Err(SpectreError::InsufficientFunds {
+                    account: leg.account.to_string(),
+                    balance,
+                    required: leg.amount,
+                });
+            }
+        }
+        for leg in legs {
+            let shard = shards.get_mut(&self.accounts.index_of(leg.account)).expect("account shard is locked");
+            self.record(shard, leg, job_id);
+        }
+        Ok(())
+    }
+
+    // Change one balance (already checked to cover a debit) and log it
+    fn record(&self, shard: &mut AccountShard, leg: &Leg, job_id: Option<u64>) {
+        let balance = shard.balances.entry(leg.account.to_string()).or_insert(0);
+        if leg.debit {
+            *balance -= leg.amount;
+        } else {
+            *balance += leg.amount;
+        }
+        let balance_after = *balance;
+        shard.history.push(LedgerEntry {
+            seq: self.next_seq.fetch_add(1, Ordering::SeqCst),
+            account: leg.account.to_string(),
+            kind: leg.kind,
+            amount: leg.amount,
+            job_id,
+            balance_after,
+        });
+    }
+}
+
+// Tells markets apart in ShardVersions, so versions from one never pass for another's
+static NEXT_MARKET_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Where every shard of one [`ParthMarket`] stood when
+/// [`changes_since`](ParthMarket::changes_since) read it. The default
+/// matches no market, so everything counts as changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShardVersions {
+    pub market: u64,
+    pub jobs: Vec<u64>,
+    pub agents: Vec<u64>,
+    pub accounts: Vec<u64>,
+}
+
+/// Full contents of the shards that changed since a [`ShardVersions`], all
+/// read under one set of locks so jobs, agents and balances agree.
+#[derive(Debug, Clone, Default)]
+pub struct ParthChanges {
+    /// Versions to pass to the next `changes_since`
+    pub versions: ShardVersions,
+    /// Whether every shard was read, because the versions came from
+    /// elsewhere; entries missing from a full read no longer exist
+    pub full: bool,
+    /// Every job in each changed job shard, by shard index
+    pub jobs: Vec<(usize, Vec<Job>)>,
+    /// Every agent in each changed agent shard, by shard index
+    pub agents: Vec<(usize, Vec<(String, AgentCapability)>)>,
+    /// Every balance in each changed account shard, by shard index
+    pub balances: Vec<(usize, Vec<(String, u64)>)>,
+}
+
+/// [`SpectreMarket`](crate::SpectreMarket) over PARTH-style sharded state.
+///
+/// State is a shallow hierarchy: jobs are sharded by job id, agents (each
+/// shard an [`SDKeyRegistry`] holding the agents that hash to it) by agent
+/// id, and balances and escrows by account and job id in a [`ShardedLedger`].
+/// Every method takes `&self`, so the market can sit in an `Arc` and serve
+/// many tokio tasks: claims on jobs in different shards by agents in
+/// different shards proceed in parallel, while two agents racing for the
+/// same job serialize on its shard and exactly one wins.
+///
+/// Operations lock the job's shard, then the catalog, then the agent's
+/// shard, then the event log, then ledger shards, never the other way round,
+/// which keeps cross-shard work deadlock-free. Every agent shard is built by the same
+/// factory, so they share the clock and verifiers. The model catalog has one
+/// shared copy: change it with [`update_catalog`](ParthMarket::update_catalog),
+/// which hands the new version to every agent shard at once.
+///
+/// Agent shards are only reachable through the market's own methods, each of
+/// which moves a registry and the ledger together, so bonded stake always
+/// matches the stake vault.
+///
+/// Every shard logs to one [`EventLog`], the first agent shard's, so the
+/// market has a single sequence of [`DomainEvent`]s that
+/// [`SpectreMarket::from_events`](crate::SpectreMarket::from_events) can
+/// rebuild it from. Each change is logged before the locks it took are
+/// released, and changes that credit a balance are logged in the same step
+/// as the credit, so the log never spends tokens before recording where
+/// they came from. Writes straight to [`ledger`](ParthMarket::ledger) are not
+/// recorded.
+pub struct ParthMarket {
+    id: u64,
+    jobs: Shards<HashMap<u64, Job>>,
+    catalog: RwLock<ModelCatalog>,
+    agents: Shards<SDKeyRegistry>,
+    pub ledger: ShardedLedger,
+    events: EventLog,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for ParthMarket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ParthMarket {
+    pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// Market whose job timestamps and proof checks read time from `clock`
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self::with_shards(DEFAULT_SHARD_COUNT, move || SDKeyRegistry::with_clock(clock.clone()))
+    }
+
+    /// Market with `shard_count` shards per level, building each agent
+    /// shard's registry with `make_registry`. The first registry's catalog
+    /// and event log are the market's.
+    pub fn with_shards(shard_count: usize, make_registry: impl FnMut() -> SDKeyRegistry) -> Self {
+        let agents = Shards::new(shard_count, make_registry);
+        let (clock, catalog, events) = {
+            let first = agents.read(0);
+            (first.clock(), first.catalog.clone(), first.events().clone())
+        };
+        // Shards start out agreeing with the first one's catalog, and log to its log
+        for index in 1..agents.count() {
+            let mut registry = agents.lock(index);
+            registry.catalog = catalog.clone();
+            registry.share_events(events.clone());
+        }
+        ParthMarket {
+            id: NEXT_MARKET_ID.fetch_add(1, Ordering::SeqCst),
+            jobs: Shards::new(shard_count, HashMap::new),
+            catalog: RwLock::new(catalog),
+            agents,
+            ledger: ShardedLedger::new(shard_count, DEFAULT_PROTOCOL_FEE_BPS),
+            events,
+            clock,
+        }
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.jobs.count()
+    }
+
+    /// Domain events for every change made through the market, across all shards
+    pub fn events(&self) -> &EventLog {
+        &self.events
+    }
+
+    /// Credit new tokens to `account`
+    pub fn mint(&self, account: &str, amount: u64) -> SpectreResult<()> {
+        self.events.emit_with(self.clock.now(), || {
+            self.ledger.mint(account, amount)?;
+            Ok(((), vec![DomainEvent::Minted { account: account.to_string(), amount }]))
+        })
+    }
+
+    /// Move tokens between two accounts' balances
+    pub fn transfer(&self, from: &str, to: &str, amount: u64) -> SpectreResult<()> {
+        self.events.emit_with(self.clock.now(), || {
+            self.ledger.transfer(from, to, amount)?;
+            Ok(((), vec![DomainEvent::Transferred { from: from.to_string(), to: to.to_string(), amount }]))
+        })
+    }
+
+    /// Register an agent, bonding `stake` from its balance
+    pub fn register_agent(&self, agent_id: String, algos: Vec<String>, stake: u64, public_key: VerifyingKey) -> SpectreResult<()> {
+        let mut registry = self.agents.lock_key(agent_id.as_str());
+        if registry.verified_agents.contains_key(&agent_id) {
+            return Err(SpectreError::AgentAlreadyRegistered { agent_id });
+        }
+        self.ledger.bond_stake(&agent_id, stake)?;
+        let registered = registry.enroll(agent_id.clone(), algos, stake, public_key, true);
+        if registered.is_err() {
+            self.ledger.unbond_stake(&agent_id, stake)?;
+        }
+        registered
+    }
+
+    /// Bond more of a registered agent's balance as stake
+    pub fn deposit_stake(&self, agent_id: &str, amount: u64) -> SpectreResult<()> {
+        let mut registry = self.agents.lock_key(agent_id);
+        if !registry.verified_agents.contains_key(agent_id) {
+            return Err(SpectreError::AgentNotRegistered { agent_id: agent_id.to_string() });
+        }
+        self.ledger.bond_stake(agent_id, amount)?;
+        registry.add_stake(agent_id, amount, true)
+    }
+
+    /// Snapshot of the model catalog every agent shard checks against
+    pub fn catalog(&self) -> ModelCatalog {
+        self.catalog.read().unwrap().clone()
+    }
+
+    /// Change the model catalog (add a model or build, retire or deprecate
+    /// one) for every agent shard at once. If `f` fails nothing changes.
+    ///
+    /// Catalog changes must go through here rather than one shard's
+    /// registry, or shards would disagree on which models they accept.
+    pub fn update_catalog<R>(&self, f: impl FnOnce(&mut ModelCatalog) -> SpectreResult<R>) -> SpectreResult<R> {
+        let mut catalog = self.catalog.write().unwrap();
+        let mut updated = catalog.clone();
+        let result = f(&mut updated)?;
+        for mut registry in self.agents.lock_all() {
+            registry.catalog = updated.clone();
+        }
+        *catalog = updated;
+        Ok(result)
+    }
+
+    /// Snapshot of an agent's stake: bonded, locked per job and unbonding
+    pub fn stake_account(&self, agent_id: &str) -> Option<StakeAccount> {
+        self.agents.read_key(agent_id).stakes.account(agent_id).cloned()
+    }
+
+    /// Every slash recorded against an agent, oldest first
+    pub fn slash_events(&self, agent_id: &str) -> Vec<SlashEvent> {
+        self.agents.read_key(agent_id).stakes.slash_events(agent_id).into_iter().cloned().collect()
+    }
+
+    /// An agent's overall reputation score
+    pub fn reputation_of(&self, agent_id: &str) -> f64 {
+        self.agents.read_key(agent_id).reputation_of(agent_id)
+    }
+
+    /// Start unbonding some of an agent's free stake; returns when it can be withdrawn
+    pub fn request_unstake(&self, agent_id: &str, amount: u64) -> SpectreResult<u64> {
+        self.agents.lock_key(agent_id).request_unstake(agent_id, amount)
+    }
+
+    /// Pay every unbonding entry whose period has elapsed back to the agent;
+    /// returns the amount paid
+    pub fn withdraw_unstaked(&self, agent_id: &str) -> SpectreResult<u64> {
+        let now = self.clock.now();
+        let mut registry = self.agents.lock_key(agent_id);
+        self.events.emit_with(now, || {
+            // Pay first, so a ledger that can't leaves the stake still claimable
+            self.ledger.unbond_stake(agent_id, registry.stakes.claimable(agent_id, now))?;
+            let amount = registry.stakes.claim_unbonded(agent_id, now);
+            let withdrawn = DomainEvent::StakeWithdrawn { agent_id: agent_id.to_string(), amount, on_ledger: true };
+            Ok((amount, if amount > 0 { vec![withdrawn] } else { vec![] }))
+        })
+    }
+
+    /// Post a job, locking its reward into escrow
+    pub fn post_job(&self, id: u64, requester: String, algo: String, reward: u64) -> SpectreResult<()> {
+        self.post_job_request(JobRequest::new(id, requester, algo, reward))
+    }
+
+    /// Post a job with full terms (capacity requirement, expiry)
+    pub fn post_job_request(&self, request: JobRequest) -> SpectreResult<()> {
+        let id = request.id;
+        let now = self.clock.now();
+        let mut jobs = self.jobs.lock_key(&id);
+        if jobs.contains_key(&id) {
+            return Err(SpectreError::DuplicateJob { job_id: id });
+        }
+        self.catalog.read().unwrap().available(&request.required_algo, now)?;
+        self.ledger.lock_escrow(id, &request.requester, request.reward_tokens)?;
+        let job = Job::from_request(request, now);
+        jobs.insert(id, job.clone());
+        self.events.emit(now, DomainEvent::JobPosted { job });
+        Ok(())
+    }
+
+    /// Snapshot of one job
+    pub fn job(&self, job_id: u64) -> Option<Job> {
+        self.jobs.read_key(&job_id).get(&job_id).cloned()
+    }
+
+    /// Snapshot of every job, by id
+    pub fn jobs(&self) -> Vec<Job> {
+        let mut jobs: Vec<Job> = self.jobs.read_all().iter().flat_map(|shard| shard.values().cloned()).collect();
+        jobs.sort_unstable_by_key(|job| job.id);
+        jobs
+    }
+
+    /// Snapshot of every registered agent's capability, in no particular order
+    pub fn agents(&self) -> Vec<(String, AgentCapability)> {
+        self.agents.read_all().iter()
+            .flat_map(|registry| registry.verified_agents.iter().map(|(agent_id, capability)| (agent_id.clone(), capability.clone())))
+            .collect()
+    }
+
+    /// Jobs, agents and balances in the shards that changed since `since`,
+    /// with every job, agent and account shard locked at once (in the usual
+    /// order) so the three levels are read at the same instant
+    pub fn changes_since(&self, since: &ShardVersions) -> ParthChanges {
+        let jobs = self.jobs.read_all();
+        let agents = self.agents.read_all();
+        let accounts = self.ledger.accounts.read_all();
+        let versions = ShardVersions {
+            market: self.id,
+            jobs: self.jobs.versions(),
+            agents: self.agents.versions(),
+            accounts: self.ledger.accounts.versions(),
+        };
+        let full = since.market != self.id;
+        // Indices of the shards whose version moved, or all of them
+        let changed = |now: &[u64], then: &[u64]| -> Vec<usize> {
+            (0..now.len()).filter(|&index| full || then.get(index) != Some(&now[index])).collect()
+        };
+        ParthChanges {
+            full,
+            jobs: changed(&versions.jobs, &since.jobs).into_iter()
+                .map(|index| (index, jobs[index].values().cloned().collect()))
+                .collect(),
+            agents: changed(&versions.agents, &since.agents).into_iter()
+                .map(|index| (index, agents[index].verified_agents.iter().map(|(agent_id, capability)| (agent_id.clone(), capability.clone())).collect()))
+                .collect(),
+            balances: changed(&versions.accounts, &since.accounts).into_iter()
+                .map(|index| (index, accounts[index].balances.iter().map(|(account, balance)| (account.clone(), *balance)).collect()))
+                .collect(),
+            versions,
+        }
+    }
+
+    /// Claim an open job for an eligible agent (Open → Claimed)
+    pub fn claim_job(&self, job_id: u64, agent_id: &str) -> SpectreResult<()> {
+        let now = self.clock.now();
+        let mut jobs = self.jobs.lock_key(&job_id);
+        let job = jobs.get_mut(&job_id).ok_or(SpectreError::JobNotFound { job_id })?;
+        let mut registry = self.agents.lock_key(agent_id);
+        job.claim(&mut registry, agent_id, now)?;
+        self.events.emit(now, DomainEvent::JobClaimed { job_id, agent_id: agent_id.to_string() });
+        Ok(())
+    }
+
+    /// Verify the agent's SDKey proof for the job and claim it atomically
+    pub fn attempt_job_execution(&self, job_id: u64, proof: &SDKeyProof) -> SpectreResult<String> {
+        let now = self.clock.now();
+        let mut jobs = self.jobs.lock_key(&job_id);
+        let job = jobs.get_mut(&job_id).ok_or(SpectreError::JobNotFound { job_id })?;
+        job.ensure_claimable(now)?;
+        ensure_proof_for_job(proof, job_id)?;
+
+        let mut registry = self.agents.lock_key(proof.agent_id.as_str());
+        registry.verify_proof_for_model(proof, &job.required_algo, job.task_complexity)?;
+        job.claim(&mut registry, &proof.agent_id, now)?;
+        self.events.emit(now, DomainEvent::JobClaimed { job_id, agent_id: proof.agent_id.clone() });
+        Ok(claimed_message(&proof.agent_id, job))
+    }
+
+    /// Submit the claiming agent's result with its SDKey proof (Claimed → ResultSubmitted)
+    pub fn submit_result(&self, job_id: u64, proof: SDKeyProof) -> SpectreResult<()> {
+        let now = self.clock.now();
+        let mut jobs = self.jobs.lock_key(&job_id);
+        let job = jobs.get_mut(&job_id).ok_or(SpectreError::JobNotFound { job_id })?;
+        job.ensure_assignee(&proof.agent_id)?;
+        ensure_proof_for_job(&proof, job_id)?;
+        job.ensure_transition(JobStatus::ResultSubmitted)?;
+        self.agents.lock_key(proof.agent_id.as_str()).admit_proof(&proof)?;
+        job.transition(JobStatus::ResultSubmitted, now)?;
+        job.result_proof = Some(proof.clone());
+        self.events.emit(now, DomainEvent::ResultSubmitted { job_id, proof });
+        Ok(())
+    }
+
+    /// Check the submitted proof (ResultSubmitted → Verified/Rejected),
+    /// slashing the agent's stake for a bad one
+    pub fn verify_result(&self, job_id: u64) -> SpectreResult<()> {
+        let now = self.clock.now();
+        let mut jobs = self.jobs.lock_key(&job_id);
+        let job = jobs.get_mut(&job_id).ok_or(SpectreError::JobNotFound { job_id })?;
+        let agent_id = job.result_proof.as_ref().map(|proof| proof.agent_id.clone()).unwrap_or_default();
+        let mut registry = self.agents.lock_key(agent_id.as_str());
+        let (verdict, offense) = job.verify_submission(&mut registry, now);
+        if let Some(offense) = offense {
+            self.slash(&mut registry, &agent_id, offense, job, now)?;
+        }
+        verdict
+    }
+
+    /// Resolve a verified or rejected job's escrow (→ Settled)
+    pub fn settle_job(&self, job_id: u64) -> SpectreResult<JobSettlement> {
+        let now = self.clock.now();
+        let mut jobs = self.jobs.lock_key(&job_id);
+        let job = jobs.get_mut(&job_id).ok_or(SpectreError::JobNotFound { job_id })?;
+        self.settle(job, now)
+    }
+
+    /// Attach the settlement transaction that must confirm on Psy before a
+    /// verified or rejected job settles; see [`apply_tx_update`](ParthMarket::apply_tx_update)
+    pub fn settle_on_confirmation(&self, job_id: u64, tx_id: String) -> SpectreResult<()> {
+        let mut jobs = self.jobs.lock_key(&job_id);
+        let job = jobs.get_mut(&job_id).ok_or(SpectreError::JobNotFound { job_id })?;
+        if !job.status.can_transition_to(JobStatus::Settled) {
+            return Err(SpectreError::InvalidJobTransition { job_id, from: job.status, to: JobStatus::Settled });
+        }
+        job.settlement_tx = Some(tx_id.clone());
+        self.events.emit(self.clock.now(), DomainEvent::SettlementTxAttached { job_id, tx_id });
+        Ok(())
+    }
+
+    /// Settle the job waiting on `update`'s transaction once it confirms, or
+    /// detach the transaction if it failed so another can be attached.
+    ///
+    /// # Returns
+    /// `None` if the update isn't final or no job is waiting on it, otherwise
+    /// the job and how settling it went
+    pub fn apply_tx_update(&self, update: &PsyTxUpdate) -> Option<(u64, SpectreResult<JobSettlement>)> {
+        if !update.status.is_terminal() {
+            return None;
+        }
+        let now = self.clock.now();
+        for index in 0..self.jobs.count() {
+            let mut jobs = self.jobs.lock(index);
+            let Some(job) = jobs.values_mut().find(|job| {
+                job.settlement_tx.as_deref() == Some(update.tx_id.as_str()) && job.status.can_transition_to(JobStatus::Settled)
+            }) else {
+                continue;
+            };
+            let job_id = job.id;
+            if update.status != PsyTxStatus::Confirmed {
+                job.settlement_tx = None;
+                self.events.emit(now, DomainEvent::SettlementTxFailed { job_id, tx_id: update.tx_id.clone() });
+                return Some((job_id, Err(SpectreError::SettlementTxFailed {
+                    job_id,
+                    tx_id: update.tx_id.clone(),
+                    status: update.status.clone(),
+                })));
+            }
+            return Some((job_id, self.settle(job, now)));
+        }
+        None
+    }
+
+    // Pay out or refund a job's escrow and settle it, under its shard's lock
+    fn settle(&self, job: &mut Job, now: u64) -> SpectreResult<JobSettlement> {
+        let job_id = job.id;
+        if !job.status.can_transition_to(JobStatus::Settled) {
+            return Err(SpectreError::InvalidJobTransition { job_id, from: job.status, to: JobStatus::Settled });
+        }
+
+        if let Some(agent_id) = &job.assigned_agent {
+            self.agents.lock_key(agent_id.as_str()).stakes.release_job(agent_id, job_id);
+        }
+        self.events.emit_with(now, || {
+            let (settlement, event) = match (job.status, &job.assigned_agent) {
+                (JobStatus::Verified, Some(agent_id)) => {
+                    let paid = self.ledger.release_escrow(job_id, agent_id)?;
+                    (
+                        JobSettlement::Paid { agent_id: agent_id.clone(), payout: paid.payout, fee: paid.fee },
+                        DomainEvent::AgentRewarded { job_id, agent_id: agent_id.clone(), payout: paid.payout, fee: paid.fee },
+                    )
+                }
+                _ => {
+                    let amount = self.ledger.refund_escrow(job_id)?;
+                    (
+                        JobSettlement::Refunded { requester: job.requester.clone(), amount },
+                        DomainEvent::RequesterRefunded { job_id, requester: job.requester.clone(), amount },
+                    )
+                }
+            };
+            job.transition(JobStatus::Settled, now)?;
+            Ok((settlement, vec![event, DomainEvent::JobSettled { job_id }]))
+        })
+    }
+
+    /// Flag a verified job's result as disputed, counting against the agent's reputation
+    pub fn dispute_job(&self, job_id: u64) -> SpectreResult<()> {
+        let mut jobs = self.jobs.lock_key(&job_id);
+        let job = jobs.get_mut(&job_id).ok_or(SpectreError::JobNotFound { job_id })?;
+        let agent_id = match (&job.assigned_agent, job.entered_at(JobStatus::Verified)) {
+            (Some(agent_id), Some(_)) => agent_id.clone(),
+            _ => return Err(SpectreError::JobNotDisputable { job_id, status: job.status }),
+        };
+        if job.disputed {
+            return Err(SpectreError::JobAlreadyDisputed { job_id });
+        }
+        job.disputed = true;
+        self.events.emit(self.clock.now(), DomainEvent::JobDisputed { job_id, agent_id: agent_id.clone() });
+        self.agents.lock_key(agent_id.as_str()).record_outcome(&agent_id, &job.required_algo, ReputationOutcome::Dispute, None);
+        Ok(())
+    }
+
+    /// Expire every job past its deadline: refund the requester and, for a
+    /// claimed job, release the agent's stake lock and slash it for the miss.
+    /// Job shards are swept one at a time, so other work carries on meanwhile.
+    pub fn expire_jobs(&self, now: u64) -> ExpirySweep {
+        let mut sweep = ExpirySweep::default();
+        for index in 0..self.jobs.count() {
+            let mut jobs = self.jobs.lock(index);
+            for job in jobs.values_mut() {
+                if !job.is_expired_at(now) || !job.status.can_transition_to(JobStatus::Expired) {
+                    continue;
+                }
+                if let Err(e) = job.transition(JobStatus::Expired, now) {
+                    sweep.failed.push((job.id, e));
+                    continue;
+                }
+                sweep.expired.push(job.id);
+                self.events.emit(now, DomainEvent::JobExpired { job_id: job.id });
+                let refunded = self.events.emit_with(now, || {
+                    let amount = self.ledger.refund_escrow(job.id)?;
+                    Ok(((), vec![DomainEvent::RequesterRefunded { job_id: job.id, requester: job.requester.clone(), amount }]))
+                });
+                if let Err(e) = refunded {
+                    sweep.failed.push((job.id, e));
+                }
+                if let Some(agent_id) = &job.assigned_agent {
+                    let mut registry = self.agents.lock_key(agent_id.as_str());
+                    registry.stakes.release_job(agent_id, job.id);
+                    registry.record_outcome(agent_id, &job.required_algo, ReputationOutcome::Failure, None);
+                    if let Err(e) = self.slash(&mut registry, agent_id, SlashOffense::MissedDeadline, job, now) {
+                        sweep.failed.push((job.id, e));
+                    }
+                }
+            }
+        }
+        sweep.expired.sort_unstable();
+        sweep.failed.sort_unstable_by_key(|(job_id, _)| *job_id);
+        sweep
+    }
+
+    /// Withdraw an unclaimed job and refund its escrow (Open → Cancelled)
+    pub fn cancel_job(&self, job_id: u64) -> SpectreResult<u64> {
+        let now = self.clock.now();
+        let mut jobs = self.jobs.lock_key(&job_id);
+        let job = jobs.get_mut(&job_id).ok_or(SpectreError::JobNotFound { job_id })?;
+        if job.status == JobStatus::Claimed || job.status == JobStatus::ResultSubmitted {
+            return Err(SpectreError::JobAlreadyTaken { job_id });
+        }
+        job.transition(JobStatus::Cancelled, now)?;
+        self.events.emit_with(now, || {
+            let amount = self.ledger.refund_escrow(job_id)?;
+            Ok((amount, vec![
+                DomainEvent::JobCancelled { job_id },
+                DomainEvent::RequesterRefunded { job_id, requester: job.requester.clone(), amount },
+            ]))
+        })
+    }
+
+    // Slash an agent for `offense` on `job`, paying the requester and burn
+    // account out of the stake vault in the same step as the event recording
+    // it; a payout the ledger can't make leaves the stake alone
+    fn slash(&self, registry: &mut SDKeyRegistry, agent_id: &str, offense: SlashOffense, job: &Job, now: u64) -> SpectreResult<SlashEvent> {
+        let (to_requester, burned) = registry.stakes.preview_slash(agent_id, offense, Some(&job.requester))?;
+        self.events.emit_with(now, || {
+            self.ledger.apply_slash(Some(job.id), Some(&job.requester), to_requester, burned)?;
+            let event = registry.stakes.slash(agent_id, offense, Some(job.id), Some(&job.requester), now)?;
+            Ok((event.clone(), vec![DomainEvent::AgentSlashed { event, on_ledger: true }]))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multi_account_moves_are_all_or_nothing() {
+        let ledger = ShardedLedger::new(8, 100);
+        ledger.mint("requester", 1_000).unwrap();
+        ledger.lock_escrow(1, "requester", 400).unwrap();
+        assert_eq!(ledger.lock_escrow(1, "requester", 1).unwrap_err().code(), "DUPLICATE_JOB");
+
+        // The vault can't cover the slash, so the requester isn't paid either
+        assert_eq!(ledger.apply_slash(Some(1), Some("requester"), 50, 50).unwrap_err().code(), "INSUFFICIENT_FUNDS");
+        assert_eq!(ledger.balance("requester"), 600);
+
+        assert_eq!(ledger.release_escrow(1, "agent").unwrap(), Settlement { payout: 396, fee: 4 });
+        assert_eq!((ledger.balance("agent"), ledger.balance(TREASURY_ACCOUNT)), (396, 4));
+        assert!(ledger.escrow(1).is_none());
+        assert_eq!(ledger.history("requester").len(), 2);
+        ledger.check_invariants().unwrap();
+
+        // Supply can't overflow, and a failed mint changes nothing
+        let err = ledger.mint("whale", u64::MAX).unwrap_err();
+        assert_eq!(err.code(), "SUPPLY_OVERFLOW");
+        assert_eq!((ledger.total_supply(), ledger.balance("whale")), (1_000, 0));
+        assert_eq!(ledger.apply_slash(None, None, u64::MAX, 1).unwrap_err().code(), "INSUFFICIENT_FUNDS");
+        ledger.check_invariants().unwrap();
+    }
+
+    #[test]
+    fn test_catalog_updates_reach_every_agent_shard() {
+        let market = ParthMarket::with_shards(4, SDKeyRegistry::new);
+        market.update_catalog(|catalog| catalog.deprecate_model("LLaMA-3-70B", 0)).unwrap();
+        for index in 0..market.shard_count() {
+            assert!(market.agents.read(index).catalog.get("LLaMA-3-70B").unwrap().deprecated_at.is_some());
+        }
+    }
+}
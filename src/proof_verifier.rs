@@ -0,0 +1,120 @@
+// src/proof_verifier.rs
+// Pluggable proof verification backends for the SDKey registry
+
+use crate::error::{SpectreError, SpectreResult};
+use crate::sdkey_verification::{AgentCapability, SDKeyProof};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use sha2::{Digest, Sha256};
+
+/// Checks that a proof's execution evidence is genuine for the agent that
+/// submitted it.
+///
+/// The registry performs the backend-independent checks (the agent's
+/// signature, model hash, capacity, reputation, stake, freshness, replay)
+/// and delegates checking the execution evidence to a `ProofVerifier`,
+/// chosen per model type.
+pub trait ProofVerifier: Send + Sync {
+    /// Short identifier for logs and diagnostics
+    fn name(&self) -> &'static str;
+
+    fn verify(&self, proof: &SDKeyProof, agent: &AgentCapability) -> SpectreResult<()>;
+}
+
+/// Format-only check: accepts any `zk_`-prefixed proof of 10+ characters.
+/// Offers no security; useful for demos and load tests.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MockPrefixVerifier;
+
+impl ProofVerifier for MockPrefixVerifier {
+    fn name(&self) -> &'static str {
+        "mock-prefix"
+    }
+
+    fn verify(&self, proof: &SDKeyProof, _agent: &AgentCapability) -> SpectreResult<()> {
+        if proof.execution_proof.starts_with("zk_") && proof.execution_proof.len() >= 10 {
+            Ok(())
+        } else {
+            Err(invalid(proof, "execution proof failed ZK format check"))
+        }
+    }
+}
+
+/// Ed25519 signature over [`SDKeyProof::signing_message`], checked against
+/// the agent's registered public key. The registry default, and the check
+/// [`SDKeyRegistry::authenticate`](crate::SDKeyRegistry::authenticate) runs
+/// on every proof before any backend.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SignatureVerifier;
+
+impl ProofVerifier for SignatureVerifier {
+    fn name(&self) -> &'static str {
+        "ed25519-signature"
+    }
+
+    fn verify(&self, proof: &SDKeyProof, agent: &AgentCapability) -> SpectreResult<()> {
+        let key_bytes: [u8; 32] = hex::decode(&agent.public_key).ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| invalid(proof, "registered public key is malformed"))?;
+        let verifying_key = VerifyingKey::from_bytes(&key_bytes)
+            .map_err(|_| invalid(proof, "registered public key is malformed"))?;
+
+        let sig_bytes: [u8; 64] = hex::decode(&proof.signature).ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| invalid(proof, "signature is not 64 hex-encoded bytes"))?;
+        let signature = Signature::from_bytes(&sig_bytes);
+
+        verifying_key.verify(&proof.signing_message(), &signature)
+            .map_err(|_| invalid(proof, "signature does not match agent's registered key"))
+    }
+}
+
+/// Requires `execution_proof` to be the hash commitment produced by
+/// [`execution_commitment`], binding the model, result, nonce and job.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HashCommitmentVerifier;
+
+impl ProofVerifier for HashCommitmentVerifier {
+    fn name(&self) -> &'static str {
+        "hash-commitment"
+    }
+
+    fn verify(&self, proof: &SDKeyProof, _agent: &AgentCapability) -> SpectreResult<()> {
+        if proof.execution_proof == execution_commitment(proof) {
+            Ok(())
+        } else {
+            Err(invalid(proof, "execution proof does not match hash commitment"))
+        }
+    }
+}
+
+/// Layers several verifiers; a proof must pass all of them.
+pub struct AllOf(pub Vec<Box<dyn ProofVerifier>>);
+
+impl ProofVerifier for AllOf {
+    fn name(&self) -> &'static str {
+        "all-of"
+    }
+
+    fn verify(&self, proof: &SDKeyProof, agent: &AgentCapability) -> SpectreResult<()> {
+        self.0.iter().try_for_each(|v| v.verify(proof, agent))
+    }
+}
+
+/// Commitment over a proof's model hash, result digest, nonce and job id,
+/// as checked by [`HashCommitmentVerifier`].
+pub fn execution_commitment(proof: &SDKeyProof) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(b"SPECTRE-EXEC-COMMIT-v1");
+    hasher.update(proof.model_hash.as_bytes());
+    hasher.update(proof.result_digest.as_bytes());
+    hasher.update(proof.nonce.to_be_bytes());
+    hasher.update(proof.job_id.to_be_bytes());
+    format!("zk_{}", hex::encode(hasher.finalize()))
+}
+
+fn invalid(proof: &SDKeyProof, reason: &str) -> SpectreError {
+    SpectreError::InvalidProof {
+        agent_id: proof.agent_id.clone(),
+        reason: reason.to_string(),
+    }
+}
@@ -0,0 +1,136 @@
+// src/psy_accounts.rs
+// Per-account signing keys and nonce allocation for Psy transactions
+
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+
+/// Signing keys for the accounts a client submits on behalf of.
+#[derive(Default)]
+pub struct PsyKeyring {
+    keys: HashMap<String, SigningKey>,
+}
+
+impl PsyKeyring {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sign `account`'s transactions with `key`, replacing any previous key
+    pub fn insert(&mut self, account: &str, key: SigningKey) {
+        self.keys.insert(account.to_string(), key);
+    }
+
+    /// Create a fresh keypair for `account`, returning its public half
+    pub fn generate(&mut self, account: &str) -> VerifyingKey {
+        let key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let public_key = key.verifying_key();
+        self.insert(account, key);
+        public_key
+    }
+
+    pub fn key(&self, account: &str) -> Option<&SigningKey> {
+        self.keys.get(account)
+    }
+
+    pub fn verifying_key(&self, account: &str) -> Option<VerifyingKey> {
+        self.key(account).map(SigningKey::verifying_key)
+    }
+
+    pub fn accounts(&self) -> impl Iterator<Item = &str> {
+        self.keys.keys().map(String::as_str)
+    }
+}
+
+// Nonce bookkeeping for one account
+#[derive(Debug, Default)]
+struct AccountNonces {
+    next: u64,
+    // Handed out but never used on-chain, lowest first
+    released: BTreeSet<u64>,
+}
+
+/// Hands out sequential per-account nonces.
+///
+/// Concurrent submitters each get a distinct nonce. A nonce whose
+/// transaction the chain rejected can be [released](NonceManager::release)
+/// and is handed out again before any new one, so failures don't leave gaps.
+/// When the local view may have drifted (a restart, an ambiguous network
+/// failure), [`resync`](NonceManager::resync) adopts the chain's next nonce.
+#[derive(Debug, Default)]
+pub struct NonceManager {
+    accounts: Mutex<HashMap<String, AccountNonces>>,
+}
+
+impl NonceManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allocate the lowest nonce not currently handed out for `account`
+    pub fn reserve(&self, account: &str) -> u64 {
+        let mut accounts = self.accounts.lock().unwrap();
+        let nonces = accounts.entry(account.to_string()).or_default();
+        if let Some(nonce) = nonces.released.pop_first() {
+            return nonce;
+        }
+        nonces.next += 1;
+        nonces.next - 1
+    }
+
+    /// Return a reserved nonce whose transaction never reached the chain
+    pub fn release(&self, account: &str, nonce: u64) {
+        let mut accounts = self.accounts.lock().unwrap();
+        let nonces = accounts.entry(account.to_string()).or_default();
+        if nonce + 1 == nonces.next {
+            nonces.next -= 1;
+            // Trailing released nonces fold back into `next` as well
+            while nonces.next > 0 && nonces.released.remove(&(nonces.next - 1)) {
+                nonces.next -= 1;
+            }
+        } else if nonce < nonces.next {
+            nonces.released.insert(nonce);
+        }
+    }
+
+    /// Nonce the next `reserve` will return, without allocating it
+    pub fn peek(&self, account: &str) -> u64 {
+        let accounts = self.accounts.lock().unwrap();
+        accounts.get(account)
+            .map(|nonces| nonces.released.first().copied().unwrap_or(nonces.next))
+            .unwrap_or(0)
+    }
+
+    /// Adopt the chain's next expected nonce for `account`, forgetting
+    /// released nonces at or above it
+    pub fn resync(&self, account: &str, chain_next: u64) {
+        let mut accounts = self.accounts.lock().unwrap();
+        let nonces = accounts.entry(account.to_string()).or_default();
+        nonces.next = chain_next;
+        nonces.released.retain(|nonce| *nonce < chain_next);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nonces_are_sequential_and_reuse_released_gaps() {
+        let nonces = NonceManager::new();
+        assert_eq!((nonces.reserve("a"), nonces.reserve("a"), nonces.reserve("a")), (0, 1, 2));
+        assert_eq!(nonces.reserve("b"), 0);
+
+        nonces.release("a", 1);
+        assert_eq!(nonces.peek("a"), 1);
+        assert_eq!(nonces.reserve("a"), 1);
+        assert_eq!(nonces.reserve("a"), 3);
+
+        nonces.release("a", 2);
+        nonces.release("a", 3);
+        assert_eq!(nonces.reserve("a"), 2);
+
+        nonces.resync("a", 10);
+        assert_eq!(nonces.reserve("a"), 10);
+    }
+}
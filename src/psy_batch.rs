@@ -0,0 +1,162 @@
+// src/psy_batch.rs
+// Automatic batching of individual Psy transaction submissions
+
+use crate::error::{SpectreError, SpectreResult};
+use crate::psy_integration::{PsyChain, PsyTransaction};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+// Submissions buffered per slot of max_batch_size before callers wait
+const QUEUE_BATCHES: usize = 4;
+
+/// When a [`BatchSubmitter`] flushes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchConfig {
+    /// Flush as soon as this many transactions are waiting
+    pub max_batch_size: usize,
+    /// Longest the first transaction of a batch waits for others to join it
+    pub linger: Duration,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        BatchConfig {
+            max_batch_size: 100,
+            linger: Duration::from_millis(5),
+        }
+    }
+}
+
+/// Counters kept by a [`BatchSubmitter`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BatchMetrics {
+    /// `submit_batch` calls made
+    pub batches: u64,
+    /// Transactions sent across all batches
+    pub transactions: u64,
+    /// Transactions the chain rejected individually
+    pub rejected: u64,
+    /// Batches that failed as a whole
+    pub failed_batches: u64,
+    pub largest_batch: usize,
+}
+
+impl BatchMetrics {
+    pub fn avg_batch_size(&self) -> f64 {
+        if self.batches == 0 {
+            return 0.0;
+        }
+        self.transactions as f64 / self.batches as f64
+    }
+
+    /// Calls avoided compared to submitting every transaction on its own
+    pub fn round_trips_saved(&self) -> u64 {
+        self.transactions - self.batches
+    }
+}
+
+type Submission = (PsyTransaction, oneshot::Sender<SpectreResult<String>>);
+
+/// Coalesces individual submissions into [`PsyChain::submit_batch`] calls.
+///
+/// Each [`submit`](BatchSubmitter::submit) waits for its own result, so
+/// callers keep one-at-a-time semantics while the chain sees one call per
+/// batch. A batch is flushed when it reaches `max_batch_size` or when its
+/// first transaction has waited `linger`. Clones share the same queue.
+#[derive(Clone)]
+pub struct BatchSubmitter {
+    queue: mpsc::Sender<Submission>,
+    metrics: Arc<Mutex<BatchMetrics>>,
+    worker: Arc<JoinHandle<()>>,
+}
+
+impl BatchSubmitter {
+    /// Start the batching task on the current tokio runtime
+    pub fn spawn(chain: Arc<dyn PsyChain>, config: BatchConfig) -> Self {
+        let max_batch_size = config.max_batch_size.max(1);
+        let (queue, mut submissions) = mpsc::channel::<Submission>(max_batch_size * QUEUE_BATCHES);
+        let metrics = Arc::new(Mutex::new(BatchMetrics::default()));
+        let worker_metrics = metrics.clone();
+
+        let worker = tokio::spawn(async move {
+            while let Some(first) = submissions.recv().await {
+                let deadline = Instant::now() + config.linger;
+                let mut batch = vec![first];
+                while batch.len() < max_batch_size {
+                    match tokio::time::timeout_at(deadline, submissions.recv()).await {
+                        Ok(Some(submission)) => batch.push(submission),
+                        // Linger elapsed or every submitter is gone
+                        _ => break,
+                    }
+                }
+                flush(chain.as_ref(), batch, &worker_metrics).await;
+            }
+        });
+
+        BatchSubmitter { queue, metrics, worker: Arc::new(worker) }
+    }
+
+    /// Queue a transaction for the next batch and wait for its result
+    pub async fn submit(&self, tx: PsyTransaction) -> SpectreResult<String> {
+        let (reply, result) = oneshot::channel();
+        if self.queue.send((tx, reply)).await.is_err() {
+            return Err(stopped());
+        }
+        result.await.unwrap_or_else(|_| Err(stopped()))
+    }
+
+    /// Build and submit a transfer on behalf of an agent
+    pub async fn submit_agent_transaction(&self, agent_id: String, task_type: String, amount: u64) -> SpectreResult<String> {
+        self.submit(PsyTransaction::new(agent_id, task_type, amount)).await
+    }
+
+    pub fn metrics(&self) -> BatchMetrics {
+        self.metrics.lock().unwrap().clone()
+    }
+
+    /// Stop the batching task; queued submissions fail
+    pub fn shutdown(&self) {
+        self.worker.abort();
+    }
+}
+
+async fn flush(chain: &dyn PsyChain, batch: Vec<Submission>, metrics: &Mutex<BatchMetrics>) {
+    let (txs, replies): (Vec<_>, Vec<_>) = batch.into_iter().unzip();
+    let size = txs.len();
+    let outcome = chain.submit_batch(txs).await;
+
+    {
+        let mut metrics = metrics.lock().unwrap();
+        metrics.batches += 1;
+        metrics.transactions += size as u64;
+        metrics.largest_batch = metrics.largest_batch.max(size);
+        match &outcome {
+            Ok(results) => metrics.rejected += results.iter().filter(|r| r.is_err()).count() as u64,
+            Err(_) => metrics.failed_batches += 1,
+        }
+    }
+
+    match outcome {
+        Ok(results) => {
+            for (reply, result) in replies.into_iter().zip(results) {
+                let _ = reply.send(result);
+            }
+        }
+        Err(error) => {
+            for reply in replies {
+                let _ = reply.send(Err(error.clone()));
+            }
+        }
+    }
+}
+
+fn stopped() -> SpectreError {
+    SpectreError::NetworkFailure {
+        endpoint: "batch submitter".to_string(),
+        reason: "batching task stopped".to_string(),
+    }
+}
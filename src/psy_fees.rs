@@ -0,0 +1,132 @@
+// src/psy_fees.rs
+// Fee market for Psy transactions: base fee, priority tips and estimates
+
+use crate::psy_integration::PsyTransaction;
+use serde::{Deserialize, Serialize};
+
+/// Lowest base fee per gas a block can have
+pub const MIN_BASE_FEE: u64 = 1;
+
+// The base fee moves by at most 1/8 per block
+const BASE_FEE_CHANGE_DENOMINATOR: u64 = 8;
+
+/// How quickly an agent needs its transaction included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FeeUrgency {
+    /// Fine to wait behind most traffic
+    Low,
+    /// Typical tip
+    Normal,
+    /// Ahead of most traffic
+    High,
+    /// Outbid everything seen recently, e.g. for latency-sensitive arbitrage
+    Instant,
+}
+
+/// Suggested fees per unit of gas, drawn from recent blocks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PsyFeeEstimate {
+    /// Base fee of the next block
+    pub base_fee: u64,
+    /// Tips paid by included transactions at the 25th, 50th and 90th percentiles
+    pub low_tip: u64,
+    pub normal_tip: u64,
+    pub high_tip: u64,
+    /// Highest tip paid
+    pub max_tip: u64,
+    /// Recent blocks the tips were sampled from
+    pub sample_blocks: u32,
+}
+
+impl PsyFeeEstimate {
+    /// Estimate from the effective tips of recently included transactions
+    pub fn from_tips(base_fee: u64, mut tips: Vec<u64>, sample_blocks: u32) -> Self {
+        tips.sort_unstable();
+        let percentile = |p: usize| if tips.is_empty() { 0 } else { tips[(tips.len() - 1) * p / 100] };
+        PsyFeeEstimate {
+            base_fee,
+            low_tip: percentile(25),
+            normal_tip: percentile(50),
+            high_tip: percentile(90),
+            max_tip: tips.last().copied().unwrap_or(0),
+            sample_blocks,
+        }
+    }
+
+    pub fn priority_fee(&self, urgency: FeeUrgency) -> u64 {
+        match urgency {
+            FeeUrgency::Low => self.low_tip,
+            FeeUrgency::Normal => self.normal_tip,
+            FeeUrgency::High => self.high_tip,
+            FeeUrgency::Instant => self.max_tip.saturating_add(1),
+        }
+    }
+
+    /// Fee cap that still covers the tip after the base fee doubles
+    pub fn max_fee(&self, urgency: FeeUrgency) -> u64 {
+        self.base_fee.saturating_mul(2).saturating_add(self.priority_fee(urgency))
+    }
+
+    /// Set `tx`'s tip for `urgency`, and its fee cap unless the sender chose one
+    pub fn apply(&self, tx: &mut PsyTransaction, urgency: FeeUrgency) {
+        tx.priority_fee = self.priority_fee(urgency);
+        if tx.max_fee == 0 {
+            tx.max_fee = self.max_fee(urgency);
+        }
+    }
+}
+
+/// Base fee of the block after one with `base_fee` that included `txs`
+/// transactions: it rises when blocks run fuller than `target_txs` and
+/// falls when they run emptier.
+pub fn next_base_fee(base_fee: u64, txs: usize, target_txs: usize) -> u64 {
+    let target = target_txs.max(1) as u64;
+    let used = txs as u64;
+    let next = if used > target {
+        let delta = (base_fee.saturating_mul(used - target) / target / BASE_FEE_CHANGE_DENOMINATOR).max(1);
+        base_fee.saturating_add(delta)
+    } else {
+        base_fee - base_fee.saturating_mul(target - used) / target / BASE_FEE_CHANGE_DENOMINATOR
+    };
+    next.max(MIN_BASE_FEE)
+}
+
+/// Tip per gas `tx` pays at `base_fee`: its priority fee, capped by what its
+/// fee cap leaves after the base fee. `None` if the cap is below the base fee.
+pub fn effective_tip(tx: &PsyTransaction, base_fee: u64) -> Option<u64> {
+    if tx.max_fee == 0 {
+        return Some(tx.priority_fee);
+    }
+    tx.max_fee.checked_sub(base_fee).map(|headroom| headroom.min(tx.priority_fee))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_fee_tracks_demand_and_estimates_use_percentiles() {
+        assert_eq!(next_base_fee(800, 100, 100), 800);
+        assert_eq!(next_base_fee(800, 200, 100), 900);
+        assert_eq!(next_base_fee(800, 0, 100), 700);
+        assert_eq!(next_base_fee(1, 0, 100), MIN_BASE_FEE);
+        assert_eq!(next_base_fee(1, 101, 100), 2);
+
+        let estimate = PsyFeeEstimate::from_tips(10, (1..=100).collect(), 5);
+        assert_eq!((estimate.low_tip, estimate.normal_tip, estimate.high_tip, estimate.max_tip), (25, 50, 90, 100));
+        assert_eq!(estimate.priority_fee(FeeUrgency::Instant), 101);
+
+        let mut tx = PsyTransaction::new("agent_001".to_string(), "arb".to_string(), 1);
+        estimate.apply(&mut tx, FeeUrgency::High);
+        assert_eq!((tx.priority_fee, tx.max_fee), (90, 110));
+        assert_eq!(effective_tip(&tx, 30), Some(80));
+        assert_eq!(effective_tip(&tx, 111), None);
+
+        // Extreme fees saturate rather than overflow
+        let extreme = PsyFeeEstimate::from_tips(u64::MAX, vec![u64::MAX], 1);
+        assert_eq!(extreme.priority_fee(FeeUrgency::Instant), u64::MAX);
+        assert_eq!(extreme.max_fee(FeeUrgency::Instant), u64::MAX);
+        assert_eq!(next_base_fee(u64::MAX, 200, 100), u64::MAX);
+        assert!(next_base_fee(u64::MAX, 0, 100) < u64::MAX);
+    }
+}
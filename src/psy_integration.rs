@@ -1,96 +1,741 @@
 // src/psy_integration.rs
-// Basic Psy Protocol Testnet Integration
+// Psy Protocol Integration: chain abstraction and JSON-RPC testnet client
 
+use crate::error::{SpectreError, SpectreResult};
+use crate::psy_accounts::{NonceManager, PsyKeyring};
+use crate::psy_fees::{FeeUrgency, PsyFeeEstimate};
+use crate::psy_retry::{is_retryable, RetryPolicy};
+use crate::psy_rpc::{
+    BatchItemOutcome, JsonRpcRequest, JsonRpcResponse, METHOD_ESTIMATE_FEES, METHOD_GET_ACCOUNT_NONCE, METHOD_GET_NETWORK_STATS,
+    METHOD_GET_TRANSACTION_RECEIPT, METHOD_GET_TRANSACTION_STATUS, METHOD_NOT_FOUND, METHOD_SUBMIT_BATCH, METHOD_SUBMIT_TRANSACTION,
+};
+use crate::psy_stats::PsyTraffic;
+use async_trait::async_trait;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use futures::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Public Psy Protocol testnet RPC endpoint
+pub const DEFAULT_TESTNET_URL: &str = "https://testnet-rpc.psy.finance";
+
+/// Environment variable that overrides the RPC endpoint in [`PsyClient::from_env`]
+pub const PSY_RPC_URL_ENV: &str = "PSY_RPC_URL";
+
+// Buffered status updates per subscriber before slow subscribers start skipping
+const UPDATE_CHANNEL_CAPACITY: usize = 1024;
+
+/// Final transactions a [`PsyClient`] keeps before forgetting the oldest
+pub const DEFAULT_ARCHIVE_CAPACITY: usize = 10_000;
+
+// Domain separator so transaction signatures can't be replayed as anything else
+const TX_DOMAIN: &[u8] = b"SPECTRE-PSY-TX-v1";
+
+/// A transfer submitted on behalf of an agent, who is the sending account.
+///
+/// Signed transactions carry the sender's Ed25519 public key and a signature
+/// over [`signing_message`](PsyTransaction::signing_message); the chain
+/// checks both and refuses to accept the same sender nonce twice.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PsyTransaction {
     pub tx_id: String,
     pub agent_id: String,
     pub task_type: String,
     pub amount: u64,
     pub status: PsyTxStatus,
+    /// Position in the sender's sequence of transactions
+    #[serde(default)]
+    pub nonce: u64,
+    /// Most the sender pays per unit of gas, base fee and tip together;
+    /// 0 accepts whatever the base fee is
+    #[serde(default)]
+    pub max_fee: u64,
+    /// Tip per unit of gas on top of the base fee, for faster inclusion
+    #[serde(default)]
+    pub priority_fee: u64,
+    /// Hex Ed25519 key of the sender, once signed
+    #[serde(default)]
+    pub public_key: Option<String>,
+    /// Hex Ed25519 signature over `signing_message()`
+    #[serde(default)]
+    pub signature: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl PsyTransaction {
+    /// A new pending, unsigned transaction with a fresh client-generated id
+    pub fn new(agent_id: String, task_type: String, amount: u64) -> Self {
+        PsyTransaction {
+            tx_id: format!("psy_{}", rand::random::<u32>()),
+            agent_id,
+            task_type,
+            amount,
+            status: PsyTxStatus::Pending,
+            nonce: 0,
+            max_fee: 0,
+            priority_fee: 0,
+            public_key: None,
+            signature: None,
+        }
+    }
+
+    /// Canonical bytes the sender signs: every field except status, key and
+    /// signature, length-prefixed so no two distinct transactions serialize
+    /// identically
+    pub fn signing_message(&self) -> Vec<u8> {
+        let mut msg = Vec::with_capacity(96);
+        msg.extend_from_slice(TX_DOMAIN);
+        for field in [&self.tx_id, &self.agent_id, &self.task_type] {
+            msg.extend_from_slice(&(field.len() as u32).to_be_bytes());
+            msg.extend_from_slice(field.as_bytes());
+        }
+        for number in [self.amount, self.nonce, self.max_fee, self.priority_fee] {
+            msg.extend_from_slice(&number.to_be_bytes());
+        }
+        msg
+    }
+
+    /// Sign as the sender with `key`
+    pub fn sign(&mut self, key: &SigningKey) {
+        self.public_key = Some(hex::encode(key.verifying_key().to_bytes()));
+        self.signature = Some(hex::encode(key.sign(&self.signing_message()).to_bytes()));
+    }
+
+    pub fn is_signed(&self) -> bool {
+        self.signature.is_some()
+    }
+
+    /// Check the signature against the embedded public key
+    pub fn verify_signature(&self) -> SpectreResult<VerifyingKey> {
+        let invalid = |reason: &str| SpectreError::InvalidTransactionSignature {
+            tx_id: self.tx_id.clone(),
+            reason: reason.to_string(),
+        };
+        let key_bytes: [u8; 32] = self.public_key.as_deref()
+            .and_then(|key| hex::decode(key).ok())
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| invalid("public key is missing or not 32 hex-encoded bytes"))?;
+        let key = VerifyingKey::from_bytes(&key_bytes).map_err(|_| invalid("public key is malformed"))?;
+        let sig_bytes: [u8; 64] = self.signature.as_deref()
+            .and_then(|sig| hex::decode(sig).ok())
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| invalid("signature is missing or not 64 hex-encoded bytes"))?;
+        key.verify(&self.signing_message(), &Signature::from_bytes(&sig_bytes))
+            .map_err(|_| invalid("signature does not match the transaction"))?;
+        Ok(key)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PsyTxStatus {
+    /// Waiting in the mempool
     Pending,
+    /// In a block that hasn't reached finality yet
+    Included,
+    /// Executed successfully and final
     Confirmed,
-    Failed,
+    /// Executed unsuccessfully and final; `code` is machine-readable
+    Reverted { code: String, reason: String },
+    /// Removed from the mempool without being included
+    Dropped { reason: String },
+    /// Superseded in the mempool by another transaction, e.g. a fee bump
+    Replaced { by: String },
+    /// Left the mempool because it waited too long to be included
+    Expired,
+}
+
+impl PsyTxStatus {
+    /// Final statuses never change again
+    pub fn is_terminal(&self) -> bool {
+        !matches!(self, PsyTxStatus::Pending | PsyTxStatus::Included)
+    }
+
+    /// Why the transaction didn't confirm, if it ended any other way
+    pub fn failure_reason(&self) -> Option<String> {
+        match self {
+            PsyTxStatus::Pending | PsyTxStatus::Included | PsyTxStatus::Confirmed => None,
+            PsyTxStatus::Reverted { code, reason } => Some(format!("reverted ({}): {}", code, reason)),
+            PsyTxStatus::Dropped { reason } => Some(format!("dropped: {}", reason)),
+            PsyTxStatus::Replaced { by } => Some(format!("replaced by {}", by)),
+            PsyTxStatus::Expired => Some("expired in the mempool".to_string()),
+        }
+    }
+}
+
+/// Outcome of a transaction once the chain has included it in a block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PsyReceipt {
+    pub tx_id: String,
+    pub status: PsyTxStatus,
+    pub block_height: u64,
+    /// Position within the block
+    pub tx_index: u32,
+    /// Whether execution succeeded (known at inclusion, final once confirmed)
+    pub success: bool,
+    pub gas_used: u64,
+    pub fee_used: u64,
+    /// Blocks on top of and including the transaction's block
+    pub confirmations: u64,
 }
 
-// Simulated Psy Protocol Client (would connect to real testnet)
+/// A transaction changed status.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PsyTxUpdate {
+    pub tx_id: String,
+    pub status: PsyTxStatus,
+}
+
+/// Stream of status updates from [`PsyChain::subscribe_all`] or [`PsyChain::watch`]
+pub type PsyTxStream = Pin<Box<dyn Stream<Item = PsyTxUpdate> + Send>>;
+
+/// Network activity over a recent window of traffic.
+///
+/// Latencies run from submission to finality; throughput counts
+/// transactions that executed, whether or not they reverted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PsyNetworkStats {
+    /// Distinct agents that submitted within the window
+    pub active_agents: u32,
+    pub tps: u32,
+    pub avg_latency_ms: u32,
+    pub parth_depth: u32,
+    #[serde(default)]
+    pub latency_p50_ms: u32,
+    #[serde(default)]
+    pub latency_p95_ms: u32,
+    #[serde(default)]
+    pub latency_p99_ms: u32,
+    /// Share of finished transactions that reverted, were dropped or expired
+    #[serde(default)]
+    pub failure_rate: f64,
+}
+
+/// A Psy Protocol chain that agent transactions can be submitted to.
+///
+/// Implemented by [`PsyClient`] (JSON-RPC to a real node) and by
+/// [`PsySimulator`](crate::PsySimulator) (in-process), so services and tests
+/// can hold an `Arc<dyn PsyChain>` and run against either.
+#[async_trait]
+pub trait PsyChain: Send + Sync {
+    /// Submit a transaction to Psy's parallel execution layer; returns its id
+    async fn submit_transaction(&self, tx: PsyTransaction) -> SpectreResult<String>;
+
+    /// Submit many transactions in one call.
+    ///
+    /// The outer error means the batch as a whole couldn't be delivered;
+    /// otherwise each transaction gets its own result, in order, and one
+    /// rejected transaction doesn't affect the others.
+    async fn submit_batch(&self, txs: Vec<PsyTransaction>) -> SpectreResult<Vec<SpectreResult<String>>> {
+        let mut results = Vec::with_capacity(txs.len());
+        for tx in txs {
+            results.push(self.submit_transaction(tx).await);
+        }
+        Ok(results)
+    }
+
+    /// Current status, or `None` if the chain has never seen the transaction
+    async fn transaction_status(&self, tx_id: &str) -> SpectreResult<Option<PsyTxStatus>>;
+
+    /// Receipt once the transaction is in a block, or `None` while it is still pending
+    async fn transaction_receipt(&self, tx_id: &str) -> SpectreResult<Option<PsyReceipt>>;
+
+    async fn network_stats(&self) -> SpectreResult<PsyNetworkStats>;
+
+    /// Next nonce the chain expects from `account`: one past the highest it
+    /// has accepted, or 0 for an account it hasn't seen
+    async fn account_nonce(&self, account: &str) -> SpectreResult<u64>;
+
+    /// Next block's base fee and the tips recent blocks paid
+    async fn fee_estimate(&self) -> SpectreResult<PsyFeeEstimate>;
+
+    /// Submit with fees set from a fresh estimate for `urgency`; a fee cap
+    /// already on `tx` is kept
+    async fn submit_with_urgency(&self, mut tx: PsyTransaction, urgency: FeeUrgency) -> SpectreResult<String> {
+        self.fee_estimate().await?.apply(&mut tx, urgency);
+        self.submit_transaction(tx).await
+    }
+
+    /// Status updates for every transaction this chain handle observes. A
+    /// subscriber that falls too far behind is sent every transaction's
+    /// current status in place of the updates it missed.
+    fn subscribe_all(&self) -> PsyTxStream;
+
+    /// Status transitions of one transaction, starting with its current
+    /// status and ending after it reaches a terminal one
+    async fn watch(&self, tx_id: &str) -> SpectreResult<PsyTxStream> {
+        // Subscribe before reading the status so no transition falls in between
+        let updates = self.subscribe_all();
+        let current = self.transaction_status(tx_id).await?;
+        Ok(watch_stream(tx_id.to_string(), current, updates))
+    }
+
+    /// Build and submit a transfer on behalf of an agent
+    async fn submit_agent_transaction(&self, agent_id: String, task_type: String, amount: u64) -> SpectreResult<String> {
+        self.submit_transaction(PsyTransaction::new(agent_id, task_type, amount)).await
+    }
+}
+
+/// JSON-RPC client for a Psy Protocol node.
+///
+/// Keeps the transactions it submitted in `pending_txs` and publishes a
+/// [`PsyTxUpdate`] to subscribers whenever a status query observes a change.
+/// Once a transaction is final it moves to a bounded archive, so a
+/// long-running client only holds what is still in flight plus the most
+/// recent `archive_capacity` outcomes.
+///
+/// Given a [`PsyKeyring`], the client signs unsigned transactions from
+/// accounts it holds keys for, allocating each a nonce from its
+/// [`NonceManager`]. Run [`spawn_status_poller`](PsyClient::spawn_status_poller) to have those
+/// queries made in the background so [`watch`](PsyChain::watch) and
+/// [`subscribe_all`](PsyChain::subscribe_all) streams advance on their own.
+///
+/// The same submissions and observed outcomes feed
+/// [`observed_stats`](PsyClient::observed_stats), which
+/// [`network_stats`](PsyChain::network_stats) falls back to when the node
+/// doesn't serve statistics itself.
 pub struct PsyClient {
-    pub testnet_url: String,
-    pub pending_txs: HashMap<String, PsyTransaction>,
+    endpoint: String,
+    http: reqwest::Client,
+    retry_policy: RetryPolicy,
+    next_request_id: AtomicU64,
+    pending_txs: Arc<Mutex<HashMap<String, PsyTransaction>>>,
+    archived_txs: Arc<Mutex<VecDeque<PsyTransaction>>>,
+    archive_capacity: usize,
+    keyring: PsyKeyring,
+    nonces: NonceManager,
+    // Transactions we signed whose submission failed ambiguously, by id
+    in_doubt: Mutex<HashMap<String, PsyTransaction>>,
+    updates: broadcast::Sender<PsyTxUpdate>,
+    traffic: Mutex<PsyTraffic>,
+    started: Instant,
+}
+
+impl Default for PsyClient {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PsyClient {
+    /// Client for the public Psy testnet
     pub fn new() -> Self {
+        Self::with_endpoint(DEFAULT_TESTNET_URL)
+    }
+
+    /// Client for the endpoint in `PSY_RPC_URL`, falling back to the testnet
+    pub fn from_env() -> Self {
+        match std::env::var(PSY_RPC_URL_ENV) {
+            Ok(endpoint) => Self::with_endpoint(&endpoint),
+            Err(_) => Self::new(),
+        }
+    }
+
+    pub fn with_endpoint(endpoint: &str) -> Self {
         PsyClient {
-            testnet_url: "https://testnet-rpc.psy.finance".to_string(),
-            pending_txs: HashMap::new(),
+            endpoint: endpoint.to_string(),
+            http: reqwest::Client::new(),
+            retry_policy: RetryPolicy::default(),
+            next_request_id: AtomicU64::new(1),
+            pending_txs: Arc::new(Mutex::new(HashMap::new())),
+            archived_txs: Arc::new(Mutex::new(VecDeque::new())),
+            archive_capacity: DEFAULT_ARCHIVE_CAPACITY,
+            keyring: PsyKeyring::new(),
+            nonces: NonceManager::new(),
+            in_doubt: Mutex::new(HashMap::new()),
+            updates: broadcast::channel(UPDATE_CHANNEL_CAPACITY).0,
+            traffic: Mutex::new(PsyTraffic::default()),
+            started: Instant::now(),
         }
     }
 
-    // Submit transaction to Psy Protocol's parallel execution layer
-    pub async fn submit_agent_transaction(&mut self, agent_id: String, task_type: String, amount: u64) -> Result<String, String> {
-        let tx_id = format!("psy_{}", rand::random::<u32>());
-        
-        let tx = PsyTransaction {
-            tx_id: tx_id.clone(),
-            agent_id,
-            task_type,
-            amount,
-            status: PsyTxStatus::Pending,
-        };
+    /// Replace the default retry, backoff and timeout policy
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Sign transactions from the accounts in `keyring`
+    pub fn with_keyring(mut self, keyring: PsyKeyring) -> Self {
+        self.keyring = keyring;
+        self
+    }
+
+    /// Keep at most `capacity` final transactions in the archive
+    pub fn with_archive_capacity(mut self, capacity: usize) -> Self {
+        self.archive_capacity = capacity;
+        self
+    }
+
+    /// Compute [`observed_stats`](PsyClient::observed_stats) over the last `window`
+    pub fn with_stats_window(mut self, window: Duration) -> Self {
+        self.traffic = Mutex::new(PsyTraffic::new(window.as_millis() as u64));
+        self
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    pub fn keyring(&self) -> &PsyKeyring {
+        &self.keyring
+    }
 
-        // In real implementation, this would make HTTP request to Psy testnet
-        // For now, simulate network delay and success
-        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
-        
-        self.pending_txs.insert(tx_id.clone(), tx);
-        
-        // Simulate Psy's PARTH (Parallel Ascending Recursive Tree Hierarchy)
-        // Each agent transaction gets its own execution path
-        println!("📡 Submitted to Psy PARTH: {}", tx_id);
-        
-        Ok(tx_id)
-    }
-
-    // Check transaction status on Psy Protocol
-    pub async fn check_transaction_status(&mut self, tx_id: &str) -> Option<PsyTxStatus> {
-        if let Some(tx) = self.pending_txs.get_mut(tx_id) {
-            // Simulate confirmation after short delay
-            if matches!(tx.status, PsyTxStatus::Pending) {
-                tx.status = if rand::random::<f32>() > 0.05 {
-                    PsyTxStatus::Confirmed
-                } else {
-                    PsyTxStatus::Failed
-                };
+    pub fn nonces(&self) -> &NonceManager {
+        &self.nonces
+    }
+
+    /// Reset `account`'s local nonce sequence to what the chain expects next
+    pub async fn resync_nonce(&self, account: &str) -> SpectreResult<u64> {
+        let next = self.account_nonce(account).await?;
+        self.nonces.resync(account, next);
+        Ok(next)
+    }
+
+    /// Statistics over this client's own submissions and the outcomes its
+    /// status queries have observed. `parth_depth` is 0: only the node knows it.
+    pub fn observed_stats(&self) -> PsyNetworkStats {
+        self.traffic.lock().unwrap().stats(self.elapsed_ms(), 0)
+    }
+
+    /// Snapshot of the submitted transactions that aren't final yet
+    pub fn pending_txs(&self) -> HashMap<String, PsyTransaction> {
+        self.pending_txs.lock().unwrap().clone()
+    }
+
+    /// Resume tracking transactions submitted before a restart (e.g. the
+    /// pending set recovered by a [`PersistentMarket`](crate::PersistentMarket)),
+    /// so status queries pick them up again. Nonces aren't touched:
+    /// [`resync_nonce`](PsyClient::resync_nonce) as after any restart.
+    pub fn restore_pending(&self, txs: impl IntoIterator<Item = PsyTransaction>) {
+        let mut pending = self.pending_txs.lock().unwrap();
+        for tx in txs {
+            pending.insert(tx.tx_id.clone(), tx);
+        }
+    }
+
+    /// Most recent final transactions, oldest first
+    pub fn archived_txs(&self) -> Vec<PsyTransaction> {
+        self.archived_txs.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Last known state of a transaction this client submitted, pending or archived
+    pub fn local_transaction(&self, tx_id: &str) -> Option<PsyTransaction> {
+        if let Some(tx) = self.pending_txs.lock().unwrap().get(tx_id) {
+            return Some(tx.clone());
+        }
+        self.archived_txs.lock().unwrap().iter().rev().find(|tx| tx.tx_id == tx_id).cloned()
+    }
+
+    /// Query the status of every non-final transaction this client submitted,
+    /// notifying subscribers of changes. Returns how many were queried.
+    ///
+    /// A transaction the node no longer knows is marked dropped.
+    pub async fn refresh_pending(&self) -> usize {
+        let unsettled: Vec<String> = self.pending_txs.lock().unwrap().keys().cloned().collect();
+        for tx_id in &unsettled {
+            // A failed query is simply repeated on the next refresh
+            if let Ok(None) = self.transaction_status(tx_id).await {
+                self.observe_status(tx_id, &PsyTxStatus::Dropped { reason: "no longer known to the node".to_string() });
             }
-            Some(tx.status.clone())
+        }
+        unsettled.len()
+    }
+
+    /// Call [`refresh_pending`](PsyClient::refresh_pending) every `interval`
+    /// until the returned task is aborted
+    pub fn spawn_status_poller(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let client = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval.max(Duration::from_millis(1)));
+            loop {
+                interval.tick().await;
+                client.refresh_pending().await;
+            }
+        })
+    }
+
+    // Send a JSON-RPC call under the retry policy. Every method is safe to
+    // repeat: reads have no effect and submissions are deduplicated by tx id.
+    async fn call<T: DeserializeOwned>(&self, method: &str, params: serde_json::Value) -> SpectreResult<T> {
+        self.retry_policy.run(&self.endpoint, |_| self.call_once(method, params.clone())).await
+    }
+
+    // One JSON-RPC round trip
+    async fn call_once<T: DeserializeOwned>(&self, method: &str, params: serde_json::Value) -> SpectreResult<T> {
+        let id = self.next_request_id.fetch_add(1, Ordering::Relaxed);
+        let request = JsonRpcRequest::new(id, method, params);
+        let response: JsonRpcResponse = self.http.post(&self.endpoint)
+            .json(&request)
+            .send().await
+            .map_err(|e| self.network_failure(e.to_string()))?
+            .json().await
+            .map_err(|e| self.network_failure(format!("malformed {} response: {}", method, e)))?;
+
+        if let Some(error) = response.error {
+            return Err(error.into_spectre_error(method));
+        }
+        if response.id != Some(id) {
+            return Err(self.network_failure(format!("{} response id {:?} does not match request {}", method, response.id, id)));
+        }
+        serde_json::from_value(response.result.unwrap_or_default())
+            .map_err(|e| self.network_failure(format!("malformed {} result: {}", method, e)))
+    }
+
+    // Sign an unsigned transaction with its sender's key, if we hold it. A
+    // retry of one we signed before keeps that nonce and signature, so the
+    // node sees the same transaction again. Returns whether the nonce is
+    // this submission's to hand back if the node refuses it.
+    fn prepare(&self, tx: &mut PsyTransaction) -> bool {
+        if tx.is_signed() {
+            return false;
+        }
+        let Some(key) = self.keyring.key(&tx.agent_id) else {
+            return false;
+        };
+        let in_doubt = self.in_doubt.lock().unwrap().get(&tx.tx_id).cloned();
+        let signed_before = in_doubt.clone().or_else(|| self.local_transaction(&tx.tx_id));
+        if let Some(signed) = signed_before.filter(|signed| signed.is_signed()) {
+            let same = PsyTransaction { nonce: signed.nonce, ..tx.clone() }.signing_message() == signed.signing_message();
+            if same {
+                tx.nonce = signed.nonce;
+                tx.public_key = signed.public_key;
+                tx.signature = signed.signature;
+                if in_doubt.is_some() {
+                    self.in_doubt.lock().unwrap().remove(&tx.tx_id);
+                }
+                return in_doubt.is_some();
+            }
+        }
+        tx.nonce = self.nonces.reserve(&tx.agent_id);
+        tx.sign(key);
+        true
+    }
+
+    // Hand back the nonce of a transaction the chain definitely didn't take.
+    // After an ambiguous failure it may have been used, so the signed
+    // transaction is kept for a retry to reuse; resync_nonce also recovers.
+    fn release_nonce(&self, tx: &PsyTransaction, error: &SpectreError) {
+        let nonce_used = match error {
+            SpectreError::RpcError { chain_code, .. } => chain_code.as_deref() == Some("NONCE_ALREADY_USED"),
+            other => other.code() == "NONCE_ALREADY_USED",
+        };
+        if nonce_used {
+            return;
+        }
+        if is_retryable(error) {
+            self.in_doubt.lock().unwrap().insert(tx.tx_id.clone(), tx.clone());
         } else {
-            None
+            self.nonces.release(&tx.agent_id, tx.nonce);
         }
     }
 
-    // Get Psy Protocol network stats (simulated)
-    pub fn get_network_stats(&self) -> PsyNetworkStats {
-        PsyNetworkStats {
-            active_agents: rand::random::<u32>() % 10000 + 5000,
-            tps: rand::random::<u32>() % 2000 + 1000,
-            avg_latency_ms: rand::random::<u32>() % 50 + 25,
-            parth_depth: 12,
+    // Remember a transaction the node accepted
+    fn track_submitted(&self, tx_id: &str, tx: PsyTransaction) {
+        self.traffic.lock().unwrap().record_submission(tx_id, &tx.agent_id, self.elapsed_ms());
+        let tx_id = tx_id.to_string();
+        self.pending_txs.lock().unwrap().insert(tx_id.clone(), PsyTransaction { tx_id: tx_id.clone(), ..tx });
+        let _ = self.updates.send(PsyTxUpdate { tx_id, status: PsyTxStatus::Pending });
+    }
+
+    // Record a status seen on-chain, notifying subscribers if it changed and
+    // archiving the transaction once it is final
+    fn observe_status(&self, tx_id: &str, status: &PsyTxStatus) {
+        let mut pending = self.pending_txs.lock().unwrap();
+        let Some(tx) = pending.get_mut(tx_id) else {
+            return;
+        };
+        if tx.status == *status {
+            return;
+        }
+        tx.status = status.clone();
+        self.traffic.lock().unwrap().record_status(tx_id, status, self.elapsed_ms());
+        let _ = self.updates.send(PsyTxUpdate { tx_id: tx_id.to_string(), status: status.clone() });
+        if status.is_terminal() {
+            let tx = pending.remove(tx_id).expect("transaction is pending");
+            drop(pending);
+            let mut archive = self.archived_txs.lock().unwrap();
+            archive.push_back(tx);
+            while archive.len() > self.archive_capacity {
+                archive.pop_front();
+            }
+        }
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    fn network_failure(&self, reason: String) -> SpectreError {
+        SpectreError::NetworkFailure {
+            endpoint: self.endpoint.clone(),
+            reason,
         }
     }
 }
 
-#[derive(Debug, Serialize)]
-pub struct PsyNetworkStats {
-    pub active_agents: u32,
-    pub tps: u32,
-    pub avg_latency_ms: u32,
-    pub parth_depth: u32,
-}
\ No newline at end of file
+#[async_trait]
+impl PsyChain for PsyClient {
+    async fn submit_transaction(&self, mut tx: PsyTransaction) -> SpectreResult<String> {
+        let allocated = self.prepare(&mut tx);
+        match self.call::<String>(METHOD_SUBMIT_TRANSACTION, serde_json::json!([tx])).await {
+            Ok(tx_id) => {
+                self.track_submitted(&tx_id, tx);
+                Ok(tx_id)
+            }
+            Err(e) => {
+                if allocated {
+                    self.release_nonce(&tx, &e);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    async fn submit_batch(&self, mut txs: Vec<PsyTransaction>) -> SpectreResult<Vec<SpectreResult<String>>> {
+        let allocated: Vec<bool> = txs.iter_mut().map(|tx| self.prepare(tx)).collect();
+        let outcomes = self.call::<Vec<BatchItemOutcome>>(METHOD_SUBMIT_BATCH, serde_json::json!([txs])).await
+            .and_then(|outcomes| {
+                if outcomes.len() == txs.len() {
+                    return Ok(outcomes);
+                }
+                Err(self.network_failure(format!(
+                    "{} returned {} results for {} transactions",
+                    METHOD_SUBMIT_BATCH, outcomes.len(), txs.len()
+                )))
+            });
+        let outcomes = match outcomes {
+            Ok(outcomes) => outcomes,
+            Err(e) => {
+                for (tx, _) in txs.iter().zip(&allocated).filter(|(_, allocated)| **allocated) {
+                    self.release_nonce(tx, &e);
+                }
+                return Err(e);
+            }
+        };
+        Ok(txs.into_iter().zip(outcomes).zip(allocated).map(|((tx, outcome), allocated)| match outcome.error {
+            Some(error) => {
+                let error = error.into_spectre_error(METHOD_SUBMIT_BATCH);
+                if allocated {
+                    self.release_nonce(&tx, &error);
+                }
+                Err(error)
+            }
+            None => {
+                self.track_submitted(&outcome.tx_id, tx);
+                Ok(outcome.tx_id)
+            }
+        }).collect())
+    }
+
+    async fn transaction_status(&self, tx_id: &str) -> SpectreResult<Option<PsyTxStatus>> {
+        let status: Option<PsyTxStatus> = self.call(METHOD_GET_TRANSACTION_STATUS, serde_json::json!([tx_id])).await?;
+        if let Some(status) = &status {
+            self.observe_status(tx_id, status);
+        }
+        Ok(status)
+    }
+
+    async fn transaction_receipt(&self, tx_id: &str) -> SpectreResult<Option<PsyReceipt>> {
+        let receipt: Option<PsyReceipt> = self.call(METHOD_GET_TRANSACTION_RECEIPT, serde_json::json!([tx_id])).await?;
+        if let Some(receipt) = &receipt {
+            self.observe_status(tx_id, &receipt.status);
+        }
+        Ok(receipt)
+    }
+
+    /// The node's statistics, or [`observed_stats`](PsyClient::observed_stats)
+    /// if the node doesn't implement the method
+    async fn network_stats(&self) -> SpectreResult<PsyNetworkStats> {
+        match self.call(METHOD_GET_NETWORK_STATS, serde_json::json!([])).await {
+            Err(SpectreError::RpcError { code: METHOD_NOT_FOUND, .. }) => Ok(self.observed_stats()),
+            result => result,
+        }
+    }
+
+    async fn account_nonce(&self, account: &str) -> SpectreResult<u64> {
+        self.call(METHOD_GET_ACCOUNT_NONCE, serde_json::json!([account])).await
+    }
+
+    async fn fee_estimate(&self) -> SpectreResult<PsyFeeEstimate> {
+        self.call(METHOD_ESTIMATE_FEES, serde_json::json!([])).await
+    }
+
+    fn subscribe_all(&self) -> PsyTxStream {
+        let (pending, archived) = (Arc::clone(&self.pending_txs), Arc::clone(&self.archived_txs));
+        broadcast_stream(self.updates.subscribe(), move || {
+            // Pending first: a transaction archived in between then shows its final status last
+            let mut current: Vec<PsyTxUpdate> = pending.lock().unwrap().values()
+                .map(|tx| PsyTxUpdate { tx_id: tx.tx_id.clone(), status: tx.status.clone() })
+                .collect();
+            current.extend(archived.lock().unwrap().iter().map(|tx| PsyTxUpdate { tx_id: tx.tx_id.clone(), status: tx.status.clone() }));
+            current
+        })
+    }
+}
+
+/// Adapt a broadcast receiver into a [`PsyTxStream`].
+///
+/// A subscriber that falls so far behind that updates were dropped for it
+/// skips the rest of its backlog and gets `current`'s statuses instead, the
+/// latest of every transaction the chain handle knows, before carrying on
+/// with new updates. It may see a status twice, but never misses the last one.
+pub(crate) fn broadcast_stream(
+    receiver: broadcast::Receiver<PsyTxUpdate>,
+    current: impl Fn() -> Vec<PsyTxUpdate> + Send + Sync + 'static,
+) -> PsyTxStream {
+    let current = Arc::new(current);
+    Box::pin(futures::stream::unfold((receiver, VecDeque::new()), move |(mut receiver, mut caught_up)| {
+        let current = Arc::clone(&current);
+        async move {
+            loop {
+                if let Some(update) = caught_up.pop_front() {
+                    return Some((update, (receiver, caught_up)));
+                }
+                match receiver.recv().await {
+                    Ok(update) => return Some((update, (receiver, caught_up))),
+                    Err(broadcast::error::RecvError::Lagged(_)) => {
+                        // Start listening afresh before reading, so nothing falls in between
+                        receiver = receiver.resubscribe();
+                        caught_up = current().into();
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        }
+    }))
+}
+
+// Narrow `updates` to one transaction: its current status first, then each
+// change, ending after the first terminal status
+fn watch_stream(tx_id: String, current: Option<PsyTxStatus>, updates: PsyTxStream) -> PsyTxStream {
+    let initial = current.clone().map(|status| PsyTxUpdate { tx_id: tx_id.clone(), status });
+    let finished = current.as_ref().is_some_and(PsyTxStatus::is_terminal);
+    let changes = futures::stream::unfold((updates, current, finished), move |(mut updates, last, finished)| {
+        let tx_id = tx_id.clone();
+        async move {
+            if finished {
+                return None;
+            }
+            while let Some(update) = updates.next().await {
+                if update.tx_id != tx_id || last.as_ref() == Some(&update.status) {
+                    continue;
+                }
+                let finished = update.status.is_terminal();
+                let last = Some(update.status.clone());
+                return Some((update, (updates, last, finished)));
+            }
+            None
+        }
+    });
+    Box::pin(futures::stream::iter(initial).chain(changes))
+}
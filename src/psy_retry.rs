@@ -0,0 +1,192 @@
+// src/psy_retry.rs
+// Retry, timeout and backoff policy for calls to a Psy node
+
+use crate::error::{SpectreError, SpectreResult};
+use crate::psy_rpc::{INTERNAL_ERROR, RATE_LIMITED};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::Duration;
+
+/// How a [`PsyClient`](crate::PsyClient) retries failed calls.
+///
+/// Each attempt is bounded by `call_timeout`. Retryable failures (see
+/// [`is_retryable`]) back off exponentially from `initial_backoff` up to
+/// `max_backoff`, randomized by `jitter` so a swarm of agents doesn't retry
+/// in lockstep. Submissions are safe to retry because transactions carry a
+/// client-generated id the chain deduplicates on.
+///
+/// Deserializing checks the policy with [`validate`](RetryPolicy::validate).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RetryPolicyFields")]
+pub struct RetryPolicy {
+    /// Total attempts including the first; 1 disables retries
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Finite, at least 1.0
+    pub multiplier: f64,
+    /// Fraction (0.0-1.0) of each backoff randomized away
+    pub jitter: f64,
+    pub call_timeout: Duration,
+}
+
+// Wire form of `RetryPolicy`, checked before it becomes one
+#[derive(Deserialize)]
+struct RetryPolicyFields {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+    call_timeout: Duration,
+}
+
+impl TryFrom<RetryPolicyFields> for RetryPolicy {
+    type Error = SpectreError;
+
+    fn try_from(fields: RetryPolicyFields) -> SpectreResult<Self> {
+        let policy = RetryPolicy {
+            max_attempts: fields.max_attempts,
+            initial_backoff: fields.initial_backoff,
+            max_backoff: fields.max_backoff,
+            multiplier: fields.multiplier,
+            jitter: fields.jitter,
+            call_timeout: fields.call_timeout,
+        };
+        policy.validate()?;
+        Ok(policy)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            multiplier: 2.0,
+            jitter: 0.2,
+            call_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A single attempt with the given timeout
+    pub fn no_retries(call_timeout: Duration) -> Self {
+        RetryPolicy { max_attempts: 1, call_timeout, ..Self::default() }
+    }
+
+    /// Reject a multiplier below 1.0 or not finite, and jitter outside 0.0-1.0
+    pub fn validate(&self) -> SpectreResult<()> {
+        if !self.multiplier.is_finite() || self.multiplier < 1.0 {
+            return Err(SpectreError::InvalidRetryPolicy {
+                reason: format!("multiplier must be finite and at least 1.0, got {}", self.multiplier),
+            });
+        }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            return Err(SpectreError::InvalidRetryPolicy {
+                reason: format!("jitter must be between 0.0 and 1.0, got {}", self.jitter),
+            });
+        }
+        Ok(())
+    }
+
+    /// Delay before retry number `retry` (1 for the first retry), before jitter.
+    /// Never more than `max_backoff`, whatever the multiplier.
+    pub fn base_backoff(&self, retry: u32) -> Duration {
+        let factor = self.multiplier.max(1.0).powi(retry.saturating_sub(1).min(i32::MAX as u32) as i32);
+        Duration::try_from_secs_f64(self.initial_backoff.as_secs_f64() * factor)
+            .map_or(self.max_backoff, |delay| delay.min(self.max_backoff))
+    }
+
+    /// Delay before retry number `retry`, with jitter applied
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let base = self.base_backoff(retry);
+        // NaN jitter counts as none
+        let jitter = if self.jitter.is_nan() { 0.0 } else { self.jitter.clamp(0.0, 1.0) };
+        if jitter == 0.0 {
+            return base;
+        }
+        let scale = 1.0 - jitter * rand::thread_rng().gen::<f64>();
+        Duration::try_from_secs_f64(base.as_secs_f64() * scale).unwrap_or(base)
+    }
+
+    /// Run `call` until it succeeds, fails fatally or runs out of attempts.
+    /// `call` receives the attempt number, starting at 1.
+    pub async fn run<T, F, Fut>(&self, endpoint: &str, mut call: F) -> SpectreResult<T>
+    where
+        F: FnMut(u32) -> Fut,
+        Fut: Future<Output = SpectreResult<T>>,
+    {
+        let attempts = self.max_attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            let outcome = match tokio::time::timeout(self.call_timeout, call(attempt)).await {
+                Ok(outcome) => outcome,
+                Err(_) => Err(SpectreError::NetworkFailure {
+                    endpoint: endpoint.to_string(),
+                    reason: format!("timed out after {:?}", self.call_timeout),
+                }),
+            };
+            match outcome {
+                Err(e) if attempt < attempts && is_retryable(&e) => {
+                    tokio::time::sleep(self.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                other => return other,
+            }
+        }
+    }
+}
+
+/// Whether a failed call may succeed if repeated unchanged: transport
+/// failures, timeouts, node-internal errors and rate limiting. Anything the
+/// chain rejected on its merits is fatal.
+pub fn is_retryable(error: &SpectreError) -> bool {
+    match error {
+        SpectreError::NetworkFailure { .. } => true,
+        SpectreError::RpcError { code, .. } => *code == INTERNAL_ERROR || *code == RATE_LIMITED,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_caps_and_jitters() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(1_000),
+            jitter: 0.5,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.base_backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.base_backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.base_backoff(10), Duration::from_millis(1_000));
+        for _ in 0..20 {
+            let delay = policy.backoff(2);
+            assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_millis(200));
+        }
+
+        // Huge or invalid factors never panic and stay capped
+        let wild = RetryPolicy { multiplier: f64::MAX, jitter: f64::NAN, ..policy.clone() };
+        assert_eq!(wild.backoff(u32::MAX), Duration::from_millis(1_000));
+        let nan = RetryPolicy { multiplier: f64::NAN, jitter: -1.0, ..policy.clone() };
+        assert_eq!(nan.backoff(3), Duration::from_millis(100));
+        assert_eq!(wild.validate().unwrap_err().code(), "INVALID_RETRY_POLICY");
+        assert_eq!(nan.validate().unwrap_err().code(), "INVALID_RETRY_POLICY");
+
+        // Deserializing checks the policy
+        let mut json = serde_json::to_value(&policy).unwrap();
+        assert_eq!(serde_json::from_value::<RetryPolicy>(json.clone()).unwrap(), policy);
+        json["jitter"] = serde_json::json!(1.5);
+        assert!(serde_json::from_value::<RetryPolicy>(json).is_err());
+
+        assert!(is_retryable(&SpectreError::RpcError { method: "m".into(), code: RATE_LIMITED, message: String::new(), chain_code: None }));
+        assert!(!is_retryable(&SpectreError::TransactionConflict { tx_id: "psy_1".into() }));
+    }
+}
@@ -0,0 +1,280 @@
+// src/psy_rpc.rs
+// Psy JSON-RPC wire format and a local mock RPC server
+
+use crate::error::{SpectreError, SpectreResult};
+use crate::psy_integration::{PsyChain, PsyTransaction};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use warp::Filter;
+
+pub const METHOD_SUBMIT_TRANSACTION: &str = "psy_submitTransaction";
+pub const METHOD_SUBMIT_BATCH: &str = "psy_submitBatch";
+pub const METHOD_GET_TRANSACTION_STATUS: &str = "psy_getTransactionStatus";
+pub const METHOD_GET_TRANSACTION_RECEIPT: &str = "psy_getTransactionReceipt";
+pub const METHOD_GET_NETWORK_STATS: &str = "psy_getNetworkStats";
+pub const METHOD_GET_ACCOUNT_NONCE: &str = "psy_getAccountNonce";
+pub const METHOD_ESTIMATE_FEES: &str = "psy_estimateFees";
+
+// Standard JSON-RPC 2.0 error codes
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+/// Node is shedding load; retry later
+pub const RATE_LIMITED: i64 = -32005;
+/// The chain refused the call; `data.code` carries the [`SpectreError::code`]
+pub const CHAIN_ERROR: i64 = -32000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonRpcRequest {
+    pub jsonrpc: String,
+    pub id: u64,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+impl JsonRpcRequest {
+    pub fn new(id: u64, method: &str, params: Value) -> Self {
+        JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id,
+            method: method.to_string(),
+            params,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl JsonRpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        JsonRpcError { code, message: message.into(), data: None }
+    }
+
+    /// The [`SpectreError::code`] carried by a [`CHAIN_ERROR`]
+    pub fn chain_code(&self) -> Option<&str> {
+        if self.code != CHAIN_ERROR {
+            return None;
+        }
+        self.data.as_ref()?.get("code")?.as_str()
+    }
+
+    pub fn into_spectre_error(self, method: &str) -> SpectreError {
+        SpectreError::RpcError {
+            method: method.to_string(),
+            code: self.code,
+            chain_code: self.chain_code().map(str::to_string),
+            message: self.message,
+        }
+    }
+}
+
+impl From<SpectreError> for JsonRpcError {
+    fn from(error: SpectreError) -> Self {
+        JsonRpcError {
+            code: CHAIN_ERROR,
+            message: error.to_string(),
+            data: Some(serde_json::json!({ "code": error.code() })),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonRpcResponse {
+    pub jsonrpc: String,
+    /// `None` only when the request was too malformed to read its id
+    pub id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<JsonRpcError>,
+}
+
+impl JsonRpcResponse {
+    pub fn success(id: u64, result: Value) -> Self {
+        JsonRpcResponse { jsonrpc: "2.0".to_string(), id: Some(id), result: Some(result), error: None }
+    }
+
+    pub fn failure(id: Option<u64>, error: JsonRpcError) -> Self {
+        JsonRpcResponse { jsonrpc: "2.0".to_string(), id, result: None, error: Some(error) }
+    }
+}
+
+/// Per-transaction result of `psy_submitBatch`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchItemOutcome {
+    pub tx_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<JsonRpcError>,
+}
+
+/// Serve one JSON-RPC request against `chain`
+pub async fn dispatch(chain: &dyn PsyChain, request: JsonRpcRequest) -> JsonRpcResponse {
+    let id = request.id;
+    if request.jsonrpc != "2.0" {
+        return JsonRpcResponse::failure(Some(id), JsonRpcError::new(INVALID_REQUEST, "jsonrpc must be \"2.0\""));
+    }
+    let result = match request.method.as_str() {
+        METHOD_SUBMIT_TRANSACTION => match param::<PsyTransaction>(&request.params) {
+            Ok(tx) => to_result(chain.submit_transaction(tx).await),
+            Err(e) => Err(e),
+        },
+        METHOD_SUBMIT_BATCH => match param::<Vec<PsyTransaction>>(&request.params) {
+            Ok(txs) => {
+                let tx_ids: Vec<String> = txs.iter().map(|tx| tx.tx_id.clone()).collect();
+                let outcomes = chain.submit_batch(txs).await.map(|results| {
+                    tx_ids.into_iter().zip(results).map(|(tx_id, result)| match result {
+                        Ok(tx_id) => BatchItemOutcome { tx_id, error: None },
+                        Err(e) => BatchItemOutcome { tx_id, error: Some(e.into()) },
+                    }).collect::<Vec<_>>()
+                });
+                to_result(outcomes)
+            }
+            Err(e) => Err(e),
+        },
+        METHOD_GET_TRANSACTION_STATUS => match param::<String>(&request.params) {
+            Ok(tx_id) => to_result(chain.transaction_status(&tx_id).await),
+            Err(e) => Err(e),
+        },
+        METHOD_GET_TRANSACTION_RECEIPT => match param::<String>(&request.params) {
+            Ok(tx_id) => to_result(chain.transaction_receipt(&tx_id).await),
+            Err(e) => Err(e),
+        },
+        METHOD_GET_NETWORK_STATS => to_result(chain.network_stats().await),
+        METHOD_ESTIMATE_FEES => to_result(chain.fee_estimate().await),
+        METHOD_GET_ACCOUNT_NONCE => match param::<String>(&request.params) {
+            Ok(account) => to_result(chain.account_nonce(&account).await),
+            Err(e) => Err(e),
+        },
+        other => Err(JsonRpcError::new(METHOD_NOT_FOUND, format!("unknown method {}", other))),
+    };
+    match result {
+        Ok(value) => JsonRpcResponse::success(id, value),
+        Err(error) => JsonRpcResponse::failure(Some(id), error),
+    }
+}
+
+// First positional parameter
+fn param<T: serde::de::DeserializeOwned>(params: &Value) -> Result<T, JsonRpcError> {
+    let first = params.get(0).cloned()
+        .ok_or_else(|| JsonRpcError::new(INVALID_PARAMS, "expected one positional parameter"))?;
+    serde_json::from_value(first).map_err(|e| JsonRpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn to_result<T: Serialize>(outcome: SpectreResult<T>) -> Result<Value, JsonRpcError> {
+    let value = outcome.map_err(JsonRpcError::from)?;
+    serde_json::to_value(value).map_err(|e| JsonRpcError::new(INTERNAL_ERROR, e.to_string()))
+}
+
+/// Local Psy JSON-RPC server backed by any [`PsyChain`], usually a
+/// [`PsySimulator`](crate::PsySimulator).
+///
+/// Lets [`PsyClient`](crate::PsyClient) be exercised end-to-end without
+/// network access. Must be started inside a tokio runtime; the server stops
+/// when dropped. Faults can be injected to exercise client retries.
+pub struct MockPsyRpcServer {
+    addr: SocketAddr,
+    faults: Arc<MockFaults>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+#[derive(Default)]
+struct MockFaults {
+    lost_responses: AtomicU32,
+    latency_ms: AtomicU64,
+    disabled_methods: Mutex<HashSet<String>>,
+}
+
+impl MockPsyRpcServer {
+    /// Serve `chain` on an ephemeral localhost port
+    pub fn start(chain: Arc<dyn PsyChain>) -> SpectreResult<Self> {
+        Self::start_on(chain, ([127, 0, 0, 1], 0).into())
+    }
+
+    /// Serve `chain` on `addr`, failing if it can't be bound
+    pub fn start_on(chain: Arc<dyn PsyChain>, addr: SocketAddr) -> SpectreResult<Self> {
+        let faults = Arc::new(MockFaults::default());
+        let route_faults = faults.clone();
+        let route = warp::post()
+            .and(warp::body::bytes())
+            .and_then(move |body: warp::hyper::body::Bytes| {
+                let chain = chain.clone();
+                let faults = route_faults.clone();
+                async move {
+                    let latency = faults.latency_ms.load(Ordering::SeqCst);
+                    if latency > 0 {
+                        tokio::time::sleep(Duration::from_millis(latency)).await;
+                    }
+                    let mut response = match serde_json::from_slice::<JsonRpcRequest>(&body) {
+                        Ok(request) if faults.disabled_methods.lock().unwrap().contains(&request.method) => {
+                            let error = JsonRpcError::new(METHOD_NOT_FOUND, format!("unknown method {}", request.method));
+                            JsonRpcResponse::failure(Some(request.id), error)
+                        }
+                        Ok(request) => dispatch(chain.as_ref(), request).await,
+                        Err(e) => JsonRpcResponse::failure(None, JsonRpcError::new(PARSE_ERROR, e.to_string())),
+                    };
+                    let lose = faults.lost_responses
+                        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                        .is_ok();
+                    if lose {
+                        response = JsonRpcResponse::failure(response.id, JsonRpcError::new(INTERNAL_ERROR, "response lost"));
+                    }
+                    Ok::<_, warp::Rejection>(warp::reply::json(&response))
+                }
+            });
+
+        let (shutdown, signal) = oneshot::channel::<()>();
+        let (addr, server) = warp::serve(route)
+            .try_bind_with_graceful_shutdown(addr, async { signal.await.ok(); })
+            .map_err(|e| SpectreError::NetworkFailure { endpoint: addr.to_string(), reason: e.to_string() })?;
+        tokio::spawn(server);
+        Ok(MockPsyRpcServer { addr, faults, shutdown: Some(shutdown) })
+    }
+
+    /// Execute the next `count` requests but answer them with an internal
+    /// error, as if the response was lost on the way back
+    pub fn lose_responses(&self, count: u32) {
+        self.faults.lost_responses.store(count, Ordering::SeqCst);
+    }
+
+    /// Delay every response by `latency`
+    pub fn set_latency(&self, latency: Duration) {
+        self.faults.latency_ms.store(latency.as_millis() as u64, Ordering::SeqCst);
+    }
+
+    /// Answer `method` with [`METHOD_NOT_FOUND`], like a node that predates it
+    pub fn disable_method(&self, method: &str) {
+        self.faults.disabled_methods.lock().unwrap().insert(method.to_string());
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Endpoint to hand to [`PsyClient::with_endpoint`](crate::PsyClient::with_endpoint)
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+}
+
+impl Drop for MockPsyRpcServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
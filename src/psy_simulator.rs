@@ -0,0 +1,650 @@
+// src/psy_simulator.rs
+// Deterministic in-process Psy chain: mempool, blocks, finality and receipts
+
+use crate::clock::{Clock, SystemClock};
+use crate::error::{SpectreError, SpectreResult};
+use crate::psy_fees::{effective_tip, next_base_fee, PsyFeeEstimate, MIN_BASE_FEE};
+use crate::psy_integration::{
+    broadcast_stream, PsyChain, PsyNetworkStats, PsyReceipt, PsyTransaction, PsyTxStatus, PsyTxStream, PsyTxUpdate,
+};
+use crate::psy_stats::PsyTraffic;
+use async_trait::async_trait;
+use ed25519_dalek::VerifyingKey;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+// Blocks looked at when estimating throughput and fees
+const STATS_WINDOW_BLOCKS: usize = 10;
+
+// Revert codes and reasons the simulator picks from for failed executions
+const SIMULATED_REVERTS: [(&str, &str); 3] = [
+    ("EXECUTION_REVERTED", "agent contract reverted"),
+    ("OUT_OF_GAS", "gas limit exhausted during execution"),
+    ("INSUFFICIENT_BALANCE", "sender balance below transfer amount"),
+];
+
+/// Tunables for [`PsySimulator`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PsySimulatorConfig {
+    /// Seed for every random decision the simulator makes
+    pub seed: u64,
+    /// Time between blocks when driven by [`PsySimulator::spawn_block_production`]
+    pub block_interval_ms: u64,
+    pub max_txs_per_block: usize,
+    /// Blocks (including its own) a transaction needs before it is final
+    pub confirmation_depth: u64,
+    /// Probability that an included transaction reverts, from 0.0 to 1.0
+    pub failure_rate: f64,
+    /// Pending transactions beyond this evict the lowest-paying one
+    pub mempool_capacity: usize,
+    /// Blocks a transaction may wait in the mempool before it expires; 0 never expires
+    pub pending_ttl_blocks: u64,
+    /// Refuse transactions without a valid sender signature
+    pub require_signatures: bool,
+    pub gas_per_tx: u64,
+    /// Base fee per gas of the genesis block; later blocks adjust it to demand
+    pub initial_base_fee: u64,
+    /// Block fullness the base fee steers towards
+    pub target_txs_per_block: usize,
+    /// Reported tree depth of Psy's PARTH execution layer
+    pub parth_depth: u32,
+}
+
+impl Default for PsySimulatorConfig {
+    fn default() -> Self {
+        PsySimulatorConfig {
+            seed: 0,
+            block_interval_ms: 400,
+            max_txs_per_block: 500,
+            confirmation_depth: 3,
+            failure_rate: 0.05,
+            mempool_capacity: 10_000,
+            pending_ttl_blocks: 150,
+            require_signatures: false,
+            gas_per_tx: 21_000,
+            initial_base_fee: 1,
+            target_txs_per_block: 250,
+            parth_depth: 12,
+        }
+    }
+}
+
+impl PsySimulatorConfig {
+    /// Reject a failure rate that isn't a probability between 0.0 and 1.0
+    pub fn validate(&self) -> SpectreResult<()> {
+        if !(0.0..=1.0).contains(&self.failure_rate) {
+            return Err(SpectreError::InvalidSimulatorConfig {
+                reason: format!("failure_rate must be between 0.0 and 1.0, got {}", self.failure_rate),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// A block produced by the simulator.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PsyBlock {
+    pub height: u64,
+    pub hash: String,
+    pub parent_hash: String,
+    pub timestamp: u64,
+    /// Transactions in inclusion order
+    pub tx_ids: Vec<String>,
+    pub gas_used: u64,
+    /// Fee per gas every transaction in the block pays before its tip
+    pub base_fee: u64,
+}
+
+// Execution result recorded when a transaction is included
+#[derive(Debug, Clone)]
+struct Inclusion {
+    block_height: u64,
+    tx_index: u32,
+    /// Revert code and reason when execution failed
+    revert: Option<(&'static str, &'static str)>,
+    gas_used: u64,
+    fee_used: u64,
+    /// Tip per gas actually paid
+    priority_fee: u64,
+}
+
+// What the chain knows about a signing account
+#[derive(Default)]
+struct SimAccount {
+    // Set by register_account_key; signed transactions need it
+    public_key: Option<String>,
+    // Transaction that claimed each nonce
+    nonces: HashMap<u64, String>,
+}
+
+struct SimState {
+    rng: ChaCha8Rng,
+    mempool: VecDeque<String>,
+    // Height of the chain head when each mempool transaction arrived
+    entered_mempool: HashMap<String, u64>,
+    txs: HashMap<String, PsyTransaction>,
+    inclusions: HashMap<String, Inclusion>,
+    blocks: Vec<PsyBlock>,
+    accounts: HashMap<String, SimAccount>,
+    // Traffic on the simulated timeline, where each block takes block_interval_ms
+    traffic: PsyTraffic,
+}
+
+impl SimState {
+    // Base fee the next block will charge
+    fn next_base_fee(&self, target_txs: usize) -> u64 {
+        let head = self.blocks.last().expect("genesis block exists");
+        next_base_fee(head.base_fee, head.tx_ids.len(), target_txs)
+    }
+
+    // Tip a mempool transaction offers at `base_fee`; None if it can't pay the base fee
+    fn tip(&self, tx_id: &str, base_fee: u64) -> Option<u64> {
+        effective_tip(&self.txs[tx_id], base_fee)
+    }
+
+    // Whether the transaction holding a nonce keeps it: anything that left
+    // the mempool unexecuted gives its nonce back
+    fn holds_nonce(&self, tx_id: &str) -> bool {
+        self.txs.get(tx_id).is_some_and(|tx| {
+            !matches!(tx.status, PsyTxStatus::Dropped { .. } | PsyTxStatus::Replaced { .. } | PsyTxStatus::Expired)
+        })
+    }
+}
+
+/// Simulated Psy chain living in the current process.
+///
+/// Submitted transactions wait in the mempool until a block is produced,
+/// either explicitly via [`produce_block`](PsySimulator::produce_block) or on
+/// a timer via [`spawn_block_production`](PsySimulator::spawn_block_production).
+/// Blocks take the highest tips first (oldest first among equal tips) from
+/// transactions whose fee cap covers the block's base fee, and the base fee
+/// follows demand. A transaction becomes final once `confirmation_depth`
+/// blocks include or build on it. While waiting it can also be replaced
+/// (explicitly, or by a fee bump on the same sender nonce), evicted when the
+/// mempool is full, or expire after `pending_ttl_blocks`.
+///
+/// All randomness comes from the configured seed, so the same submissions
+/// and block schedule always produce the same chain.
+pub struct PsySimulator {
+    config: PsySimulatorConfig,
+    clock: Arc<dyn Clock>,
+    state: Arc<Mutex<SimState>>,
+    updates: broadcast::Sender<PsyTxUpdate>,
+}
+
+impl Default for PsySimulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PsySimulator {
+    pub fn new() -> Self {
+        Self::build(PsySimulatorConfig::default(), Arc::new(SystemClock))
+    }
+
+    /// Simulator with custom tunables, stamping blocks with time from `clock`;
+    /// fails if the tunables don't [`validate`](PsySimulatorConfig::validate)
+    pub fn with_config(config: PsySimulatorConfig, clock: Arc<dyn Clock>) -> SpectreResult<Self> {
+        config.validate()?;
+        Ok(Self::build(config, clock))
+    }
+
+    fn build(config: PsySimulatorConfig, clock: Arc<dyn Clock>) -> Self {
+        let genesis = PsyBlock {
+            height: 0,
+            hash: block_hash("", 0, &[]),
+            parent_hash: String::new(),
+            timestamp: clock.now(),
+            tx_ids: Vec::new(),
+            gas_used: 0,
+            base_fee: config.initial_base_fee.max(MIN_BASE_FEE),
+        };
+        PsySimulator {
+            state: Arc::new(Mutex::new(SimState {
+                rng: ChaCha8Rng::seed_from_u64(config.seed),
+                mempool: VecDeque::new(),
+                entered_mempool: HashMap::new(),
+                txs: HashMap::new(),
+                inclusions: HashMap::new(),
+                blocks: vec![genesis],
+                accounts: HashMap::new(),
+                traffic: PsyTraffic::new(STATS_WINDOW_BLOCKS as u64 * config.block_interval_ms.max(1)),
+            })),
+            config,
+            clock,
+            updates: broadcast::channel(1024).0,
+        }
+    }
+
+    pub fn config(&self) -> &PsySimulatorConfig {
+        &self.config
+    }
+
+    /// Height of the latest block (genesis is 0)
+    pub fn height(&self) -> u64 {
+        self.state.lock().unwrap().blocks.len() as u64 - 1
+    }
+
+    pub fn block(&self, height: u64) -> Option<PsyBlock> {
+        self.state.lock().unwrap().blocks.get(height as usize).cloned()
+    }
+
+    pub fn mempool_len(&self) -> usize {
+        self.state.lock().unwrap().mempool.len()
+    }
+
+    /// Register the key `account`'s signed transactions must verify against,
+    /// replacing any earlier one. Signed transactions from an account without
+    /// a registered key are refused.
+    pub fn register_account_key(&self, account: &str, public_key: &VerifyingKey) {
+        let mut state = self.state.lock().unwrap();
+        state.accounts.entry(account.to_string()).or_default().public_key = Some(hex::encode(public_key.to_bytes()));
+    }
+
+    /// Every transaction the simulator has accepted
+    pub fn transactions(&self) -> Vec<PsyTransaction> {
+        self.state.lock().unwrap().txs.values().cloned().collect()
+    }
+
+    /// Seal the next block from the best-paying mempool transactions and advance finality
+    pub fn produce_block(&self) -> PsyBlock {
+        let mut state = self.state.lock().unwrap();
+        let height = state.blocks.len() as u64;
+        self.expire_stale(&mut state, height);
+        let base_fee = state.next_base_fee(self.config.target_txs_per_block);
+
+        // Highest tip first; the sort is stable so equal tips keep arrival order
+        let mut candidates: Vec<(String, u64)> = state.mempool.iter()
+            .filter_map(|tx_id| state.tip(tx_id, base_fee).map(|tip| (tx_id.clone(), tip)))
+            .collect();
+        candidates.sort_by_key(|(_, tip)| std::cmp::Reverse(*tip));
+        candidates.truncate(self.config.max_txs_per_block);
+        let chosen: HashSet<&str> = candidates.iter().map(|(tx_id, _)| tx_id.as_str()).collect();
+        state.mempool.retain(|tx_id| !chosen.contains(tx_id.as_str()));
+        let tx_ids: Vec<String> = candidates.iter().map(|(tx_id, _)| tx_id.clone()).collect();
+
+        let mut gas_used = 0;
+        for (index, (tx_id, tip)) in candidates.iter().enumerate() {
+            state.entered_mempool.remove(tx_id);
+            let revert = if state.rng.gen_bool(self.config.failure_rate) {
+                Some(SIMULATED_REVERTS[state.rng.gen_range(0..SIMULATED_REVERTS.len())])
+            } else {
+                None
+            };
+            let inclusion = Inclusion {
+                block_height: height,
+                tx_index: index as u32,
+                revert,
+                gas_used: self.config.gas_per_tx,
+                fee_used: self.config.gas_per_tx.saturating_mul(base_fee.saturating_add(*tip)),
+                priority_fee: *tip,
+            };
+            gas_used += inclusion.gas_used;
+            state.inclusions.insert(tx_id.clone(), inclusion);
+            self.set_status(&mut state, tx_id, PsyTxStatus::Included);
+        }
+
+        let parent_hash = state.blocks.last().map(|b| b.hash.clone()).unwrap_or_default();
+        let block = PsyBlock {
+            height,
+            hash: block_hash(&parent_hash, height, &tx_ids),
+            parent_hash,
+            timestamp: self.clock.now(),
+            tx_ids,
+            gas_used,
+            base_fee,
+        };
+        state.blocks.push(block.clone());
+
+        // Transactions in the block that just reached confirmation_depth become final
+        let depth = self.config.confirmation_depth.max(1);
+        if height + 1 >= depth {
+            let final_height = height + 1 - depth;
+            let finalized = state.blocks[final_height as usize].tx_ids.clone();
+            for tx_id in finalized {
+                let status = match state.inclusions[&tx_id].revert {
+                    None => PsyTxStatus::Confirmed,
+                    Some((code, reason)) => PsyTxStatus::Reverted { code: code.to_string(), reason: reason.to_string() },
+                };
+                self.set_status(&mut state, &tx_id, status);
+            }
+        }
+        block
+    }
+
+    /// Produce a block every `block_interval_ms` until the returned task is aborted
+    pub fn spawn_block_production(self: &Arc<Self>) -> JoinHandle<()> {
+        let simulator = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_millis(simulator.config.block_interval_ms.max(1)));
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                simulator.produce_block();
+            }
+        })
+    }
+
+    /// Swap a pending transaction for `tx`, which takes its place in the
+    /// mempool; the original ends as [`PsyTxStatus::Replaced`]. `tx` must come
+    /// from the same sender with the same nonce, and tip at least 10% more
+    /// (as a fee bump through [`submit_transaction`](PsyChain::submit_transaction) must).
+    pub fn replace_transaction(&self, tx_id: &str, tx: PsyTransaction) -> SpectreResult<String> {
+        let mut state = self.state.lock().unwrap();
+        if state.txs.contains_key(&tx.tx_id) {
+            return Err(SpectreError::TransactionConflict { tx_id: tx.tx_id });
+        }
+        if let Some(original) = state.txs.get(tx_id).filter(|original| original.status == PsyTxStatus::Pending) {
+            let mismatch = if original.agent_id != tx.agent_id {
+                Some(format!("sent by {}, not {}", tx.agent_id, original.agent_id))
+            } else if original.nonce != tx.nonce {
+                Some(format!("nonce {}, not {}", tx.nonce, original.nonce))
+            } else {
+                None
+            };
+            if let Some(reason) = mismatch {
+                return Err(SpectreError::ReplacementMismatch { tx_id: tx_id.to_string(), reason });
+            }
+            let min_priority_fee = bumped_tip(original.priority_fee);
+            if tx.priority_fee < min_priority_fee {
+                return Err(SpectreError::ReplacementUnderpriced { tx_id: tx_id.to_string(), min_priority_fee });
+            }
+        }
+        self.check_sender(&state, &tx, Some(tx_id))?;
+        self.replace(&mut state, tx_id, tx)
+    }
+
+    // Put `tx` in the mempool slot of pending transaction `tx_id`
+    fn replace(&self, state: &mut SimState, tx_id: &str, tx: PsyTransaction) -> SpectreResult<String> {
+        let status = state.txs.get(tx_id).map(|known| known.status.clone());
+        let Some(position) = state.mempool.iter().position(|pending| pending == tx_id) else {
+            return Err(SpectreError::TransactionNotReplaceable { tx_id: tx_id.to_string(), status });
+        };
+        record_sender(state, &tx);
+        let entered = state.entered_mempool.remove(tx_id).unwrap_or_default();
+        let replacement = tx.tx_id.clone();
+        state.mempool[position] = replacement.clone();
+        state.entered_mempool.insert(replacement.clone(), entered);
+        state.txs.insert(replacement.clone(), PsyTransaction { status: PsyTxStatus::Pending, ..tx });
+        let _ = self.updates.send(PsyTxUpdate { tx_id: replacement.clone(), status: PsyTxStatus::Pending });
+        let now_ms = self.sim_time_ms(state);
+        state.traffic.record_submission(&replacement, &state.txs[&replacement].agent_id, now_ms);
+        self.set_status(state, tx_id, PsyTxStatus::Replaced { by: replacement.clone() });
+        Ok(replacement)
+    }
+
+    // Check a transaction's signature, its key against the account's and its
+    // nonce against those already taken (other than by the tx it replaces)
+    fn check_sender(&self, state: &SimState, tx: &PsyTransaction, replacing: Option<&str>) -> SpectreResult<()> {
+        if !tx.is_signed() {
+            if self.config.require_signatures {
+                return Err(SpectreError::InvalidTransactionSignature {
+                    tx_id: tx.tx_id.clone(),
+                    reason: "transaction is unsigned".to_string(),
+                });
+            }
+            return Ok(());
+        }
+        tx.verify_signature()?;
+        let Some(account) = state.accounts.get(&tx.agent_id).filter(|account| account.public_key.is_some()) else {
+            return Err(SpectreError::InvalidTransactionSignature {
+                tx_id: tx.tx_id.clone(),
+                reason: format!("account {} has no registered key", tx.agent_id),
+            });
+        };
+        if account.public_key != tx.public_key {
+            return Err(SpectreError::InvalidTransactionSignature {
+                tx_id: tx.tx_id.clone(),
+                reason: format!("signed with a key other than account {}'s", tx.agent_id),
+            });
+        }
+        match account.nonces.get(&tx.nonce) {
+            Some(holder) if Some(holder.as_str()) != replacing && state.holds_nonce(holder) => {
+                Err(SpectreError::NonceAlreadyUsed { account: tx.agent_id.clone(), nonce: tx.nonce })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Simulated time of the chain head
+    fn sim_time_ms(&self, state: &SimState) -> u64 {
+        (state.blocks.len() as u64 - 1) * self.config.block_interval_ms.max(1)
+    }
+
+    // Expire mempool transactions that have waited pending_ttl_blocks
+    fn expire_stale(&self, state: &mut SimState, height: u64) {
+        let ttl = self.config.pending_ttl_blocks;
+        if ttl == 0 {
+            return;
+        }
+        let (stale, fresh): (Vec<String>, Vec<String>) = state.mempool.drain(..)
+            .partition(|tx_id| height.saturating_sub(state.entered_mempool[tx_id]) > ttl);
+        state.mempool = fresh.into();
+        for tx_id in stale {
+            state.entered_mempool.remove(&tx_id);
+            self.set_status(state, &tx_id, PsyTxStatus::Expired);
+        }
+    }
+
+    // Admit a transaction to the mempool
+    fn accept(&self, state: &mut SimState, tx: PsyTransaction) -> SpectreResult<String> {
+        let tx_id = tx.tx_id.clone();
+        if let Some(known) = state.txs.get(&tx_id) {
+            // Resubmitting the same transaction (e.g. a client retry) is a no-op;
+            // reusing its id for different contents is refused, and so is
+            // resubmitting one that already left the mempool unincluded
+            let same = known.signing_message() == tx.signing_message() && known.signature == tx.signature;
+            if !same {
+                return Err(SpectreError::TransactionConflict { tx_id });
+            }
+            if !state.holds_nonce(&tx_id) {
+                return Err(SpectreError::TransactionDropped { status: known.status.clone(), tx_id });
+            }
+            return Ok(tx_id);
+        }
+        // Reusing the nonce of a pending transaction replaces it if the tip rises enough
+        let pending = pending_nonce_holder(state, &tx);
+        self.check_sender(state, &tx, pending.as_deref())?;
+        if let Some(pending) = pending {
+            let min_priority_fee = bumped_tip(state.txs[&pending].priority_fee);
+            if tx.priority_fee < min_priority_fee {
+                return Err(SpectreError::ReplacementUnderpriced { tx_id: pending, min_priority_fee });
+            }
+            return self.replace(state, &pending, tx);
+        }
+
+        // A full mempool makes room by evicting its lowest-paying (then oldest)
+        // transaction, provided the newcomer pays more
+        let capacity = self.config.mempool_capacity.max(1);
+        if state.mempool.len() >= capacity {
+            let base_fee = state.next_base_fee(self.config.target_txs_per_block);
+            let lowest = state.mempool.iter().enumerate()
+                .map(|(position, tx_id)| (position, state.tip(tx_id, base_fee)))
+                .min_by_key(|(_, tip)| *tip);
+            if let Some((position, lowest_tip)) = lowest {
+                if effective_tip(&tx, base_fee) <= lowest_tip {
+                    return Err(SpectreError::MempoolFull { capacity, min_priority_fee: lowest_tip.unwrap_or(0) });
+                }
+                let evicted = state.mempool.remove(position).expect("position is in the mempool");
+                state.entered_mempool.remove(&evicted);
+                self.set_status(state, &evicted, PsyTxStatus::Dropped { reason: "outbid in a full mempool".to_string() });
+            }
+        }
+        record_sender(state, &tx);
+        let head = state.blocks.len() as u64 - 1;
+        state.txs.insert(tx_id.clone(), PsyTransaction { status: PsyTxStatus::Pending, ..tx });
+        state.mempool.push_back(tx_id.clone());
+        state.entered_mempool.insert(tx_id.clone(), head);
+        let _ = self.updates.send(PsyTxUpdate { tx_id: tx_id.clone(), status: PsyTxStatus::Pending });
+        let now_ms = self.sim_time_ms(state);
+        state.traffic.record_submission(&tx_id, &state.txs[&tx_id].agent_id, now_ms);
+        Ok(tx_id)
+    }
+
+    fn set_status(&self, state: &mut SimState, tx_id: &str, status: PsyTxStatus) {
+        let now_ms = self.sim_time_ms(state);
+        if let Some(tx) = state.txs.get_mut(tx_id) {
+            tx.status = status.clone();
+            state.traffic.record_status(tx_id, &status, now_ms);
+            let _ = self.updates.send(PsyTxUpdate { tx_id: tx_id.to_string(), status });
+        }
+    }
+}
+
+#[async_trait]
+impl PsyChain for PsySimulator {
+    async fn submit_transaction(&self, tx: PsyTransaction) -> SpectreResult<String> {
+        let mut state = self.state.lock().unwrap();
+        self.accept(&mut state, tx)
+    }
+
+    async fn submit_batch(&self, txs: Vec<PsyTransaction>) -> SpectreResult<Vec<SpectreResult<String>>> {
+        let mut state = self.state.lock().unwrap();
+        Ok(txs.into_iter().map(|tx| self.accept(&mut state, tx)).collect())
+    }
+
+    async fn transaction_status(&self, tx_id: &str) -> SpectreResult<Option<PsyTxStatus>> {
+        Ok(self.state.lock().unwrap().txs.get(tx_id).map(|tx| tx.status.clone()))
+    }
+
+    async fn transaction_receipt(&self, tx_id: &str) -> SpectreResult<Option<PsyReceipt>> {
+        let state = self.state.lock().unwrap();
+        let (Some(tx), Some(inclusion)) = (state.txs.get(tx_id), state.inclusions.get(tx_id)) else {
+            return Ok(None);
+        };
+        let head = state.blocks.len() as u64 - 1;
+        Ok(Some(PsyReceipt {
+            tx_id: tx_id.to_string(),
+            status: tx.status.clone(),
+            block_height: inclusion.block_height,
+            tx_index: inclusion.tx_index,
+            success: inclusion.revert.is_none(),
+            gas_used: inclusion.gas_used,
+            fee_used: inclusion.fee_used,
+            confirmations: head - inclusion.block_height + 1,
+        }))
+    }
+
+    async fn network_stats(&self) -> SpectreResult<PsyNetworkStats> {
+        let state = self.state.lock().unwrap();
+        Ok(state.traffic.stats(self.sim_time_ms(&state), self.config.parth_depth))
+    }
+
+    async fn fee_estimate(&self) -> SpectreResult<PsyFeeEstimate> {
+        let state = self.state.lock().unwrap();
+        let recent: Vec<&PsyBlock> = state.blocks.iter().skip(1).rev().take(STATS_WINDOW_BLOCKS).collect();
+        let tips = recent.iter()
+            .flat_map(|block| &block.tx_ids)
+            .map(|tx_id| state.inclusions[tx_id].priority_fee)
+            .collect();
+        let base_fee = state.next_base_fee(self.config.target_txs_per_block);
+        Ok(PsyFeeEstimate::from_tips(base_fee, tips, recent.len() as u32))
+    }
+
+    async fn account_nonce(&self, account: &str) -> SpectreResult<u64> {
+        let state = self.state.lock().unwrap();
+        Ok(state.accounts.get(account)
+            .and_then(|account| account.nonces.iter().filter(|(_, tx_id)| state.holds_nonce(tx_id)).map(|(nonce, _)| nonce + 1).max())
+            .unwrap_or(0))
+    }
+
+    fn subscribe_all(&self) -> PsyTxStream {
+        let state = Arc::clone(&self.state);
+        broadcast_stream(self.updates.subscribe(), move || {
+            let state = state.lock().unwrap();
+            let mut current: Vec<PsyTxUpdate> = state.txs.values()
+                .map(|tx| PsyTxUpdate { tx_id: tx.tx_id.clone(), status: tx.status.clone() })
+                .collect();
+            current.sort_unstable_by(|a, b| a.tx_id.cmp(&b.tx_id));
+            current
+        })
+    }
+}
+
+// Pending transaction already holding a signed transaction's sender nonce
+fn pending_nonce_holder(state: &SimState, tx: &PsyTransaction) -> Option<String> {
+    if !tx.is_signed() {
+        return None;
+    }
+    let holder = state.accounts.get(&tx.agent_id)?.nonces.get(&tx.nonce)?;
+    let pending = holder != &tx.tx_id && state.txs.get(holder).is_some_and(|held| held.status == PsyTxStatus::Pending);
+    pending.then(|| holder.clone())
+}
+
+// Smallest tip that may replace a pending transaction tipping `tip`: 10% more, at least 1
+fn bumped_tip(tip: u64) -> u64 {
+    tip.saturating_add((tip / 10).max(1))
+}
+
+// Bind a signed transaction's key and nonce to its sending account
+fn record_sender(state: &mut SimState, tx: &PsyTransaction) {
+    if !tx.is_signed() {
+        return;
+    }
+    let account = state.accounts.entry(tx.agent_id.clone()).or_default();
+    account.nonces.insert(tx.nonce, tx.tx_id.clone());
+}
+
+// Every variable-length field is length-prefixed, so no two blocks'
+// contents can run together into the same bytes
+fn block_hash(parent_hash: &str, height: u64, tx_ids: &[String]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update((parent_hash.len() as u64).to_be_bytes());
+    hasher.update(parent_hash.as_bytes());
+    hasher.update(height.to_be_bytes());
+    hasher.update((tx_ids.len() as u64).to_be_bytes());
+    for tx_id in tx_ids {
+        hasher.update((tx_id.len() as u64).to_be_bytes());
+        hasher.update(tx_id.as_bytes());
+    }
+    format!("0x{}", hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_hash_separates_tx_ids() {
+        let split = |ids: &[&str]| block_hash("0xparent", 1, &ids.iter().map(|id| id.to_string()).collect::<Vec<_>>());
+        assert_ne!(split(&["ab", "c"]), split(&["a", "bc"]));
+        assert_ne!(split(&["abc"]), split(&["abc", ""]));
+        assert_eq!(split(&["ab", "c"]), split(&["ab", "c"]));
+    }
+
+    #[test]
+    fn test_extreme_fees_saturate() {
+        assert_eq!(bumped_tip(u64::MAX), u64::MAX);
+
+        // Including a transaction with an absurd tip neither panics nor
+        // poisons the simulator's state
+        let sim = PsySimulator::new();
+        let tx = PsyTransaction { priority_fee: u64::MAX, ..PsyTransaction::new("agent_001".to_string(), "arb".to_string(), 1) };
+        let tx_id = sim.accept(&mut sim.state.lock().unwrap(), tx).unwrap();
+        assert_eq!(sim.produce_block().tx_ids, vec![tx_id.clone()]);
+        assert_eq!(sim.state.lock().unwrap().inclusions[&tx_id].fee_used, u64::MAX);
+    }
+
+    #[test]
+    fn test_failure_rate_must_be_a_probability() {
+        for failure_rate in [-0.1, 1.5, f64::NAN, f64::INFINITY] {
+            let config = PsySimulatorConfig { failure_rate, ..PsySimulatorConfig::default() };
+            let err = PsySimulator::with_config(config, Arc::new(SystemClock)).err().unwrap();
+            assert_eq!(err.code(), "INVALID_SIMULATOR_CONFIG");
+        }
+        for failure_rate in [0.0, 1.0] {
+            let config = PsySimulatorConfig { failure_rate, ..PsySimulatorConfig::default() };
+            let sim = PsySimulator::with_config(config, Arc::new(SystemClock)).unwrap();
+            sim.accept(&mut sim.state.lock().unwrap(), PsyTransaction::new("agent_001".to_string(), "arb".to_string(), 1)).unwrap();
+            sim.produce_block();
+        }
+    }
+}
@@ -0,0 +1,185 @@
+// src/psy_stats.rs
+// Network statistics computed from observed transaction traffic
+
+use crate::psy_integration::{PsyNetworkStats, PsyTxStatus};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Sliding window [`PsyTraffic`] keeps by default
+pub const DEFAULT_STATS_WINDOW_MS: u64 = 60_000;
+
+// Submissions still awaiting an outcome beyond this are forgotten oldest first
+const MAX_IN_FLIGHT: usize = 100_000;
+
+// A transaction that reached a terminal status
+#[derive(Debug, Clone)]
+struct Outcome {
+    at_ms: u64,
+    /// Submission to finality, for transactions that executed
+    latency_ms: Option<u64>,
+    failed: bool,
+}
+
+/// Submissions and outcomes seen over a sliding time window.
+///
+/// Times are milliseconds on whatever timeline the owner uses (wall time
+/// since start, or simulated block time); they only need to be monotonic.
+/// A transaction counts towards throughput and latency once it executes
+/// (confirmed or reverted), and towards the failure rate if it ended any
+/// way other than confirmed. A replaced transaction hands its submission
+/// time to its replacement, so fee bumps still show the latency the agent saw.
+#[derive(Debug)]
+pub struct PsyTraffic {
+    window_ms: u64,
+    first_seen_ms: Option<u64>,
+    // tx id -> (agent, submitted at)
+    in_flight: HashMap<String, (String, u64)>,
+    in_flight_order: VecDeque<String>,
+    submissions: VecDeque<(u64, String)>,
+    outcomes: VecDeque<Outcome>,
+}
+
+impl Default for PsyTraffic {
+    fn default() -> Self {
+        Self::new(DEFAULT_STATS_WINDOW_MS)
+    }
+}
+
+impl PsyTraffic {
+    pub fn new(window_ms: u64) -> Self {
+        PsyTraffic {
+            window_ms: window_ms.max(1),
+            first_seen_ms: None,
+            in_flight: HashMap::new(),
+            in_flight_order: VecDeque::new(),
+            submissions: VecDeque::new(),
+            outcomes: VecDeque::new(),
+        }
+    }
+
+    pub fn window_ms(&self) -> u64 {
+        self.window_ms
+    }
+
+    /// `agent` submitted `tx_id` at `at_ms`
+    pub fn record_submission(&mut self, tx_id: &str, agent: &str, at_ms: u64) {
+        self.first_seen_ms.get_or_insert(at_ms);
+        self.submissions.push_back((at_ms, agent.to_string()));
+        if self.in_flight.insert(tx_id.to_string(), (agent.to_string(), at_ms)).is_none() {
+            self.in_flight_order.push_back(tx_id.to_string());
+        }
+        while self.in_flight.len() > MAX_IN_FLIGHT {
+            let Some(oldest) = self.in_flight_order.pop_front() else { break };
+            self.in_flight.remove(&oldest);
+        }
+        self.prune(at_ms);
+    }
+
+    /// `tx_id` reached `status` at `at_ms`; only terminal statuses of
+    /// submitted transactions are counted
+    pub fn record_status(&mut self, tx_id: &str, status: &PsyTxStatus, at_ms: u64) {
+        if !status.is_terminal() {
+            return;
+        }
+        let Some((agent, submitted_ms)) = self.in_flight.remove(tx_id) else {
+            return;
+        };
+        // Ids of finished transactions are swept from the order lazily
+        if self.in_flight_order.len() > 2 * self.in_flight.len() + 64 {
+            let in_flight = &self.in_flight;
+            self.in_flight_order.retain(|pending| in_flight.contains_key(pending));
+        }
+        let (latency_ms, failed) = match status {
+            PsyTxStatus::Confirmed => (Some(at_ms.saturating_sub(submitted_ms)), false),
+            PsyTxStatus::Reverted { .. } => (Some(at_ms.saturating_sub(submitted_ms)), true),
+            PsyTxStatus::Replaced { by } => {
+                // Not an outcome yet: the replacement carries on in its place
+                if let Some(replacement) = self.in_flight.get_mut(by) {
+                    *replacement = (agent, submitted_ms);
+                }
+                return;
+            }
+            _ => (None, true),
+        };
+        self.outcomes.push_back(Outcome { at_ms, latency_ms, failed });
+        self.prune(at_ms);
+    }
+
+    /// Statistics over the window ending at `now_ms`
+    pub fn stats(&self, now_ms: u64, parth_depth: u32) -> PsyNetworkStats {
+        let in_window = |at_ms: u64| at_ms <= now_ms && now_ms - at_ms < self.window_ms;
+        let outcomes: Vec<&Outcome> = self.outcomes.iter().filter(|o| in_window(o.at_ms)).collect();
+        let agents: HashSet<&str> = self.submissions.iter()
+            .filter(|(at_ms, _)| in_window(*at_ms))
+            .map(|(_, agent)| agent.as_str())
+            .collect();
+        let mut latencies: Vec<u64> = outcomes.iter().filter_map(|o| o.latency_ms).collect();
+        latencies.sort_unstable();
+
+        // Before a full window has passed, rate over the time actually observed
+        let observed_ms = self.first_seen_ms
+            .map(|first| now_ms.saturating_sub(first).min(self.window_ms))
+            .unwrap_or(0)
+            .max(1);
+        let failures = outcomes.iter().filter(|o| o.failed).count();
+        PsyNetworkStats {
+            active_agents: agents.len() as u32,
+            tps: (latencies.len() as u64 * 1000 / observed_ms) as u32,
+            avg_latency_ms: latencies.iter().sum::<u64>().checked_div(latencies.len() as u64).unwrap_or(0) as u32,
+            parth_depth,
+            latency_p50_ms: percentile(&latencies, 50),
+            latency_p95_ms: percentile(&latencies, 95),
+            latency_p99_ms: percentile(&latencies, 99),
+            failure_rate: if outcomes.is_empty() { 0.0 } else { failures as f64 / outcomes.len() as f64 },
+        }
+    }
+
+    // Drop samples that have left the window
+    fn prune(&mut self, now_ms: u64) {
+        let expired = |at_ms: u64| now_ms.saturating_sub(at_ms) >= self.window_ms;
+        while self.submissions.front().is_some_and(|(at_ms, _)| expired(*at_ms)) {
+            self.submissions.pop_front();
+        }
+        while self.outcomes.front().is_some_and(|o| expired(o.at_ms)) {
+            self.outcomes.pop_front();
+        }
+    }
+}
+
+// Nearest-rank percentile of sorted samples
+fn percentile(sorted: &[u64], p: usize) -> u32 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (sorted.len() * p).div_ceil(100).max(1);
+    sorted[rank - 1] as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_percentiles_and_failure_rate() {
+        let mut traffic = PsyTraffic::new(10_000);
+        for i in 0..100u64 {
+            let tx_id = format!("tx_{}", i);
+            traffic.record_submission(&tx_id, &format!("agent_{}", i % 4), 0);
+            traffic.record_status(&tx_id, &PsyTxStatus::Included, i + 1);
+            let status = if i < 90 {
+                PsyTxStatus::Confirmed
+            } else {
+                PsyTxStatus::Reverted { code: "OUT_OF_GAS".to_string(), reason: String::new() }
+            };
+            traffic.record_status(&tx_id, &status, i + 1);
+        }
+        let stats = traffic.stats(1_000, 12);
+        assert_eq!(stats.active_agents, 4);
+        assert_eq!(stats.tps, 100);
+        assert_eq!((stats.latency_p50_ms, stats.latency_p95_ms, stats.latency_p99_ms), (50, 95, 99));
+        assert!((stats.failure_rate - 0.1).abs() < 1e-9);
+
+        // Everything has slid out of the window
+        let stats = traffic.stats(20_000, 12);
+        assert_eq!((stats.active_agents, stats.tps, stats.latency_p99_ms), (0, 0, 0));
+    }
+}
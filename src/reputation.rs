@@ -0,0 +1,255 @@
+// src/reputation.rs
+// Reputation Engine: agent scores derived from a decaying history of job outcomes
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Score a brand-new agent starts from before it has any history
+pub const DEFAULT_PRIOR_MEAN: f64 = 0.8;
+
+/// How many outcomes' worth of evidence the prior counts as
+pub const DEFAULT_PRIOR_WEIGHT: f64 = 10.0;
+
+/// Age at which an outcome counts half as much as a fresh one (30 days)
+pub const DEFAULT_HALF_LIFE_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// Successes slower than this earn less than full credit
+pub const DEFAULT_TARGET_LATENCY_MS: u64 = 60_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReputationOutcome {
+    /// Job verified and delivered
+    Success,
+    /// Proof rejected or deadline missed
+    Failure,
+    /// Requester disputed a delivered result
+    Dispute,
+}
+
+/// One scored outcome in an agent's history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReputationEvent {
+    pub agent_id: String,
+    pub model_type: String,
+    pub outcome: ReputationOutcome,
+    /// Time from claim to verified result, for successes
+    pub latency_ms: Option<u64>,
+    pub at: u64,
+}
+
+/// A computed score together with the evidence behind it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReputationScore {
+    pub score: f64,
+    pub policy: String,
+    pub prior_mean: f64,
+    pub prior_weight: f64,
+    /// Decay-weighted evidence for and against the agent
+    pub successes: f64,
+    pub failures: f64,
+    pub disputes: f64,
+    /// Success credit withheld because results were slower than target
+    pub latency_penalty: f64,
+    pub events: usize,
+}
+
+impl fmt::Display for ReputationScore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.3} ({}): prior {:.2} x {:.1}, {} events -> {:.2} successes, {:.2} failures, {:.2} disputes, {:.2} latency penalty",
+            self.score, self.policy, self.prior_mean, self.prior_weight, self.events,
+            self.successes, self.failures, self.disputes, self.latency_penalty
+        )
+    }
+}
+
+/// Turns an agent's event history into a score in [0, 1].
+pub trait ScoringPolicy: Send + Sync {
+    /// Short identifier shown in explanations
+    fn name(&self) -> &'static str;
+
+    fn score(&self, events: &[&ReputationEvent], now: u64) -> ReputationScore;
+}
+
+/// Beta-Bernoulli scoring with exponential time decay.
+///
+/// New agents start at the prior mean instead of a perfect score, and each
+/// outcome moves the score by its weight, halving every `half_life_secs`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecayingBetaPolicy {
+    pub prior_mean: f64,
+    pub prior_weight: f64,
+    pub half_life_secs: u64,
+    pub target_latency_ms: u64,
+    pub failure_weight: f64,
+    pub dispute_weight: f64,
+}
+
+impl Default for DecayingBetaPolicy {
+    fn default() -> Self {
+        DecayingBetaPolicy {
+            prior_mean: DEFAULT_PRIOR_MEAN,
+            prior_weight: DEFAULT_PRIOR_WEIGHT,
+            half_life_secs: DEFAULT_HALF_LIFE_SECS,
+            target_latency_ms: DEFAULT_TARGET_LATENCY_MS,
+            failure_weight: 1.0,
+            dispute_weight: 2.0,
+        }
+    }
+}
+
+impl DecayingBetaPolicy {
+    fn decay(&self, at: u64, now: u64) -> f64 {
+        let age = now.saturating_sub(at) as f64;
+        0.5_f64.powf(age / self.half_life_secs.max(1) as f64)
+    }
+
+    // Share of full success credit earned at `latency_ms` (never below half)
+    fn latency_credit(&self, latency_ms: Option<u64>) -> f64 {
+        match latency_ms {
+            Some(ms) if ms > self.target_latency_ms => (self.target_latency_ms as f64 / ms as f64).max(0.5),
+            _ => 1.0,
+        }
+    }
+}
+
+impl ScoringPolicy for DecayingBetaPolicy {
+    fn name(&self) -> &'static str {
+        "decaying-beta"
+    }
+
+    fn score(&self, events: &[&ReputationEvent], now: u64) -> ReputationScore {
+        let (mut successes, mut failures, mut disputes, mut latency_penalty) = (0.0, 0.0, 0.0, 0.0);
+        for event in events {
+            let weight = self.decay(event.at, now);
+            match event.outcome {
+                ReputationOutcome::Success => {
+                    let credit = weight * self.latency_credit(event.latency_ms);
+                    successes += credit;
+                    latency_penalty += weight - credit;
+                }
+                ReputationOutcome::Failure => failures += weight * self.failure_weight,
+                ReputationOutcome::Dispute => disputes += weight * self.dispute_weight,
+            }
+        }
+
+        let alpha = self.prior_mean * self.prior_weight + successes;
+        let beta = (1.0 - self.prior_mean) * self.prior_weight + failures + disputes + latency_penalty;
+        ReputationScore {
+            score: alpha / (alpha + beta),
+            policy: self.name().to_string(),
+            prior_mean: self.prior_mean,
+            prior_weight: self.prior_weight,
+            successes,
+            failures,
+            disputes,
+            latency_penalty,
+            events: events.len(),
+        }
+    }
+}
+
+/// Keeps every agent's outcome history and scores it on demand.
+pub struct ReputationEngine {
+    history: HashMap<String, Vec<ReputationEvent>>,
+    policy: Box<dyn ScoringPolicy>,
+}
+
+impl Default for ReputationEngine {
+    fn default() -> Self {
+        Self::with_policy(DecayingBetaPolicy::default())
+    }
+}
+
+impl ReputationEngine {
+    pub fn with_policy(policy: impl ScoringPolicy + 'static) -> Self {
+        ReputationEngine {
+            history: HashMap::new(),
+            policy: Box::new(policy),
+        }
+    }
+
+    pub fn set_policy(&mut self, policy: impl ScoringPolicy + 'static) {
+        self.policy = Box::new(policy);
+    }
+
+    pub fn policy(&self) -> &dyn ScoringPolicy {
+        self.policy.as_ref()
+    }
+
+    pub fn record(&mut self, event: ReputationEvent) {
+        self.history.entry(event.agent_id.clone()).or_default().push(event);
+    }
+
+    /// An agent's outcomes, oldest first
+    pub fn history(&self, agent_id: &str) -> &[ReputationEvent] {
+        self.history.get(agent_id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Every recorded outcome, each agent's oldest first
+    pub fn events(&self) -> impl Iterator<Item = &ReputationEvent> {
+        self.history.values().flatten()
+    }
+
+    // Replace the whole outcome history, keeping the scoring policy
+    pub(crate) fn restore(&mut self, events: Vec<ReputationEvent>) {
+        self.history.clear();
+        for event in events {
+            self.record(event);
+        }
+    }
+
+    /// Overall score across every model the agent has worked
+    pub fn score(&self, agent_id: &str, now: u64) -> f64 {
+        self.explain(agent_id, None, now).score
+    }
+
+    /// Score from the agent's outcomes on one model only
+    pub fn model_score(&self, agent_id: &str, model_type: &str, now: u64) -> f64 {
+        self.explain(agent_id, Some(model_type), now).score
+    }
+
+    /// The score and the evidence behind it, overall or for one model
+    pub fn explain(&self, agent_id: &str, model_type: Option<&str>, now: u64) -> ReputationScore {
+        let events: Vec<&ReputationEvent> = self.history(agent_id).iter()
+            .filter(|e| model_type.is_none_or(|m| e.model_type == m))
+            .collect();
+        self.policy.score(&events, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(outcome: ReputationOutcome, latency_ms: Option<u64>, at: u64) -> ReputationEvent {
+        ReputationEvent {
+            agent_id: "agent".to_string(),
+            model_type: "LLaMA-3-70B".to_string(),
+            outcome,
+            latency_ms,
+            at,
+        }
+    }
+
+    #[test]
+    fn test_prior_decay_and_latency() {
+        let mut engine = ReputationEngine::default();
+        assert_eq!(engine.score("agent", 0), DEFAULT_PRIOR_MEAN);
+
+        engine.record(event(ReputationOutcome::Failure, None, 0));
+        let fresh = engine.score("agent", 0);
+        let aged = engine.score("agent", DEFAULT_HALF_LIFE_SECS * 10);
+        assert!(fresh < DEFAULT_PRIOR_MEAN);
+        assert!(aged > fresh && aged < DEFAULT_PRIOR_MEAN);
+
+        let mut fast = ReputationEngine::default();
+        let mut slow = ReputationEngine::default();
+        fast.record(event(ReputationOutcome::Success, Some(1_000), 0));
+        slow.record(event(ReputationOutcome::Success, Some(DEFAULT_TARGET_LATENCY_MS * 4), 0));
+        assert!(fast.score("agent", 0) > slow.score("agent", 0));
+        assert_eq!(slow.explain("agent", None, 0).latency_penalty, 0.5);
+    }
+}
@@ -1,8 +1,32 @@
 // src/sdkey_verification.rs
 // Advanced Software Defined Key (SDKey) Verification System
 
+use crate::clock::{Clock, SystemClock};
+use crate::error::{SpectreError, SpectreResult};
+use crate::events::{DomainEvent, EventLog, EventRecord};
+use crate::model_catalog::ModelCatalog;
+use crate::proof_verifier::{execution_commitment, ProofVerifier, SignatureVerifier};
+use crate::reputation::{ReputationEngine, ReputationEvent, ReputationOutcome, ReputationScore, ScoringPolicy};
+use crate::staking::{SlashEvent, SlashOffense, SlashPolicy, StakeManager};
+use ed25519_dalek::Signer;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::sync::Arc;
+
+pub use ed25519_dalek::{SigningKey, VerifyingKey};
+
+// Minimum reputation an agent needs for its proofs to be accepted
+pub const MIN_REPUTATION: f64 = 0.7;
+// Minimum stake (economic security) required for task verification
+pub const MIN_STAKE: u64 = 1000;
+// How old a proof may be before it is rejected as stale
+pub const DEFAULT_PROOF_MAX_AGE_SECS: u64 = 300;
+// How far ahead of our clock a proof timestamp may be
+pub const DEFAULT_MAX_CLOCK_SKEW_SECS: u64 = 30;
+
+// Domain separator so SDKey signatures can't be replayed in another protocol
+const PROOF_DOMAIN: &[u8] = b"SPECTRE-SDKEY-PROOF-v2";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SDKeyProof {
@@ -11,137 +35,747 @@ pub struct SDKeyProof {
     pub execution_proof: String,   // Proof of computation (simulated ZK proof)
     pub timestamp: u64,
     pub nonce: u32,
+    pub job_id: u64,               // Job this proof is bound to
+    pub result_digest: String,     // Hex SHA-256 of the result the agent produced
+    pub signature: String,         // Hex Ed25519 signature over signing_message()
 }
 
+impl SDKeyProof {
+    // Canonical bytes the agent signs: every field except the signature itself,
+    // length-prefixed so no two distinct proofs serialize identically
+    pub fn signing_message(&self) -> Vec<u8> {
+        let mut msg = Vec::with_capacity(128);
+        msg.extend_from_slice(PROOF_DOMAIN);
+        for field in [&self.agent_id, &self.model_hash, &self.result_digest, &self.execution_proof] {
+            msg.extend_from_slice(&(field.len() as u32).to_be_bytes());
+            msg.extend_from_slice(field.as_bytes());
+        }
+        msg.extend_from_slice(&self.timestamp.to_be_bytes());
+        msg.extend_from_slice(&self.nonce.to_be_bytes());
+        msg.extend_from_slice(&self.job_id.to_be_bytes());
+        msg
+    }
+}
+
+// Hex SHA-256 digest of a job result, as carried in SDKeyProof::result_digest
+pub fn result_digest(result: &[u8]) -> String {
+    hex::encode(Sha256::digest(result))
+}
+
+// A single model an agent has been verified to run; accepted hashes come from the catalog
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AgentCapability {
+pub struct ModelCapability {
     pub model_type: String,        // "LLaMA-3-70B", "GPT-4", etc.
-    pub verified_hash: String,     // Expected hash for this model
-    pub max_tokens: u32,           // Maximum tokens this agent can process
-    pub reputation_score: f64,     // 0.0 to 1.0 reputation
-    pub stake_amount: u64,         // Tokens staked for verification
+    pub max_tokens: u32,           // Maximum tokens at registration; the catalog's current limit is enforced
+}
+
+// An agent's SDKey identity: every model it can run
+// (reputation and stake are tracked by the registry's engines)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentCapability {
+    pub public_key: String,        // Hex Ed25519 key that signs this agent's proofs
+    pub models: Vec<ModelCapability>,
+}
+
+impl AgentCapability {
+    pub fn model(&self, model_type: &str) -> Option<&ModelCapability> {
+        self.models.iter().find(|m| m.model_type == model_type)
+    }
+
+    pub fn supports(&self, model_type: &str) -> bool {
+        self.model(model_type).is_some()
+    }
+}
+
+// Nonces an agent has used inside the freshness window, with their proof timestamps
+#[derive(Debug, Clone, Default)]
+struct NonceWindow {
+    seen: HashMap<u32, u64>,
+}
+
+impl NonceWindow {
+    // Forget nonces whose proofs would now be rejected as stale anyway
+    fn prune(&mut self, oldest_accepted: u64) {
+        self.seen.retain(|_, timestamp| *timestamp >= oldest_accepted);
+    }
+}
+
+/// Everything an [`SDKeyRegistry`] accumulates while running, without its
+/// configuration (catalog, clock, verifiers, staking and scoring policies).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistrySnapshot {
+    pub verified_agents: HashMap<String, AgentCapability>,
+    pub stakes: StakeManager,
+    pub reputation: Vec<ReputationEvent>,
+    /// Nonces each agent used inside the freshness window, with their proof timestamps
+    pub used_nonces: HashMap<String, HashMap<u32, u64>>,
+}
+
+/// The part of an [`SDKeyRegistry`]'s configuration that decides how an
+/// operation plays out, so it can be stored with the state it produced.
+/// Clock, verifiers and the scoring policy are code and stay with the builder.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegistryConfig {
+    pub catalog: ModelCatalog,
+    pub proof_max_age_secs: u64,
+    pub max_clock_skew_secs: u64,
+    pub unbonding_period_secs: u64,
+    pub job_stake_ratio_bps: u64,
+    pub slash_policies: HashMap<SlashOffense, SlashPolicy>,
 }
 
 pub struct SDKeyRegistry {
     pub verified_agents: HashMap<String, AgentCapability>,
-    pub model_hashes: HashMap<String, String>, // model_type -> expected_hash
+    pub catalog: ModelCatalog,
+    pub proof_max_age_secs: u64,
+    pub max_clock_skew_secs: u64,
+    pub stakes: StakeManager,      // Bonded collateral backing each agent
+    pub reputation: ReputationEngine, // Outcome history each agent's score derives from
+    clock: Arc<dyn Clock>,
+    events: EventLog,
+    used_nonces: HashMap<String, NonceWindow>,
+    default_verifier: Box<dyn ProofVerifier>,
+    model_verifiers: HashMap<String, Box<dyn ProofVerifier>>,
+}
+
+// Configures and builds an SDKeyRegistry, including which proof verifier it uses
+pub struct SDKeyRegistryBuilder {
+    catalog: ModelCatalog,
+    clock: Arc<dyn Clock>,
+    proof_max_age_secs: u64,
+    max_clock_skew_secs: u64,
+    stakes: StakeManager,
+    reputation: ReputationEngine,
+    events: EventLog,
+    default_verifier: Box<dyn ProofVerifier>,
+    model_verifiers: HashMap<String, Box<dyn ProofVerifier>>,
+}
+
+impl Default for SDKeyRegistryBuilder {
+    fn default() -> Self {
+        SDKeyRegistryBuilder {
+            catalog: ModelCatalog::builtin(),
+            clock: Arc::new(SystemClock),
+            proof_max_age_secs: DEFAULT_PROOF_MAX_AGE_SECS,
+            max_clock_skew_secs: DEFAULT_MAX_CLOCK_SKEW_SECS,
+            stakes: StakeManager::default(),
+            reputation: ReputationEngine::default(),
+            events: EventLog::new(),
+            default_verifier: Box::new(SignatureVerifier),
+            model_verifiers: HashMap::new(),
+        }
+    }
+}
+
+impl SDKeyRegistryBuilder {
+    // Models (and their accepted hashes) the registry recognizes
+    pub fn catalog(mut self, catalog: ModelCatalog) -> Self {
+        self.catalog = catalog;
+        self
+    }
+
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn proof_max_age(mut self, secs: u64) -> Self {
+        self.proof_max_age_secs = secs;
+        self
+    }
+
+    pub fn max_clock_skew(mut self, secs: u64) -> Self {
+        self.max_clock_skew_secs = secs;
+        self
+    }
+
+    // How long withdrawn stake stays slashable before it can be claimed
+    pub fn unbonding_period(mut self, secs: u64) -> Self {
+        self.stakes.unbonding_period_secs = secs;
+        self
+    }
+
+    // Share of a job's reward (in basis points) locked from the agent's stake while it works the job
+    pub fn job_stake_ratio_bps(mut self, bps: u64) -> Self {
+        self.stakes.job_stake_ratio_bps = bps;
+        self
+    }
+
+    // Policy that turns each agent's outcome history into a reputation score
+    pub fn scoring_policy(mut self, policy: impl ScoringPolicy + 'static) -> Self {
+        self.reputation.set_policy(policy);
+        self
+    }
+
+    // Log the registry (and any market built on it) emits its domain events into
+    pub fn event_log(mut self, events: EventLog) -> Self {
+        self.events = events;
+        self
+    }
+
+    // Verifier used for every model without a specific override
+    pub fn verifier(mut self, verifier: impl ProofVerifier + 'static) -> Self {
+        self.default_verifier = Box::new(verifier);
+        self
+    }
+
+    // Verifier used only for proofs about `model_type`
+    pub fn verifier_for_model(mut self, model_type: &str, verifier: impl ProofVerifier + 'static) -> Self {
+        self.model_verifiers.insert(model_type.to_string(), Box::new(verifier));
+        self
+    }
+
+    pub fn build(self) -> SDKeyRegistry {
+        SDKeyRegistry {
+            verified_agents: HashMap::new(),
+            catalog: self.catalog,
+            proof_max_age_secs: self.proof_max_age_secs,
+            max_clock_skew_secs: self.max_clock_skew_secs,
+            stakes: self.stakes,
+            reputation: self.reputation,
+            clock: self.clock,
+            events: self.events,
+            used_nonces: HashMap::new(),
+            default_verifier: self.default_verifier,
+            model_verifiers: self.model_verifiers,
+        }
+    }
+}
+
+impl Default for SDKeyRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SDKeyRegistry {
     pub fn new() -> Self {
-        let mut registry = SDKeyRegistry {
-            verified_agents: HashMap::new(),
-            model_hashes: HashMap::new(),
-        };
+        Self::builder().build()
+    }
+
+    pub fn builder() -> SDKeyRegistryBuilder {
+        SDKeyRegistryBuilder::default()
+    }
 
-        // Pre-populate with known model hashes (in real system, these would be on-chain)
-        registry.model_hashes.insert("LLaMA-3-70B".to_string(), "0xa1b2c3d4e5f6".to_string());
-        registry.model_hashes.insert("GPT-4-Turbo".to_string(), "0xf6e5d4c3b2a1".to_string());
-        registry.model_hashes.insert("Claude-3-Opus".to_string(), "0x123456789abc".to_string());
-        
-        registry
+    // Create a registry that reads time from `clock` for proof freshness checks
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self::builder().clock(clock).build()
     }
 
-    // Register an agent with verified capabilities
-    pub fn register_agent(&mut self, agent_id: String, model_type: String, stake: u64) -> Result<(), String> {
-        let expected_hash = self.model_hashes.get(&model_type)
-            .ok_or("Unknown model type")?;
+    // Current time according to the registry's clock
+    pub fn now(&self) -> u64 {
+        self.clock.now()
+    }
+
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    // Domain events for every change to the registry and the market built on it
+    pub fn events(&self) -> &EventLog {
+        &self.events
+    }
+
+    // Log to `events` from now on, for registries sharing one market's log
+    pub(crate) fn share_events(&mut self, events: EventLog) {
+        self.events = events;
+    }
+
+    // Register an agent with verified capabilities for a single model
+    pub fn register_agent(&mut self, agent_id: String, model_type: String, stake: u64, public_key: VerifyingKey) -> SpectreResult<()> {
+        self.register_agent_with_models(agent_id, vec![model_type], stake, public_key)
+    }
+
+    // Register an agent that can run several models under one SDKey; an
+    // agent id can only be registered once
+    pub fn register_agent_with_models(
+        &mut self,
+        agent_id: String,
+        model_types: Vec<String>,
+        stake: u64,
+        public_key: VerifyingKey,
+    ) -> SpectreResult<()> {
+        self.enroll(agent_id, model_types, stake, public_key, false)
+    }
+
+    // register_agent_with_models, for a market that bonded `stake` on its
+    // ledger (`on_ledger`) so replaying the event bonds it there too
+    pub(crate) fn enroll(
+        &mut self,
+        agent_id: String,
+        model_types: Vec<String>,
+        stake: u64,
+        public_key: VerifyingKey,
+        on_ledger: bool,
+    ) -> SpectreResult<()> {
+        if self.verified_agents.contains_key(&agent_id) {
+            return Err(SpectreError::AgentAlreadyRegistered { agent_id });
+        }
+        let now = self.now();
+        let mut models = Vec::with_capacity(model_types.len());
+        for model_type in model_types {
+            let entry = self.catalog.available(&model_type, now)?;
+            models.push(ModelCapability {
+                max_tokens: entry.max_tokens,
+                model_type,
+            });
+        }
 
         let capability = AgentCapability {
-            model_type: model_type.clone(),
-            verified_hash: expected_hash.clone(),
-            max_tokens: match model_type.as_str() {
-                "LLaMA-3-70B" => 8192,
-                "GPT-4-Turbo" => 128000,
-                "Claude-3-Opus" => 200000,
-                _ => 4096,
-            },
-            reputation_score: 1.0, // Start with perfect reputation
-            stake_amount: stake,
+            public_key: hex::encode(public_key.as_bytes()),
+            models,
         };
 
-        self.verified_agents.insert(agent_id, capability);
+        self.stakes.deposit(&agent_id, stake);
+        self.verified_agents.insert(agent_id.clone(), capability.clone());
+        self.events.emit(now, DomainEvent::AgentRegistered { agent_id, capability, stake, on_ledger });
         Ok(())
     }
 
-    // Verify an agent's proof of computation
-    pub fn verify_sdkey_proof(&self, proof: &SDKeyProof, task_complexity: u32) -> Result<bool, String> {
+    // Check that an agent may take on a task for `model_type`, without a proof
+    pub fn check_eligibility(&self, agent_id: &str, model_type: &str, task_complexity: u32) -> SpectreResult<&ModelCapability> {
+        let agent_capability = self.verified_agents.get(agent_id)
+            .ok_or_else(|| SpectreError::AgentNotRegistered { agent_id: agent_id.to_string() })?;
+
+        let model = agent_capability.model(model_type)
+            .ok_or_else(|| SpectreError::CapabilityMismatch {
+                agent_id: agent_id.to_string(),
+                required_model: model_type.to_string(),
+            })?;
+
+        // Check if agent has sufficient capacity for task. The catalog's
+        // current limit wins, so changing it reaches agents already registered.
+        let max_tokens = self.catalog.get(model_type).map_or(model.max_tokens, |entry| entry.max_tokens);
+        if task_complexity > max_tokens {
+            return Err(SpectreError::CapacityExceeded {
+                agent_id: agent_id.to_string(),
+                requested: task_complexity,
+                max_tokens,
+            });
+        }
+
+        // Verify reputation threshold, overall and on this model
+        let now = self.now();
+        let score = self.reputation.score(agent_id, now)
+            .min(self.reputation.model_score(agent_id, model_type, now));
+        if score < MIN_REPUTATION {
+            return Err(SpectreError::LowReputation {
+                agent_id: agent_id.to_string(),
+                score,
+                threshold: MIN_REPUTATION,
+            });
+        }
+
+        // Verify bonded stake (economic security)
+        let staked = self.stakes.bonded(agent_id);
+        if staked < MIN_STAKE {
+            return Err(SpectreError::InsufficientStake {
+                agent_id: agent_id.to_string(),
+                staked,
+                required: MIN_STAKE,
+            });
+        }
+
+        Ok(model)
+    }
+
+    // Verify an agent's proof of computation against whichever of its models the proof claims
+    pub fn verify_sdkey_proof(&mut self, proof: &SDKeyProof, task_complexity: u32) -> SpectreResult<bool> {
+        let agent_id = &proof.agent_id;
+        let agent_capability = self.verified_agents.get(agent_id)
+            .ok_or_else(|| SpectreError::AgentNotRegistered { agent_id: agent_id.clone() })?;
+
+        let now = self.now();
+        let model_type = agent_capability.models.iter()
+            .find(|m| self.catalog.is_hash_accepted(&m.model_type, &proof.model_hash, now))
+            .map(|m| m.model_type.clone())
+            .ok_or_else(|| SpectreError::HashMismatch {
+                agent_id: agent_id.clone(),
+                expected: agent_capability.models.iter()
+                    .map(|m| self.expected_hashes(&m.model_type, now))
+                    .collect::<Vec<_>>()
+                    .join(","),
+                actual: proof.model_hash.clone(),
+            })?;
+
+        self.verify_proof_for_model(proof, &model_type, task_complexity)
+    }
+
+    // Verify an agent's proof of computation for a specific required model:
+    // the evidence, then its freshness, using up its nonce
+    pub fn verify_proof_for_model(&mut self, proof: &SDKeyProof, model_type: &str, task_complexity: u32) -> SpectreResult<bool> {
+        self.verify_evidence(proof, model_type, task_complexity)?;
+
+        // 5. Reject stale, future-dated and replayed proofs. The signature was
+        //    checked in step 2, so only the agent itself can use up its nonces.
+        self.check_freshness(proof)?;
+        self.consume_nonce(proof)?;
+
+        Ok(true)
+    }
+
+    // Check what a proof claims, whenever it is checked: the agent's
+    // eligibility, its signature, the model build and the execution evidence.
+    // Freshness is a matter of when the proof was handed in (see admit_proof).
+    pub fn verify_evidence(&self, proof: &SDKeyProof, model_type: &str, task_complexity: u32) -> SpectreResult<()> {
+        let agent_id = &proof.agent_id;
+
+        // 1. Agent must hold the model, with capacity, reputation and stake to match
+        self.check_eligibility(agent_id, model_type, task_complexity)?;
+
+        // 2. The agent must have signed the proof, whatever backend checks the
+        //    execution evidence: every later failure is then its own doing
+        self.authenticate(proof)?;
+
+        // 3. Verify model hash is a currently accepted build of the model
+        let now = self.now();
+        if !self.catalog.is_hash_accepted(model_type, &proof.model_hash, now) {
+            return Err(SpectreError::HashMismatch {
+                agent_id: agent_id.clone(),
+                expected: self.expected_hashes(model_type, now),
+                actual: proof.model_hash.clone(),
+            });
+        }
+
+        // 4. Verify the execution evidence with the backend configured for this model
+        self.verifier_for(model_type).verify(proof, &self.verified_agents[agent_id])
+    }
+
+    // Take in a proof handed over now (a submitted result): it must be the
+    // agent's own, fresh and not replayed. Its nonce is used up here, so a
+    // later verify_evidence only judges the evidence, however long it waits.
+    pub fn admit_proof(&mut self, proof: &SDKeyProof) -> SpectreResult<()> {
+        self.authenticate(proof)?;
+        self.check_freshness(proof)?;
+        self.consume_nonce(proof)
+    }
+
+    // Check the proof carries the registered agent's own Ed25519 signature,
+    // so it is evidence of what that agent claimed
+    pub fn authenticate(&self, proof: &SDKeyProof) -> SpectreResult<()> {
         let agent_capability = self.verified_agents.get(&proof.agent_id)
-            .ok_or("Agent not registered")?;
+            .ok_or_else(|| SpectreError::AgentNotRegistered { agent_id: proof.agent_id.clone() })?;
+        SignatureVerifier.verify(proof, agent_capability)
+    }
 
-        // 1. Verify model hash matches registered capability
-        if proof.model_hash != agent_capability.verified_hash {
-            return Err("Model hash mismatch - potential model substitution attack".to_string());
+    // Check a proof's timestamp against the freshness window and allowed clock skew
+    fn check_freshness(&self, proof: &SDKeyProof) -> SpectreResult<()> {
+        let now = self.now();
+        if proof.timestamp > now.saturating_add(self.max_clock_skew_secs) {
+            return Err(SpectreError::FutureProof {
+                agent_id: proof.agent_id.clone(),
+                timestamp: proof.timestamp,
+                now,
+                max_skew_secs: self.max_clock_skew_secs,
+            });
         }
+        if proof.timestamp.saturating_add(self.proof_max_age_secs) < now {
+            return Err(SpectreError::StaleProof {
+                agent_id: proof.agent_id.clone(),
+                timestamp: proof.timestamp,
+                now,
+                max_age_secs: self.proof_max_age_secs,
+            });
+        }
+        Ok(())
+    }
 
-        // 2. Check if agent has sufficient capacity for task
-        if task_complexity > agent_capability.max_tokens {
-            return Err("Task exceeds agent's verified capacity".to_string());
+    // Record the proof's nonce, rejecting it if the agent already used it
+    fn consume_nonce(&mut self, proof: &SDKeyProof) -> SpectreResult<()> {
+        let now = self.now();
+        let window = self.nonce_window(&proof.agent_id, now);
+        if window.seen.contains_key(&proof.nonce) {
+            return Err(SpectreError::ReplayedProof {
+                agent_id: proof.agent_id.clone(),
+                nonce: proof.nonce,
+            });
         }
+        window.seen.insert(proof.nonce, proof.timestamp);
+        self.events.emit(now, DomainEvent::ProofAccepted {
+            agent_id: proof.agent_id.clone(),
+            nonce: proof.nonce,
+            timestamp: proof.timestamp,
+        });
+        Ok(())
+    }
 
-        // 3. Verify reputation threshold
-        if agent_capability.reputation_score < 0.7 {
-            return Err("Agent reputation below threshold".to_string());
+    // An agent's nonce window, pruned to what is still fresh at `now`
+    fn nonce_window(&mut self, agent_id: &str, now: u64) -> &mut NonceWindow {
+        let oldest_accepted = now.saturating_sub(self.proof_max_age_secs);
+        let window = self.used_nonces.entry(agent_id.to_string()).or_default();
+        window.prune(oldest_accepted);
+        window
+    }
+
+    // Apply the registry's share of an event replayed from a log, without emitting it again
+    pub(crate) fn apply_event(&mut self, record: &EventRecord) -> SpectreResult<()> {
+        let at = record.at;
+        match &record.event {
+            DomainEvent::AgentRegistered { agent_id, capability, stake, .. } => {
+                self.stakes.deposit(agent_id, *stake);
+                self.verified_agents.insert(agent_id.clone(), capability.clone());
+            }
+            DomainEvent::StakeDeposited { agent_id, amount, .. } => self.stakes.deposit(agent_id, *amount),
+            DomainEvent::UnstakeRequested { agent_id, amount, .. } => {
+                self.stakes.request_withdrawal(agent_id, *amount, at)?;
+            }
+            DomainEvent::StakeWithdrawn { agent_id, .. } => {
+                self.stakes.claim_unbonded(agent_id, at);
+            }
+            DomainEvent::ProofAccepted { agent_id, nonce, timestamp } => {
+                self.nonce_window(agent_id, at).seen.insert(*nonce, *timestamp);
+            }
+            DomainEvent::ReputationRecorded { event } => self.reputation.record(event.clone()),
+            DomainEvent::AgentSlashed { event, .. } => {
+                self.stakes.slash(&event.agent_id, event.offense, event.job_id, event.requester.as_deref(), event.at)?;
+            }
+            _ => {}
         }
+        Ok(())
+    }
 
-        // 4. Verify stake amount (economic security)
-        if agent_capability.stake_amount < 1000 {
-            return Err("Insufficient stake for task verification".to_string());
+    // Copy of the registry's accumulated state, for persistence
+    pub fn snapshot(&self) -> RegistrySnapshot {
+        RegistrySnapshot {
+            verified_agents: self.verified_agents.clone(),
+            stakes: self.stakes.clone(),
+            reputation: self.reputation.events().cloned().collect(),
+            used_nonces: self.used_nonces.iter().map(|(agent_id, window)| (agent_id.clone(), window.seen.clone())).collect(),
         }
+    }
 
-        // 5. Simulate ZK proof verification (in real system, this would be cryptographic)
-        let is_valid_proof = self.simulate_zk_verification(&proof.execution_proof);
-        if !is_valid_proof {
-            return Err("Invalid execution proof".to_string());
+    // Copy of the settings the registry's state depends on, for persistence
+    pub fn config(&self) -> RegistryConfig {
+        RegistryConfig {
+            catalog: self.catalog.clone(),
+            proof_max_age_secs: self.proof_max_age_secs,
+            max_clock_skew_secs: self.max_clock_skew_secs,
+            unbonding_period_secs: self.stakes.unbonding_period_secs,
+            job_stake_ratio_bps: self.stakes.job_stake_ratio_bps,
+            slash_policies: self.stakes.policies.clone(),
         }
+    }
 
-        Ok(true)
+    // Replace the registry's catalog, freshness and staking settings with `config`
+    pub fn apply_config(&mut self, config: RegistryConfig) {
+        self.catalog = config.catalog;
+        self.proof_max_age_secs = config.proof_max_age_secs;
+        self.max_clock_skew_secs = config.max_clock_skew_secs;
+        self.stakes.unbonding_period_secs = config.unbonding_period_secs;
+        self.stakes.job_stake_ratio_bps = config.job_stake_ratio_bps;
+        self.stakes.policies = config.slash_policies;
+    }
+
+    // Replace the registry's accumulated state with `snapshot`, keeping its configuration
+    pub fn restore(&mut self, snapshot: RegistrySnapshot) {
+        self.verified_agents = snapshot.verified_agents;
+        self.stakes.restore(snapshot.stakes);
+        self.reputation.restore(snapshot.reputation);
+        self.used_nonces = snapshot.used_nonces.into_iter().map(|(agent_id, seen)| (agent_id, NonceWindow { seen })).collect();
     }
 
-    // Simulate Zero-Knowledge proof verification
-    fn simulate_zk_verification(&self, proof: &str) -> bool {
-        // In real implementation, this would verify:
-        // - The agent actually ran the specified model
-        // - The computation was performed correctly
-        // - No data was leaked during execution
-        
-        // For simulation, check proof format and length
-        proof.starts_with("zk_") && proof.len() >= 10
+    // Number of nonces currently remembered for an agent
+    pub fn tracked_nonces(&self, agent_id: &str) -> usize {
+        self.used_nonces.get(agent_id).map_or(0, |w| w.seen.len())
     }
 
-    // Slash agent reputation for failed verification
-    pub fn slash_agent(&mut self, agent_id: &str, penalty: f64) {
-        if let Some(capability) = self.verified_agents.get_mut(agent_id) {
-            capability.reputation_score = (capability.reputation_score - penalty).max(0.0);
-            println!("⚠️  Agent {} slashed: reputation now {:.2}", agent_id, capability.reputation_score);
+    // Accepted hashes for a model, comma-separated for error context
+    fn expected_hashes(&self, model_type: &str, now: u64) -> String {
+        self.catalog.get(model_type)
+            .map(|entry| entry.accepted_hashes(now).join(","))
+            .unwrap_or_default()
+    }
+
+    // Start a proof for `model_type` using this registry's catalog and clock
+    pub fn proof_builder<'a>(&self, signing_key: &'a SigningKey, agent_id: String, model_type: &str) -> SpectreResult<ProofBuilder<'a>> {
+        let now = self.now();
+        Ok(ProofBuilder::from_catalog(&self.catalog, signing_key, agent_id, model_type, now)?.timestamp(now))
+    }
+
+    // The proof verifier responsible for `model_type`
+    pub fn verifier_for(&self, model_type: &str) -> &dyn ProofVerifier {
+        self.model_verifiers.get(model_type)
+            .unwrap_or(&self.default_verifier)
+            .as_ref()
+    }
+
+    // Bond additional stake for a registered agent
+    pub fn deposit_stake(&mut self, agent_id: &str, amount: u64) -> SpectreResult<()> {
+        self.add_stake(agent_id, amount, false)
+    }
+
+    // deposit_stake, noting whether the market bonded `amount` on its ledger
+    pub(crate) fn add_stake(&mut self, agent_id: &str, amount: u64, on_ledger: bool) -> SpectreResult<()> {
+        self.ensure_registered(agent_id)?;
+        self.stakes.deposit(agent_id, amount);
+        self.events.emit(self.now(), DomainEvent::StakeDeposited { agent_id: agent_id.to_string(), amount, on_ledger });
+        Ok(())
+    }
+
+    // Start unbonding free stake; returns the time it becomes claimable
+    pub fn request_unstake(&mut self, agent_id: &str, amount: u64) -> SpectreResult<u64> {
+        let now = self.now();
+        let release_at = self.stakes.request_withdrawal(agent_id, amount, now)?;
+        self.events.emit(now, DomainEvent::UnstakeRequested { agent_id: agent_id.to_string(), amount, release_at });
+        Ok(release_at)
+    }
+
+    // Release stake whose unbonding period has elapsed; returns the amount released
+    pub fn claim_unstaked(&mut self, agent_id: &str) -> u64 {
+        self.release_unstaked(agent_id, self.now(), false)
+    }
+
+    // claim_unstaked as of `now`, noting whether the market pays the amount
+    // back on its ledger; a market pays stakes.claimable(agent_id, now) first
+    pub(crate) fn release_unstaked(&mut self, agent_id: &str, now: u64, on_ledger: bool) -> u64 {
+        let amount = self.stakes.claim_unbonded(agent_id, now);
+        if amount > 0 {
+            self.events.emit(now, DomainEvent::StakeWithdrawn { agent_id: agent_id.to_string(), amount, on_ledger });
         }
+        amount
+    }
+
+    // Slash an agent's bonded stake for `offense`, compensating `requester` where the policy allows
+    pub fn slash_stake(&mut self, agent_id: &str, offense: SlashOffense, job_id: Option<u64>, requester: Option<&str>) -> SpectreResult<SlashEvent> {
+        self.apply_slash(agent_id, offense, job_id, requester, false)
+    }
+
+    // slash_stake, noting whether the market paid the slash out of its stake vault
+    pub(crate) fn apply_slash(
+        &mut self,
+        agent_id: &str,
+        offense: SlashOffense,
+        job_id: Option<u64>,
+        requester: Option<&str>,
+        on_ledger: bool,
+    ) -> SpectreResult<SlashEvent> {
+        let now = self.now();
+        let event = self.stakes.slash(agent_id, offense, job_id, requester, now)?;
+        self.events.emit(now, DomainEvent::AgentSlashed { event: event.clone(), on_ledger });
+        Ok(event)
     }
 
-    // Reward agent for successful verification
-    pub fn reward_agent(&mut self, agent_id: &str, bonus: f64) {
-        if let Some(capability) = self.verified_agents.get_mut(agent_id) {
-            capability.reputation_score = (capability.reputation_score + bonus).min(1.0);
+    fn ensure_registered(&self, agent_id: &str) -> SpectreResult<()> {
+        if self.verified_agents.contains_key(agent_id) {
+            Ok(())
+        } else {
+            Err(SpectreError::AgentNotRegistered { agent_id: agent_id.to_string() })
         }
     }
+
+    // Add a job outcome to an agent's reputation history
+    pub fn record_outcome(&mut self, agent_id: &str, model_type: &str, outcome: ReputationOutcome, latency_ms: Option<u64>) {
+        let at = self.now();
+        let event = ReputationEvent {
+            agent_id: agent_id.to_string(),
+            model_type: model_type.to_string(),
+            outcome,
+            latency_ms,
+            at,
+        };
+        self.reputation.record(event.clone());
+        self.events.emit(at, DomainEvent::ReputationRecorded { event });
+    }
+
+    // Current overall reputation score (0.0 to 1.0)
+    pub fn reputation_of(&self, agent_id: &str) -> f64 {
+        self.reputation.score(agent_id, self.now())
+    }
+
+    // Why an agent's score is what it is, overall or for one model
+    pub fn explain_reputation(&self, agent_id: &str, model_type: Option<&str>) -> ReputationScore {
+        self.reputation.explain(agent_id, model_type, self.now())
+    }
 }
 
-// Generate a mock SDKey proof for testing
-pub fn generate_mock_proof(agent_id: String, model_type: &str) -> SDKeyProof {
-    let model_hash = match model_type {
-        "LLaMA-3-70B" => "0xa1b2c3d4e5f6",
-        "GPT-4-Turbo" => "0xf6e5d4c3b2a1", 
-        "Claude-3-Opus" => "0x123456789abc",
-        _ => "0x000000000000",
-    };
+// Generate a fresh Ed25519 keypair for an agent
+pub fn generate_agent_keypair() -> SigningKey {
+    SigningKey::generate(&mut rand::rngs::OsRng)
+}
 
-    SDKeyProof {
-        agent_id,
-        model_hash: model_hash.to_string(),
-        execution_proof: format!("zk_proof_{}", rand::random::<u32>()),
-        timestamp: std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs(),
-        nonce: rand::random::<u32>(),
+// Builds SDKey proofs signed with an agent's keypair
+pub struct ProofBuilder<'a> {
+    signing_key: &'a SigningKey,
+    agent_id: String,
+    model_hash: String,
+    job_id: u64,
+    result_digest: String,
+    timestamp: u64,
+    nonce: u32,
+}
+
+impl<'a> ProofBuilder<'a> {
+    // Proof for the latest build of a model in the built-in catalog; fails with
+    // UnknownModel rather than signing a hash no registry would recognise
+    pub fn new(signing_key: &'a SigningKey, agent_id: String, model_type: &str) -> SpectreResult<Self> {
+        Self::from_catalog(&ModelCatalog::builtin(), signing_key, agent_id, model_type, u64::MAX)
+    }
+
+    // Proof for the build of `model_type` that is current in `catalog` at `now`
+    pub fn from_catalog(catalog: &ModelCatalog, signing_key: &'a SigningKey, agent_id: String, model_type: &str, now: u64) -> SpectreResult<Self> {
+        let version = catalog.get(model_type)
+            .ok_or_else(|| SpectreError::UnknownModel { model_type: model_type.to_string() })?
+            .current_version(now)
+            .ok_or_else(|| SpectreError::ModelDeprecated { model_type: model_type.to_string() })?;
+        Ok(Self::with_model_hash(signing_key, agent_id, &version.hash))
+    }
+
+    pub fn with_model_hash(signing_key: &'a SigningKey, agent_id: String, model_hash: &str) -> Self {
+        ProofBuilder {
+            signing_key,
+            agent_id,
+            model_hash: model_hash.to_string(),
+            job_id: 0,
+            result_digest: result_digest(b""),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            nonce: rand::random::<u32>(),
+        }
+    }
+
+    pub fn job(mut self, job_id: u64) -> Self {
+        self.job_id = job_id;
+        self
+    }
+
+    // Commit to the result bytes the agent produced
+    pub fn result(mut self, result: &[u8]) -> Self {
+        self.result_digest = result_digest(result);
+        self
     }
-}
\ No newline at end of file
+
+    // Override the model hash (e.g. to claim a different model build)
+    pub fn model_hash(mut self, model_hash: &str) -> Self {
+        self.model_hash = model_hash.to_string();
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: u64) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub fn nonce(mut self, nonce: u32) -> Self {
+        self.nonce = nonce;
+        self
+    }
+
+    pub fn build(self) -> SDKeyProof {
+        let mut proof = SDKeyProof {
+            agent_id: self.agent_id,
+            model_hash: self.model_hash,
+            execution_proof: String::new(),
+            timestamp: self.timestamp,
+            nonce: self.nonce,
+            job_id: self.job_id,
+            result_digest: self.result_digest,
+            signature: String::new(),
+        };
+        proof.execution_proof = execution_commitment(&proof);
+        let signature = self.signing_key.sign(&proof.signing_message());
+        proof.signature = hex::encode(signature.to_bytes());
+        proof
+    }
+}
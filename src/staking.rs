@@ -0,0 +1,374 @@
+// src/staking.rs
+// Stake Management: bonded collateral, per-job locks, unbonding and slashing
+
+use crate::error::{SpectreError, SpectreResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// How long withdrawn stake stays slashable before it can be claimed (7 days)
+pub const DEFAULT_UNBONDING_PERIOD_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Share of a job's reward an agent must lock from its stake while working it
+pub const DEFAULT_JOB_STAKE_RATIO_BPS: u64 = 5_000;
+
+/// Misbehaviour that costs an agent stake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SlashOffense {
+    /// Proof claimed a model build the agent isn't running (model substitution)
+    HashMismatch,
+    /// Execution evidence failed cryptographic verification
+    InvalidProof,
+    /// Claimed a job and let it expire without delivering
+    MissedDeadline,
+}
+
+impl SlashOffense {
+    /// The offense a failed verification amounts to, if it is slashable at all
+    pub fn from_error(error: &SpectreError) -> Option<Self> {
+        match error {
+            SpectreError::HashMismatch { .. } => Some(SlashOffense::HashMismatch),
+            SpectreError::InvalidProof { .. } => Some(SlashOffense::InvalidProof),
+            _ => None,
+        }
+    }
+}
+
+/// How much of an agent's stake an offense costs, and where it goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlashPolicy {
+    /// Fraction of bonded and unbonding stake slashed, in basis points
+    pub slash_bps: u64,
+    /// Fraction of the slashed amount paid to the harmed requester; the rest is burned
+    pub to_requester_bps: u64,
+}
+
+impl SlashPolicy {
+    pub fn for_offense(offense: SlashOffense) -> Self {
+        match offense {
+            SlashOffense::HashMismatch => SlashPolicy { slash_bps: 5_000, to_requester_bps: 5_000 },
+            SlashOffense::InvalidProof => SlashPolicy { slash_bps: 2_000, to_requester_bps: 5_000 },
+            SlashOffense::MissedDeadline => SlashPolicy { slash_bps: 500, to_requester_bps: 10_000 },
+        }
+    }
+}
+
+/// Stake withdrawn but still inside the unbonding period.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Unbonding {
+    pub amount: u64,
+    pub release_at: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StakeAccount {
+    /// Total bonded stake, including any locked against jobs
+    pub bonded: u64,
+    /// Stake locked per active job
+    pub locked: BTreeMap<u64, u64>,
+    pub unbonding: Vec<Unbonding>,
+}
+
+impl StakeAccount {
+    pub fn total_locked(&self) -> u64 {
+        self.locked.values().sum()
+    }
+
+    /// Bonded stake not locked against any job
+    pub fn free(&self) -> u64 {
+        self.bonded.saturating_sub(self.total_locked())
+    }
+
+    /// Bonded stake plus stake still unbonding: everything a slash can reach
+    pub fn slashable(&self) -> u64 {
+        let unbonding = self.unbonding.iter().map(|entry| entry.amount as u128).sum::<u128>();
+        (self.bonded as u128 + unbonding).min(u64::MAX as u128) as u64
+    }
+
+    // Take `amount` (at most slashable()) from bonded stake first, then from
+    // the newest unbonding entries
+    fn take(&mut self, amount: u64) {
+        let from_bonded = amount.min(self.bonded);
+        self.bonded -= from_bonded;
+        let mut rest = amount - from_bonded;
+        while rest > 0 {
+            let Some(entry) = self.unbonding.last_mut() else { break };
+            let taken = rest.min(entry.amount);
+            entry.amount -= taken;
+            rest -= taken;
+            if entry.amount == 0 {
+                self.unbonding.pop();
+            }
+        }
+    }
+}
+
+/// Audit record of a single slash.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SlashEvent {
+    pub seq: u64,
+    pub agent_id: String,
+    pub offense: SlashOffense,
+    pub job_id: Option<u64>,
+    pub requester: Option<String>,
+    pub slashed: u64,
+    pub burned: u64,
+    pub to_requester: u64,
+    pub at: u64,
+}
+
+/// Tracks every agent's stake as collateral.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StakeManager {
+    accounts: HashMap<String, StakeAccount>,
+    slash_log: Vec<SlashEvent>,
+    pub unbonding_period_secs: u64,
+    pub job_stake_ratio_bps: u64,
+    pub policies: HashMap<SlashOffense, SlashPolicy>,
+}
+
+impl Default for StakeManager {
+    fn default() -> Self {
+        let policies = [SlashOffense::HashMismatch, SlashOffense::InvalidProof, SlashOffense::MissedDeadline]
+            .into_iter()
+            .map(|offense| (offense, SlashPolicy::for_offense(offense)))
+            .collect();
+        StakeManager {
+            accounts: HashMap::new(),
+            slash_log: Vec::new(),
+            unbonding_period_secs: DEFAULT_UNBONDING_PERIOD_SECS,
+            job_stake_ratio_bps: DEFAULT_JOB_STAKE_RATIO_BPS,
+            policies,
+        }
+    }
+}
+
+impl StakeManager {
+    // Take `other`'s accounts and slash log, keeping this manager's periods and policies
+    pub(crate) fn restore(&mut self, other: StakeManager) {
+        self.accounts = other.accounts;
+        self.slash_log = other.slash_log;
+    }
+
+    pub fn account(&self, agent_id: &str) -> Option<&StakeAccount> {
+        self.accounts.get(agent_id)
+    }
+
+    pub fn bonded(&self, agent_id: &str) -> u64 {
+        self.accounts.get(agent_id).map_or(0, |a| a.bonded)
+    }
+
+    pub fn free(&self, agent_id: &str) -> u64 {
+        self.accounts.get(agent_id).map_or(0, |a| a.free())
+    }
+
+    /// Bond new stake (initial deposit or top-up)
+    pub fn deposit(&mut self, agent_id: &str, amount: u64) {
+        self.accounts.entry(agent_id.to_string()).or_default().bonded += amount;
+    }
+
+    /// Start unbonding `amount` of free stake; returns when it can be claimed.
+    /// It stays slashable until then.
+    pub fn request_withdrawal(&mut self, agent_id: &str, amount: u64, now: u64) -> SpectreResult<u64> {
+        let release_at = now.checked_add(self.unbonding_period_secs).ok_or(SpectreError::UnbondingOverflow {
+            now,
+            unbonding_period_secs: self.unbonding_period_secs,
+        })?;
+        let account = self.accounts.get_mut(agent_id)
+            .ok_or_else(|| SpectreError::AgentNotRegistered { agent_id: agent_id.to_string() })?;
+        if account.free() < amount {
+            return Err(SpectreError::InsufficientStake {
+                agent_id: agent_id.to_string(),
+                staked: account.free(),
+                required: amount,
+            });
+        }
+        account.bonded -= amount;
+        account.unbonding.push(Unbonding { amount, release_at });
+        Ok(release_at)
+    }
+
+    /// Total of the unbonding entries whose period has elapsed by `now`, left in place
+    pub fn claimable(&self, agent_id: &str, now: u64) -> u64 {
+        self.accounts.get(agent_id).map_or(0, |account| {
+            account.unbonding.iter().filter(|entry| entry.release_at <= now).map(|entry| entry.amount).sum()
+        })
+    }
+
+    /// Release every unbonding entry whose period has elapsed; returns the total claimed
+    pub fn claim_unbonded(&mut self, agent_id: &str, now: u64) -> u64 {
+        let Some(account) = self.accounts.get_mut(agent_id) else {
+            return 0;
+        };
+        let mut claimed = 0;
+        account.unbonding.retain(|entry| {
+            if entry.release_at <= now {
+                claimed += entry.amount;
+                false
+            } else {
+                true
+            }
+        });
+        claimed
+    }
+
+    /// Stake that must be locked to work a job paying `reward`, capped at `u64::MAX`
+    pub fn required_for_job(&self, reward: u64) -> u64 {
+        let required = reward as u128 * self.job_stake_ratio_bps as u128 / 10_000;
+        required.min(u64::MAX as u128) as u64
+    }
+
+    /// Lock stake proportional to `reward` for the duration of a job
+    pub fn lock_for_job(&mut self, agent_id: &str, job_id: u64, reward: u64) -> SpectreResult<u64> {
+        let required = self.required_for_job(reward);
+        let account = self.accounts.get_mut(agent_id)
+            .ok_or_else(|| SpectreError::AgentNotRegistered { agent_id: agent_id.to_string() })?;
+        if account.free() < required {
+            return Err(SpectreError::InsufficientStake {
+                agent_id: agent_id.to_string(),
+                staked: account.free(),
+                required,
+            });
+        }
+        account.locked.insert(job_id, required);
+        Ok(required)
+    }
+
+    /// Unlock a job's stake once the job is resolved
+    pub fn release_job(&mut self, agent_id: &str, job_id: u64) -> u64 {
+        self.accounts.get_mut(agent_id)
+            .and_then(|account| account.locked.remove(&job_id))
+            .unwrap_or(0)
+    }
+
+    /// What [`slash`](StakeManager::slash) would take from the agent right
+    /// now, as `(to_requester, burned)`, without changing anything
+    pub fn preview_slash(&self, agent_id: &str, offense: SlashOffense, requester: Option<&str>) -> SpectreResult<(u64, u64)> {
+        let policy = self.policies.get(&offense).copied()
+            .unwrap_or_else(|| SlashPolicy::for_offense(offense));
+        let account = self.accounts.get(agent_id)
+            .ok_or_else(|| SpectreError::AgentNotRegistered { agent_id: agent_id.to_string() })?;
+        let share = |amount: u64, bps: u64| (amount as u128 * bps as u128 / 10_000).min(amount as u128) as u64;
+        let slashed = share(account.slashable(), policy.slash_bps);
+        let to_requester = match requester {
+            Some(_) => share(slashed, policy.to_requester_bps),
+            None => 0,
+        };
+        Ok((to_requester, slashed - to_requester))
+    }
+
+    /// Slash an agent's stake according to the offense's policy. The share is
+    /// of bonded and unbonding stake together, taken from bonded stake first
+    /// and then from the newest unbonding entries, so unstaking right after
+    /// an offense doesn't escape it.
+    pub fn slash(
+        &mut self,
+        agent_id: &str,
+        offense: SlashOffense,
+        job_id: Option<u64>,
+        requester: Option<&str>,
+        now: u64,
+    ) -> SpectreResult<SlashEvent> {
+        let (to_requester, burned) = self.preview_slash(agent_id, offense, requester)?;
+        let slashed = to_requester + burned;
+        let account = self.accounts.get_mut(agent_id)
+            .ok_or_else(|| SpectreError::AgentNotRegistered { agent_id: agent_id.to_string() })?;
+
+        // The job's lock is consumed by the slash. Locks for the agent's other
+        // jobs stay: if they now exceed the bond, nothing is free until they resolve.
+        if let Some(job_id) = job_id {
+            account.locked.remove(&job_id);
+        }
+        account.take(slashed);
+
+        let event = SlashEvent {
+            seq: self.slash_log.len() as u64,
+            agent_id: agent_id.to_string(),
+            offense,
+            job_id,
+            requester: requester.map(str::to_string),
+            slashed,
+            burned,
+            to_requester,
+            at: now,
+        };
+        self.slash_log.push(event.clone());
+        Ok(event)
+    }
+
+    /// Slash history for one agent, oldest first
+    pub fn slash_events(&self, agent_id: &str) -> Vec<&SlashEvent> {
+        self.slash_log.iter().filter(|e| e.agent_id == agent_id).collect()
+    }
+
+    pub fn slash_log(&self) -> &[SlashEvent] {
+        &self.slash_log
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locking_unbonding_and_slashing() {
+        let mut stakes = StakeManager::default();
+        stakes.deposit("agent", 4_000);
+        assert_eq!(stakes.lock_for_job("agent", 1, 6_000).unwrap(), 3_000);
+        assert_eq!(stakes.free("agent"), 1_000);
+
+        // Locked stake can't be withdrawn
+        assert!(stakes.request_withdrawal("agent", 2_000, 0).is_err());
+        let release_at = stakes.request_withdrawal("agent", 1_000, 0).unwrap();
+        assert_eq!(stakes.claim_unbonded("agent", release_at - 1), 0);
+        assert_eq!(stakes.claim_unbonded("agent", release_at), 1_000);
+
+        let event = stakes.slash("agent", SlashOffense::HashMismatch, Some(1), Some("requester"), 5).unwrap();
+        assert_eq!((event.slashed, event.to_requester, event.burned), (1_500, 750, 750));
+        assert_eq!(stakes.bonded("agent"), 1_500);
+        assert_eq!(stakes.free("agent"), 1_500);
+        assert_eq!(stakes.slash_events("agent").len(), 1);
+    }
+
+    #[test]
+    fn test_slash_keeps_locks_for_other_jobs() {
+        let mut stakes = StakeManager::default();
+        stakes.deposit("agent", 4_000);
+        stakes.lock_for_job("agent", 1, 2_000).unwrap();
+        stakes.lock_for_job("agent", 2, 4_000).unwrap();
+
+        // Job 2's lock now exceeds the bond, but it is still held
+        stakes.slash("agent", SlashOffense::HashMismatch, Some(1), None, 5).unwrap();
+        assert_eq!(stakes.bonded("agent"), 2_000);
+        assert_eq!(stakes.free("agent"), 0);
+        assert_eq!(stakes.release_job("agent", 2), 2_000);
+        assert_eq!(stakes.free("agent"), 2_000);
+
+        assert_eq!(stakes.required_for_job(u64::MAX), u64::MAX / 2);
+    }
+
+    #[test]
+    fn test_unbonding_stake_stays_slashable() {
+        let mut stakes = StakeManager::default();
+        stakes.deposit("agent", 4_000);
+        stakes.request_withdrawal("agent", 1_000, 0).unwrap();
+        let release_at = stakes.request_withdrawal("agent", 2_000, 10).unwrap();
+        assert_eq!(stakes.account("agent").unwrap().slashable(), 4_000);
+
+        // Unstaking right after cheating doesn't escape the slash: half of all
+        // 4_000 is taken, the bond first and then the newest unbonding entry
+        let event = stakes.slash("agent", SlashOffense::HashMismatch, None, None, 20).unwrap();
+        assert_eq!(event.slashed, 2_000);
+        assert_eq!(stakes.bonded("agent"), 0);
+        assert_eq!(stakes.account("agent").unwrap().unbonding, vec![
+            Unbonding { amount: 1_000, release_at: DEFAULT_UNBONDING_PERIOD_SECS },
+            Unbonding { amount: 1_000, release_at },
+        ]);
+        assert_eq!(stakes.claim_unbonded("agent", release_at), 2_000);
+
+        // A period too long to add to the clock is an error, not an overflow
+        stakes.deposit("agent", 100);
+        stakes.unbonding_period_secs = u64::MAX;
+        assert_eq!(stakes.request_withdrawal("agent", 100, 1).unwrap_err().code(), "UNBONDING_OVERFLOW");
+        assert_eq!(stakes.bonded("agent"), 100);
+    }
+}
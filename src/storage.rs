@@ -0,0 +1,591 @@
+// src/storage.rs
+// Durable market state: write-ahead log, snapshots, crash recovery and compaction
+
+use crate::clock::{Clock, SystemClock};
+use crate::error::{SpectreError, SpectreResult};
+use crate::ledger::Ledger;
+use crate::lifecycle::{ExpirySweep, JobSettlement};
+use crate::psy_integration::{PsyTransaction, PsyTxUpdate};
+use crate::sdkey_verification::{RegistryConfig, RegistrySnapshot, SDKeyProof, SDKeyRegistry, SDKeyRegistryBuilder, VerifyingKey};
+use crate::{Job, JobRequest, SpectreMarket};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Log records between automatic compactions unless configured otherwise
+pub const DEFAULT_SNAPSHOT_EVERY: u64 = 1_000;
+
+const WAL_FILE: &str = "wal.log";
+const SNAPSHOT_FILE: &str = "snapshot.json";
+
+/// A state-changing call on a [`PersistentMarket`], as written to its log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum MarketOp {
+    Mint { account: String, amount: u64 },
+    Transfer { from: String, to: String, amount: u64 },
+    RegisterAgent { agent_id: String, algos: Vec<String>, stake: u64, public_key: String },
+    DepositStake { agent_id: String, amount: u64 },
+    RequestUnstake { agent_id: String, amount: u64 },
+    WithdrawUnstaked { agent_id: String },
+    PostJob { request: JobRequest },
+    ClaimJob { job_id: u64, agent_id: String },
+    AttemptJobExecution { job_id: u64, proof: SDKeyProof },
+    SubmitResult { job_id: u64, proof: SDKeyProof },
+    VerifyResult { job_id: u64 },
+    DisputeJob { job_id: u64 },
+    SettleJob { job_id: u64 },
+    SettleOnConfirmation { job_id: u64, tx_id: String },
+    CancelJob { job_id: u64 },
+    ExpireJobs { now: u64 },
+    TrackTx { tx: PsyTransaction },
+    ApplyTxUpdate { update: PsyTxUpdate },
+}
+
+/// One entry of the write-ahead log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalRecord {
+    /// Position in the log, counting from 1 without gaps
+    pub seq: u64,
+    /// Market time the operation ran at; replay runs it at the same time
+    pub at: u64,
+    /// Sequence number of the last domain event once the operation ran;
+    /// replaying it must land on the same one
+    pub event_seq: u64,
+    pub op: MarketOp,
+}
+
+/// Full market state as of log record `seq`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketSnapshot {
+    pub seq: u64,
+    /// By id
+    pub jobs: Vec<Job>,
+    pub ledger: Ledger,
+    pub registry: RegistrySnapshot,
+    pub pending_txs: Vec<PsyTransaction>,
+    /// Sequence number of the last domain event the state had emitted
+    #[serde(default)]
+    pub event_seq: u64,
+    /// Registry configuration the state was built under
+    #[serde(default)]
+    pub registry_config: Option<RegistryConfig>,
+}
+
+/// Where a [`PersistentMarket`] keeps its log and snapshots.
+pub trait MarketStorage: Send {
+    /// Append `record` durably: it must survive a crash once this returns
+    fn append(&mut self, record: &WalRecord) -> SpectreResult<()>;
+
+    /// Durably replace the latest snapshot
+    fn save_snapshot(&mut self, snapshot: &MarketSnapshot) -> SpectreResult<()>;
+
+    /// Latest snapshot, if any, and the records logged after it, in order
+    /// and without gaps
+    fn load(&mut self) -> SpectreResult<(Option<MarketSnapshot>, Vec<WalRecord>)>;
+
+    /// Drop records up to and including `through_seq`, which a saved
+    /// snapshot covers
+    fn compact(&mut self, through_seq: u64) -> SpectreResult<()>;
+}
+
+/// [`MarketStorage`] in a directory: `wal.log` holds one JSON record per
+/// line, `snapshot.json` the latest snapshot.
+///
+/// Appends are fsynced before they return. Snapshots and compacted logs are
+/// written to a temporary file and renamed into place, so a crash leaves
+/// either the old file or the new one. A crash in the middle of an append
+/// leaves an unterminated last line; loading drops it, since the operation
+/// it held was never acknowledged.
+pub struct FileStorage {
+    dir: PathBuf,
+    wal: File,
+}
+
+impl FileStorage {
+    /// Open the store in `dir`, creating the directory if needed
+    pub fn open(dir: impl AsRef<Path>) -> SpectreResult<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(|e| failure(&dir, e))?;
+        let wal = open_wal(&dir.join(WAL_FILE))?;
+        Ok(FileStorage { dir, wal })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // Every complete record in the log, truncating a torn final one
+    fn read_wal(&mut self) -> SpectreResult<Vec<WalRecord>> {
+        let path = self.dir.join(WAL_FILE);
+        let bytes = fs::read(&path).map_err(|e| failure(&path, e))?;
+        let mut records = Vec::new();
+        let mut offset = 0;
+        for line in bytes.split_inclusive(|byte| *byte == b'\n') {
+            let Some(json) = line.strip_suffix(b"\n") else {
+                self.wal.set_len(offset as u64).and_then(|_| self.wal.sync_data()).map_err(|e| failure(&path, e))?;
+                break;
+            };
+            let record = serde_json::from_slice(json)
+                .map_err(|e| failure(&path, format!("record at byte {} is corrupt: {}", offset, e)))?;
+            records.push(record);
+            offset += line.len();
+        }
+        Ok(records)
+    }
+}
+
+impl MarketStorage for FileStorage {
+    fn append(&mut self, record: &WalRecord) -> SpectreResult<()> {
+        let path = self.dir.join(WAL_FILE);
+        let mut line = serde_json::to_vec(record).map_err(|e| failure(&path, e))?;
+        line.push(b'\n');
+        let len = self.wal.metadata().map_err(|e| failure(&path, e))?.len();
+        if let Err(e) = self.wal.write_all(&line).and_then(|_| self.wal.sync_data()) {
+            // Don't leave a partial record for the next append to land after
+            let _ = self.wal.set_len(len);
+            return Err(failure(&path, e));
+        }
+        Ok(())
+    }
+
+    fn save_snapshot(&mut self, snapshot: &MarketSnapshot) -> SpectreResult<()> {
+        let path = self.dir.join(SNAPSHOT_FILE);
+        let bytes = serde_json::to_vec(snapshot).map_err(|e| failure(&path, e))?;
+        write_atomically(&path, &bytes)
+    }
+
+    fn load(&mut self) -> SpectreResult<(Option<MarketSnapshot>, Vec<WalRecord>)> {
+        let path = self.dir.join(SNAPSHOT_FILE);
+        let snapshot: Option<MarketSnapshot> = match fs::read(&path) {
+            Ok(bytes) => Some(serde_json::from_slice(&bytes).map_err(|e| failure(&path, e))?),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => return Err(failure(&path, e)),
+        };
+        let covered = snapshot.as_ref().map_or(0, |snapshot| snapshot.seq);
+        // A crash between saving a snapshot and compacting leaves covered records behind
+        let records: Vec<WalRecord> = self.read_wal()?.into_iter().filter(|record| record.seq > covered).collect();
+        for (expected, record) in (covered + 1..).zip(&records) {
+            if record.seq != expected {
+                let reason = format!("expected record {} but found {}", expected, record.seq);
+                return Err(failure(&self.dir.join(WAL_FILE), reason));
+            }
+        }
+        Ok((snapshot, records))
+    }
+
+    fn compact(&mut self, through_seq: u64) -> SpectreResult<()> {
+        let path = self.dir.join(WAL_FILE);
+        let mut bytes = Vec::new();
+        for record in self.read_wal()?.into_iter().filter(|record| record.seq > through_seq) {
+            serde_json::to_writer(&mut bytes, &record).map_err(|e| failure(&path, e))?;
+            bytes.push(b'\n');
+        }
+        write_atomically(&path, &bytes)?;
+        self.wal = open_wal(&path)?;
+        Ok(())
+    }
+}
+
+fn failure(path: &Path, reason: impl Display) -> SpectreError {
+    SpectreError::StorageFailure { path: path.display().to_string(), reason: reason.to_string() }
+}
+
+fn open_wal(path: &Path) -> SpectreResult<File> {
+    OpenOptions::new().create(true).append(true).open(path).map_err(|e| failure(path, e))
+}
+
+// Replace `path` with `bytes` so that a crash leaves either the old or the new contents
+fn write_atomically(path: &Path, bytes: &[u8]) -> SpectreResult<()> {
+    let tmp = path.with_extension("tmp");
+    let mut file = File::create(&tmp).map_err(|e| failure(&tmp, e))?;
+    file.write_all(bytes).and_then(|_| file.sync_all()).map_err(|e| failure(&tmp, e))?;
+    fs::rename(&tmp, path).map_err(|e| failure(path, e))?;
+    if let Some(dir) = path.parent() {
+        File::open(dir).and_then(|dir| dir.sync_all()).map_err(|e| failure(dir, e))?;
+    }
+    Ok(())
+}
+
+// Clock the market reads while an operation runs: pinned to the time logged
+// with the operation, so replaying it sees exactly what the first run saw
+struct OpClock {
+    inner: Arc<dyn Clock>,
+    pinned: Mutex<Option<u64>>,
+}
+
+impl OpClock {
+    fn pin(&self, at: u64) {
+        *self.pinned.lock().unwrap() = Some(at);
+    }
+
+    fn unpin(&self) {
+        *self.pinned.lock().unwrap() = None;
+    }
+}
+
+impl Clock for OpClock {
+    fn now(&self) -> u64 {
+        self.pinned.lock().unwrap().unwrap_or_else(|| self.inner.now())
+    }
+}
+
+/// A [`SpectreMarket`] whose every change is logged durably to a
+/// [`MarketStorage`] before the call returns, so a restart recovers jobs,
+/// agents, balances and pending Psy transactions.
+///
+/// Each operation runs first and is logged only if it changed something: a
+/// call that failed without touching the state leaves no record, while one
+/// that failed after changing it (a rejected result still slashes) is
+/// logged and fails again on replay. If writing the record fails, the
+/// in-memory state is ahead of storage, so every later call returns that
+/// error until the market is reopened.
+///
+/// Opening the market loads the latest snapshot and replays the log after
+/// it. Each operation is logged with the time it ran at and replays at that
+/// time, so proof freshness, deadlines and timestamps come out the same.
+/// Replay emits the same [`DomainEvent`](crate::DomainEvent)s under the
+/// same sequence numbers, so event consumers can resume from the last one
+/// they saw; a record that replays differently fails the open with
+/// `ReplayDiverged` rather than recovering the wrong state.
+///
+/// The registry's [`RegistryConfig`] (catalog, proof freshness, staking
+/// terms) is stored in every snapshot, and a new store is snapshotted
+/// straight away. Reopening restores it, so the log always replays under
+/// the settings it was written with; the builder's settings only take
+/// effect for a new store.
+///
+/// Every [`snapshot_every`](PersistentMarket::with_snapshot_every) records
+/// the market [`compact`](PersistentMarket::compact)s: it snapshots its state
+/// and drops the records the snapshot covers.
+///
+/// Psy transactions are tracked alongside the market: record each
+/// submission with [`track_tx`](PersistentMarket::track_tx) and feed status
+/// updates to [`apply_tx_update`](PersistentMarket::apply_tx_update). After a
+/// restart, hand [`pending_txs`](PersistentMarket::pending_txs) to
+/// [`PsyClient::restore_pending`](crate::PsyClient::restore_pending).
+pub struct PersistentMarket<S: MarketStorage = FileStorage> {
+    market: SpectreMarket,
+    pending_txs: HashMap<String, PsyTransaction>,
+    storage: S,
+    clock: Arc<OpClock>,
+    // Set when a change was applied but its record couldn't be written
+    unlogged: Option<SpectreError>,
+    last_seq: u64,
+    snapshot_seq: u64,
+    snapshot_every: u64,
+}
+
+impl PersistentMarket<FileStorage> {
+    /// Open the market stored in `dir`, with the default registry and system time
+    pub fn open_dir(dir: impl AsRef<Path>) -> SpectreResult<Self> {
+        Self::open(FileStorage::open(dir)?)
+    }
+}
+
+impl<S: MarketStorage> PersistentMarket<S> {
+    /// Recover the market held in `storage`, with the default registry and system time
+    pub fn open(storage: S) -> SpectreResult<Self> {
+        Self::open_with(storage, Arc::new(SystemClock), SDKeyRegistry::builder())
+    }
+
+    /// Recover the market held in `storage`, reading time from `clock` and
+    /// configuring a new store's registry with `registry` (whose own clock is
+    /// replaced); an existing store keeps the configuration it was saved with
+    pub fn open_with(mut storage: S, clock: Arc<dyn Clock>, registry: SDKeyRegistryBuilder) -> SpectreResult<Self> {
+        let clock = Arc::new(OpClock { inner: clock, pinned: Mutex::new(None) });
+        let mut market = SpectreMarket::with_registry(registry.clock(clock.clone()).build());
+        let mut pending_txs = HashMap::new();
+
+        let (snapshot, records) = storage.load()?;
+        let fresh = snapshot.is_none() && records.is_empty();
+        let mut last_seq = 0;
+        if let Some(snapshot) = snapshot {
+            last_seq = snapshot.seq;
+            if let Some(config) = snapshot.registry_config {
+                market.registry.apply_config(config);
+            }
+            market.jobs = snapshot.jobs.into_iter().map(|job| (job.id, job)).collect();
+            market.ledger = snapshot.ledger;
+            market.registry.restore(snapshot.registry);
+            market.events().resume_after(snapshot.event_seq);
+            pending_txs = snapshot.pending_txs.into_iter().map(|tx| (tx.tx_id.clone(), tx)).collect();
+        }
+        let snapshot_seq = last_seq;
+        for record in records {
+            clock.pin(record.at);
+            last_seq = record.seq;
+            replay(&mut market, &mut pending_txs, record)?;
+        }
+        clock.unpin();
+
+        let mut market = PersistentMarket {
+            market,
+            pending_txs,
+            storage,
+            clock,
+            unlogged: None,
+            last_seq,
+            snapshot_seq,
+            snapshot_every: DEFAULT_SNAPSHOT_EVERY,
+        };
+        if fresh {
+            // Pin down the configuration before anything is logged under it
+            market.snapshot()?;
+        }
+        Ok(market)
+    }
+
+    /// Compact after every `records` log records; 0 leaves compaction to the caller
+    pub fn with_snapshot_every(mut self, records: u64) -> Self {
+        self.snapshot_every = records;
+        self
+    }
+
+    /// The recovered market; change it only through this wrapper's methods
+    pub fn market(&self) -> &SpectreMarket {
+        &self.market
+    }
+
+    /// Tracked Psy transactions that aren't final yet
+    pub fn pending_txs(&self) -> &HashMap<String, PsyTransaction> {
+        &self.pending_txs
+    }
+
+    /// Sequence number of the last logged operation
+    pub fn last_seq(&self) -> u64 {
+        self.last_seq
+    }
+
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    /// Save a snapshot of the current state
+    pub fn snapshot(&mut self) -> SpectreResult<()> {
+        if let Some(e) = &self.unlogged {
+            return Err(e.clone());
+        }
+        let mut jobs: Vec<Job> = self.market.jobs.values().cloned().collect();
+        jobs.sort_unstable_by_key(|job| job.id);
+        self.storage.save_snapshot(&MarketSnapshot {
+            seq: self.last_seq,
+            jobs,
+            ledger: self.market.ledger.clone(),
+            registry: self.market.registry.snapshot(),
+            pending_txs: self.pending_txs.values().cloned().collect(),
+            event_seq: self.market.events().last_seq(),
+            registry_config: Some(self.market.registry.config()),
+        })?;
+        self.snapshot_seq = self.last_seq;
+        Ok(())
+    }
+
+    /// Snapshot the current state and drop the log records it covers
+    pub fn compact(&mut self) -> SpectreResult<()> {
+        self.snapshot()?;
+        self.storage.compact(self.snapshot_seq)
+    }
+
+    pub fn mint(&mut self, account: &str, amount: u64) -> SpectreResult<()> {
+        self.run(MarketOp::Mint { account: account.to_string(), amount }, |market, _| market.mint(account, amount))
+    }
+
+    pub fn transfer(&mut self, from: &str, to: &str, amount: u64) -> SpectreResult<()> {
+        self.run(MarketOp::Transfer { from: from.to_string(), to: to.to_string(), amount }, |market, _| {
+            market.transfer(from, to, amount)
+        })
+    }
+
+    pub fn register_agent(&mut self, agent_id: String, algos: Vec<String>, stake: u64, public_key: VerifyingKey) -> SpectreResult<()> {
+        let public_key_hex = hex::encode(public_key.as_bytes());
+        let op = MarketOp::RegisterAgent { agent_id: agent_id.clone(), algos: algos.clone(), stake, public_key: public_key_hex };
+        self.run(op, |market, _| market.register_agent(agent_id, algos, stake, public_key))
+    }
+
+    pub fn deposit_stake(&mut self, agent_id: &str, amount: u64) -> SpectreResult<()> {
+        self.run(MarketOp::DepositStake { agent_id: agent_id.to_string(), amount }, |market, _| {
+            market.deposit_stake(agent_id, amount)
+        })
+    }
+
+    pub fn request_unstake(&mut self, agent_id: &str, amount: u64) -> SpectreResult<u64> {
+        self.run(MarketOp::RequestUnstake { agent_id: agent_id.to_string(), amount }, |market, _| {
+            market.request_unstake(agent_id, amount)
+        })
+    }
+
+    pub fn withdraw_unstaked(&mut self, agent_id: &str) -> SpectreResult<u64> {
+        self.run(MarketOp::WithdrawUnstaked { agent_id: agent_id.to_string() }, |market, _| market.withdraw_unstaked(agent_id))
+    }
+
+    pub fn post_job(&mut self, id: u64, requester: String, algo: String, reward: u64) -> SpectreResult<()> {
+        self.post_job_request(JobRequest::new(id, requester, algo, reward))
+    }
+
+    pub fn post_job_request(&mut self, request: JobRequest) -> SpectreResult<()> {
+        self.run(MarketOp::PostJob { request: request.clone() }, |market, _| market.post_job_request(request))
+    }
+
+    pub fn claim_job(&mut self, job_id: u64, agent_id: &str) -> SpectreResult<()> {
+        self.run(MarketOp::ClaimJob { job_id, agent_id: agent_id.to_string() }, |market, _| market.claim_job(job_id, agent_id))
+    }
+
+    pub fn attempt_job_execution(&mut self, job_id: u64, proof: &SDKeyProof) -> SpectreResult<String> {
+        self.run(MarketOp::AttemptJobExecution { job_id, proof: proof.clone() }, |market, _| {
+            market.attempt_job_execution(job_id, proof)
+        })
+    }
+
+    pub fn submit_result(&mut self, job_id: u64, proof: SDKeyProof) -> SpectreResult<()> {
+        self.run(MarketOp::SubmitResult { job_id, proof: proof.clone() }, |market, _| market.submit_result(job_id, proof))
+    }
+
+    pub fn verify_result(&mut self, job_id: u64) -> SpectreResult<()> {
+        self.run(MarketOp::VerifyResult { job_id }, |market, _| market.verify_result(job_id))
+    }
+
+    pub fn dispute_job(&mut self, job_id: u64) -> SpectreResult<()> {
+        self.run(MarketOp::DisputeJob { job_id }, |market, _| market.dispute_job(job_id))
+    }
+
+    pub fn settle_job(&mut self, job_id: u64) -> SpectreResult<JobSettlement> {
+        self.run(MarketOp::SettleJob { job_id }, |market, _| market.settle_job(job_id))
+    }
+
+    pub fn settle_on_confirmation(&mut self, job_id: u64, tx_id: String) -> SpectreResult<()> {
+        self.run(MarketOp::SettleOnConfirmation { job_id, tx_id: tx_id.clone() }, |market, _| {
+            market.settle_on_confirmation(job_id, tx_id)
+        })
+    }
+
+    pub fn cancel_job(&mut self, job_id: u64) -> SpectreResult<u64> {
+        self.run(MarketOp::CancelJob { job_id }, |market, _| market.cancel_job(job_id))
+    }
+
+    pub fn expire_jobs(&mut self, now: u64) -> SpectreResult<ExpirySweep> {
+        self.run(MarketOp::ExpireJobs { now }, |market, _| Ok(market.expire_jobs(now)))
+    }
+
+    /// Remember a submitted Psy transaction until it is final
+    pub fn track_tx(&mut self, tx: PsyTransaction) -> SpectreResult<()> {
+        self.run(MarketOp::TrackTx { tx: tx.clone() }, |_, pending_txs| {
+            track_tx(pending_txs, tx);
+            Ok(())
+        })
+    }
+
+    /// Apply a Psy status update to the tracked transaction and to the
+    /// market, as [`SpectreMarket::apply_tx_update`] does
+    pub fn apply_tx_update(&mut self, update: &PsyTxUpdate) -> SpectreResult<Option<(u64, SpectreResult<JobSettlement>)>> {
+        self.run(MarketOp::ApplyTxUpdate { update: update.clone() }, |market, pending_txs| {
+            Ok(apply_tx_update(market, pending_txs, update))
+        })
+    }
+
+    // Run `apply` with the clock pinned to now, then log `op` with that time
+    // unless it failed without changing anything
+    fn run<T>(
+        &mut self,
+        op: MarketOp,
+        apply: impl FnOnce(&mut SpectreMarket, &mut HashMap<String, PsyTransaction>) -> SpectreResult<T>,
+    ) -> SpectreResult<T> {
+        if let Some(e) = &self.unlogged {
+            return Err(e.clone());
+        }
+        if self.snapshot_every > 0 && self.last_seq - self.snapshot_seq >= self.snapshot_every {
+            self.compact()?;
+        }
+        let at = self.clock.inner.now();
+        let events_before = self.market.events().last_seq();
+        self.clock.pin(at);
+        let result = apply(&mut self.market, &mut self.pending_txs);
+        self.clock.unpin();
+
+        // Every market change emits an event, so a failure that emitted none changed nothing
+        let event_seq = self.market.events().last_seq();
+        if result.is_err() && event_seq == events_before {
+            return result;
+        }
+        let record = WalRecord { seq: self.last_seq + 1, at, event_seq, op };
+        if let Err(e) = self.storage.append(&record) {
+            self.unlogged = Some(e.clone());
+            return Err(e);
+        }
+        self.last_seq = record.seq;
+        result
+    }
+}
+
+// Re-run a logged operation, which fails again exactly where it failed the
+// first time, and check it emitted the same events
+fn replay(market: &mut SpectreMarket, pending_txs: &mut HashMap<String, PsyTransaction>, record: WalRecord) -> SpectreResult<()> {
+    let seq = record.seq;
+    let _ = match record.op {
+        MarketOp::Mint { account, amount } => market.mint(&account, amount),
+        MarketOp::Transfer { from, to, amount } => market.transfer(&from, &to, amount),
+        MarketOp::RegisterAgent { agent_id, algos, stake, public_key } => {
+            let key = hex::decode(public_key).ok()
+                .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+                .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok());
+            match key {
+                Some(key) => market.register_agent(agent_id, algos, stake, key),
+                None => {
+                    let reason = format!("agent {} has a malformed public key", agent_id);
+                    return Err(SpectreError::ReplayDiverged { seq, reason });
+                }
+            }
+        }
+        MarketOp::DepositStake { agent_id, amount } => market.deposit_stake(&agent_id, amount),
+        MarketOp::RequestUnstake { agent_id, amount } => market.request_unstake(&agent_id, amount).map(drop),
+        MarketOp::WithdrawUnstaked { agent_id } => market.withdraw_unstaked(&agent_id).map(drop),
+        MarketOp::PostJob { request } => market.post_job_request(request),
+        MarketOp::ClaimJob { job_id, agent_id } => market.claim_job(job_id, &agent_id),
+        MarketOp::AttemptJobExecution { job_id, proof } => market.attempt_job_execution(job_id, &proof).map(drop),
+        MarketOp::SubmitResult { job_id, proof } => market.submit_result(job_id, proof),
+        MarketOp::VerifyResult { job_id } => market.verify_result(job_id),
+        MarketOp::DisputeJob { job_id } => market.dispute_job(job_id),
+        MarketOp::SettleJob { job_id } => market.settle_job(job_id).map(drop),
+        MarketOp::SettleOnConfirmation { job_id, tx_id } => market.settle_on_confirmation(job_id, tx_id),
+        MarketOp::CancelJob { job_id } => market.cancel_job(job_id).map(drop),
+        MarketOp::ExpireJobs { now } => {
+            market.expire_jobs(now);
+            Ok(())
+        }
+        MarketOp::TrackTx { tx } => {
+            track_tx(pending_txs, tx);
+            Ok(())
+        }
+        MarketOp::ApplyTxUpdate { update } => {
+            apply_tx_update(market, pending_txs, &update);
+            Ok(())
+        }
+    };
+    let event_seq = market.events().last_seq();
+    if event_seq != record.event_seq {
+        let reason = format!("the event log ended at {} instead of {}", event_seq, record.event_seq);
+        return Err(SpectreError::ReplayDiverged { seq, reason });
+    }
+    Ok(())
+}
+
+fn track_tx(pending_txs: &mut HashMap<String, PsyTransaction>, tx: PsyTransaction) {
+    pending_txs.insert(tx.tx_id.clone(), tx);
+}
+
+fn apply_tx_update(
+    market: &mut SpectreMarket,
+    pending_txs: &mut HashMap<String, PsyTransaction>,
+    update: &PsyTxUpdate,
+) -> Option<(u64, SpectreResult<JobSettlement>)> {
+    if update.status.is_terminal() {
+        pending_txs.remove(&update.tx_id);
+    } else if let Some(tx) = pending_txs.get_mut(&update.tx_id) {
+        tx.status = update.status.clone();
+    }
+    market.apply_tx_update(update)
+}
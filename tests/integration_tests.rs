@@ -1,11 +1,14 @@
 // tests/integration_tests.rs
 // Comprehensive test suite for Spectre Protocol
 
+use futures::StreamExt;
 use spectre_protocol::*;
+use std::io::Write;
+use std::sync::Arc;
 
 #[tokio::test]
 async fn test_psy_integration() {
-    let mut client = PsyClient::new();
+    let client = PsySimulator::new();
     
     let result = client.submit_agent_transaction(
         "test_agent".to_string(),
@@ -21,14 +24,16 @@ async fn test_psy_integration() {
 #[test]
 fn test_sdkey_verification_success() {
     let mut registry = SDKeyRegistry::new();
+    let agent_key = generate_agent_keypair();
     
     registry.register_agent(
         "agent_001".to_string(),
         "LLaMA-3-70B".to_string(),
-        5000
+        5000,
+        agent_key.verifying_key()
     ).unwrap();
     
-    let proof = generate_mock_proof("agent_001".to_string(), "LLaMA-3-70B");
+    let proof = ProofBuilder::new(&agent_key, "agent_001".to_string(), "LLaMA-3-70B").unwrap().build();
     let result = registry.verify_sdkey_proof(&proof, 4000);
     assert!(result.is_ok());
 }
@@ -43,8 +48,1722 @@ fn test_market_validation_data() {
 #[test]
 fn test_spectre_market_basic() {
     let mut market = SpectreMarket::new();
-    market.register_agent("agent_001".to_string(), vec!["LLaMA-3".to_string()]);
-    market.post_job(1, "user_001".to_string(), "LLaMA-3".to_string(), 100);
-    let result = market.attempt_job_execution(1, "agent_001".to_string());
+    let agent_key = generate_agent_keypair();
+    market.ledger.mint("agent_001", 5000).unwrap();
+    market.register_agent("agent_001".to_string(), vec!["LLaMA-3-70B".to_string()], 5000, agent_key.verifying_key()).unwrap();
+    market.ledger.mint("user_001", 100).unwrap();
+    market.post_job(1, "user_001".to_string(), "LLaMA-3-70B".to_string(), 100).unwrap();
+    let proof = ProofBuilder::new(&agent_key, "agent_001".to_string(), "LLaMA-3-70B").unwrap().job(1).build();
+    let result = market.attempt_job_execution(1, &proof);
     assert!(result.is_ok());
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_errors_carry_codes_and_context() {
+    let mut registry = SDKeyRegistry::new();
+    let agent_key = generate_agent_keypair();
+    let err = registry
+        .register_agent("agent_x".to_string(), "Unknown-Model".to_string(), 5000, agent_key.verifying_key())
+        .unwrap_err();
+    assert_eq!(err.code(), "UNKNOWN_MODEL");
+
+    registry.register_agent("agent_001".to_string(), "LLaMA-3-70B".to_string(), 5000, agent_key.verifying_key()).unwrap();
+    let proof = ProofBuilder::new(&agent_key, "agent_001".to_string(), "LLaMA-3-70B").unwrap()
+        .model_hash("0xdeadbeef")
+        .build();
+    match registry.verify_sdkey_proof(&proof, 100) {
+        Err(SpectreError::HashMismatch { agent_id, actual, .. }) => {
+            assert_eq!(agent_id, "agent_001");
+            assert_eq!(actual, "0xdeadbeef");
+        }
+        other => panic!("expected hash mismatch, got {:?}", other),
+    }
+
+    let mut market = SpectreMarket::new();
+    market.ledger.mint("agent_001", 5000).unwrap();
+    market.register_agent("agent_001".to_string(), vec!["LLaMA-3-70B".to_string()], 5000, agent_key.verifying_key()).unwrap();
+    market.ledger.mint("user_001", 100).unwrap();
+    market.post_job(1, "user_001".to_string(), "LLaMA-3-70B".to_string(), 100).unwrap();
+    let proof = ProofBuilder::new(&agent_key, "agent_001".to_string(), "LLaMA-3-70B").unwrap().job(1).build();
+    market.attempt_job_execution(1, &proof).unwrap();
+    let err = market.attempt_job_execution(1, &proof).unwrap_err();
+    assert_eq!(err, SpectreError::JobAlreadyTaken { job_id: 1 });
+    assert_eq!(err.code(), "JOB_ALREADY_TAKEN");
+}
+
+#[test]
+fn test_job_escrow_settlement_and_refunds() {
+    let mut market = SpectreMarket::new();
+    let agent_key = generate_agent_keypair();
+    market.ledger.mint("agent_001", 5000).unwrap();
+    market.register_agent("agent_001".to_string(), vec!["LLaMA-3-70B".to_string()], 5000, agent_key.verifying_key()).unwrap();
+    market.ledger.mint("user_001", 1_000).unwrap();
+
+    // Can't post a job the requester can't pay for
+    let err = market.post_job(1, "user_001".to_string(), "LLaMA-3-70B".to_string(), 5_000).unwrap_err();
+    assert_eq!(err.code(), "INSUFFICIENT_FUNDS");
+
+    market.post_job(1, "user_001".to_string(), "LLaMA-3-70B".to_string(), 500).unwrap();
+    market.post_job(2, "user_001".to_string(), "LLaMA-3-70B".to_string(), 200).unwrap();
+    let expiring = JobRequest::new(3, "user_001".to_string(), "LLaMA-3-70B".to_string(), 100).expires_at(10);
+    market.post_job_request(expiring).unwrap();
+    assert_eq!(market.ledger.balance("user_001"), 200);
+
+    // Claiming alone doesn't pay out
+    let claim = ProofBuilder::new(&agent_key, "agent_001".to_string(), "LLaMA-3-70B").unwrap().job(1).build();
+    market.attempt_job_execution(1, &claim).unwrap();
+    assert_eq!(market.ledger.balance("agent_001"), 0);
+
+    let result = ProofBuilder::new(&agent_key, "agent_001".to_string(), "LLaMA-3-70B").unwrap()
+        .job(1)
+        .result(b"summary: ok")
+        .build();
+    market.submit_result(1, result).unwrap();
+    market.verify_result(1).unwrap();
+    let settlement = market.settle_job(1).unwrap();
+    assert_eq!(settlement, JobSettlement::Paid { agent_id: "agent_001".to_string(), payout: 495, fee: 5 });
+    assert_eq!(market.ledger.balance("agent_001"), 495);
+    assert_eq!(market.ledger.balance(TREASURY_ACCOUNT), 5);
+
+    assert_eq!(market.cancel_job(2).unwrap(), 200);
+    assert_eq!(market.expire_jobs(10).expired, vec![3]);
+    assert_eq!(market.ledger.balance("user_001"), 500);
+
+    assert_eq!(market.ledger.total_supply(), 6_000);
+    market.ledger.check_invariants().unwrap();
+}
+
+#[test]
+fn test_job_lifecycle_transitions() {
+    let mut market = SpectreMarket::new();
+    let key_1 = generate_agent_keypair();
+    let key_2 = generate_agent_keypair();
+    market.ledger.mint("agent_001", 5000).unwrap();
+    market.register_agent("agent_001".to_string(), vec!["GPT-4-Turbo".to_string()], 5000, key_1.verifying_key()).unwrap();
+    market.ledger.mint("agent_002", 5000).unwrap();
+    market.register_agent("agent_002".to_string(), vec!["GPT-4-Turbo".to_string()], 5000, key_2.verifying_key()).unwrap();
+    market.ledger.mint("user_001", 300).unwrap();
+    market.post_job(1, "user_001".to_string(), "GPT-4-Turbo".to_string(), 300).unwrap();
+
+    // Can't skip straight to settlement or verification
+    assert_eq!(market.settle_job(1).unwrap_err().code(), "INVALID_JOB_TRANSITION");
+    assert_eq!(market.verify_result(1).unwrap_err().code(), "INVALID_JOB_TRANSITION");
+
+    market.claim_job(1, "agent_001").unwrap();
+    assert_eq!(market.claim_job(1, "agent_002").unwrap_err(), SpectreError::JobAlreadyTaken { job_id: 1 });
+    assert_eq!(market.cancel_job(1).unwrap_err().code(), "JOB_ALREADY_TAKEN");
+
+    // Only the assigned agent may submit
+    let foreign = ProofBuilder::new(&key_2, "agent_002".to_string(), "GPT-4-Turbo").unwrap().job(1).build();
+    assert_eq!(market.submit_result(1, foreign).unwrap_err().code(), "NOT_JOB_ASSIGNEE");
+
+    // A substituted model gets the job rejected and the requester refunded
+    let proof = ProofBuilder::new(&key_1, "agent_001".to_string(), "GPT-4-Turbo").unwrap()
+        .job(1)
+        .model_hash("0xbad")
+        .build();
+    market.submit_result(1, proof).unwrap();
+    assert_eq!(market.verify_result(1).unwrap_err().code(), "HASH_MISMATCH");
+    assert_eq!(market.jobs[&1].status, JobStatus::Rejected);
+
+    let settlement = market.settle_job(1).unwrap();
+    assert_eq!(settlement, JobSettlement::Refunded { requester: "user_001".to_string(), amount: 300 });
+    // ...and compensated from the agent's slashed stake
+    assert_eq!(market.ledger.balance("user_001"), 300 + 1_250);
+
+    let job = &market.jobs[&1];
+    assert_eq!(job.status, JobStatus::Settled);
+    assert!(job.entered_at(JobStatus::Claimed).is_some());
+    assert!(job.entered_at(JobStatus::Verified).is_none());
+    assert_eq!(job.transitions.len(), 4);
+}
+
+#[test]
+fn test_market_matches_jobs_against_sdkey_registry() {
+    let mut market = SpectreMarket::new();
+    let agent_key = generate_agent_keypair();
+    let models = vec!["LLaMA-3-70B".to_string(), "Claude-3-Opus".to_string()];
+    market.ledger.mint("agent_multi", 5000).unwrap();
+    market.register_agent("agent_multi".to_string(), models, 5000, agent_key.verifying_key()).unwrap();
+    let poor_key = generate_agent_keypair();
+    market.ledger.mint("agent_poor", 10).unwrap();
+    market.register_agent("agent_poor".to_string(), vec!["Claude-3-Opus".to_string()], 10, poor_key.verifying_key()).unwrap();
+    market.ledger.mint("user_001", 1_000).unwrap();
+
+    // An agent id is registered once; a second registration changes nothing
+    market.ledger.mint("agent_multi", 1_000).unwrap();
+    let err = market.register_agent("agent_multi".to_string(), vec!["GPT-4-Turbo".to_string()], 1_000, poor_key.verifying_key()).unwrap_err();
+    assert_eq!(err.code(), "AGENT_ALREADY_REGISTERED");
+    assert!(!market.registry.verified_agents["agent_multi"].supports("GPT-4-Turbo"));
+    assert_eq!(market.registry.stakes.bonded("agent_multi"), 5000);
+    assert_eq!(market.ledger.balance("agent_multi"), 1_000);
+
+    let big_job = JobRequest::new(1, "user_001".to_string(), "LLaMA-3-70B".to_string(), 100).with_complexity(50_000);
+    market.post_job_request(big_job).unwrap();
+    let claude_job = JobRequest::new(2, "user_001".to_string(), "Claude-3-Opus".to_string(), 100).with_complexity(50_000);
+    market.post_job_request(claude_job).unwrap();
+    market.post_job(3, "user_001".to_string(), "GPT-4-Turbo".to_string(), 100).unwrap();
+
+    // One identity, several models, each with its own capacity
+    let llama = ProofBuilder::new(&agent_key, "agent_multi".to_string(), "LLaMA-3-70B").unwrap().job(1).build();
+    assert_eq!(market.attempt_job_execution(1, &llama).unwrap_err().code(), "CAPACITY_EXCEEDED");
+    let claude = ProofBuilder::new(&agent_key, "agent_multi".to_string(), "Claude-3-Opus").unwrap().job(2).build();
+    market.attempt_job_execution(2, &claude).unwrap();
+    assert_eq!(market.claim_job(3, "agent_multi").unwrap_err().code(), "CAPABILITY_MISMATCH");
+
+    // Same stake rules as direct registry verification
+    assert_eq!(market.claim_job(2, "agent_poor").unwrap_err().code(), "INSUFFICIENT_STAKE");
+
+    // Reputation uses the registry's single 0.0-1.0 scale
+    market.registry.record_outcome("agent_multi", "LLaMA-3-70B", ReputationOutcome::Failure, None);
+    market.registry.record_outcome("agent_multi", "LLaMA-3-70B", ReputationOutcome::Failure, None);
+    market.post_job(4, "user_001".to_string(), "LLaMA-3-70B".to_string(), 100).unwrap();
+    let llama = ProofBuilder::new(&agent_key, "agent_multi".to_string(), "LLaMA-3-70B").unwrap().job(4).build();
+    assert_eq!(market.attempt_job_execution(4, &llama).unwrap_err().code(), "LOW_REPUTATION");
+}
+
+#[test]
+fn test_sdkey_proof_signatures() {
+    let mut registry = SDKeyRegistry::new();
+    let agent_key = generate_agent_keypair();
+    let attacker_key = generate_agent_keypair();
+    registry.register_agent("agent_001".to_string(), "Claude-3-Opus".to_string(), 5000, agent_key.verifying_key()).unwrap();
+
+    let proof = ProofBuilder::new(&agent_key, "agent_001".to_string(), "Claude-3-Opus").unwrap()
+        .job(42)
+        .result(b"answer")
+        .build();
+    assert_eq!(proof.result_digest, result_digest(b"answer"));
+    assert!(registry.verify_sdkey_proof(&proof, 1000).is_ok());
+
+    // A well-formed "zk_" proof no longer passes without the agent's key
+    let forged = ProofBuilder::new(&attacker_key, "agent_001".to_string(), "Claude-3-Opus").unwrap().job(42).build();
+    assert!(forged.execution_proof.starts_with("zk_"));
+    assert_eq!(registry.verify_sdkey_proof(&forged, 1000).unwrap_err().code(), "INVALID_PROOF");
+
+    // Every signed field is covered: tampering with any of them breaks the signature
+    let mut tampered = proof.clone();
+    tampered.job_id = 43;
+    assert_eq!(registry.verify_sdkey_proof(&tampered, 1000).unwrap_err().code(), "INVALID_PROOF");
+    let mut tampered = proof.clone();
+    tampered.result_digest = result_digest(b"different answer");
+    assert_eq!(registry.verify_sdkey_proof(&tampered, 1000).unwrap_err().code(), "INVALID_PROOF");
+    let mut tampered = proof.clone();
+    tampered.nonce = tampered.nonce.wrapping_add(1);
+    assert_eq!(registry.verify_sdkey_proof(&tampered, 1000).unwrap_err().code(), "INVALID_PROOF");
+    let mut tampered = proof.clone();
+    tampered.execution_proof = format!("zk_{}", "0".repeat(64));
+    assert_ne!(tampered.signing_message(), proof.signing_message());
+    assert_eq!(registry.verify_sdkey_proof(&tampered, 1000).unwrap_err().code(), "INVALID_PROOF");
+
+    // Unknown models are refused instead of signed with a placeholder hash
+    let err = ProofBuilder::new(&agent_key, "agent_001".to_string(), "No-Such-Model").err().unwrap();
+    assert_eq!(err.code(), "UNKNOWN_MODEL");
+}
+
+#[test]
+fn test_proof_freshness_and_replay_protection() {
+    let clock = ManualClock::new(1_700_000_000);
+    let mut registry = SDKeyRegistry::with_clock(Arc::new(clock.clone()));
+    let agent_key = generate_agent_keypair();
+    registry.register_agent("agent_001".to_string(), "LLaMA-3-70B".to_string(), 5000, agent_key.verifying_key()).unwrap();
+    let builder = || ProofBuilder::new(&agent_key, "agent_001".to_string(), "LLaMA-3-70B").unwrap();
+
+    let proof = builder().timestamp(clock.now()).nonce(7).build();
+    registry.verify_sdkey_proof(&proof, 100).unwrap();
+    assert_eq!(registry.verify_sdkey_proof(&proof, 100).unwrap_err().code(), "REPLAYED_PROOF");
+
+    // A fresh nonce from the same agent is accepted
+    registry.verify_sdkey_proof(&builder().timestamp(clock.now()).nonce(8).build(), 100).unwrap();
+
+    // Future-dated beyond allowed skew, within skew, and stale
+    let future = builder().timestamp(clock.now() + DEFAULT_MAX_CLOCK_SKEW_SECS + 1).build();
+    assert_eq!(registry.verify_sdkey_proof(&future, 100).unwrap_err().code(), "FUTURE_PROOF");
+    let skewed = builder().timestamp(clock.now() + DEFAULT_MAX_CLOCK_SKEW_SECS).build();
+    registry.verify_sdkey_proof(&skewed, 100).unwrap();
+
+    clock.advance(DEFAULT_PROOF_MAX_AGE_SECS + 1);
+    match registry.verify_sdkey_proof(&proof, 100) {
+        Err(SpectreError::StaleProof { timestamp, max_age_secs, .. }) => {
+            assert_eq!(timestamp, 1_700_000_000);
+            assert_eq!(max_age_secs, DEFAULT_PROOF_MAX_AGE_SECS);
+        }
+        other => panic!("expected stale proof, got {:?}", other),
+    }
+
+    // Nonces outside the window are pruned instead of accumulating forever
+    registry.verify_sdkey_proof(&builder().timestamp(clock.now()).nonce(7).build(), 100).unwrap();
+    assert_eq!(registry.tracked_nonces("agent_001"), 2);
+
+    // A result is judged fresh when it is submitted, not when the requester gets round to verifying it
+    let mut market = SpectreMarket::with_registry(SDKeyRegistry::with_clock(Arc::new(clock.clone())));
+    market.mint("agent_001", 5_000).unwrap();
+    market.mint("user_001", 100).unwrap();
+    market.register_agent("agent_001".to_string(), vec!["LLaMA-3-70B".to_string()], 5_000, agent_key.verifying_key()).unwrap();
+    market.post_job(1, "user_001".to_string(), "LLaMA-3-70B".to_string(), 100).unwrap();
+    market.attempt_job_execution(1, &builder().job(1).timestamp(clock.now()).nonce(1).build()).unwrap();
+    let result = builder().job(1).result(b"done").timestamp(clock.now()).nonce(2).build();
+    clock.advance(DEFAULT_PROOF_MAX_AGE_SECS + 1);
+    assert_eq!(market.submit_result(1, result).unwrap_err().code(), "STALE_PROOF");
+    assert_eq!(market.jobs[&1].status, JobStatus::Claimed);
+    let result = builder().job(1).result(b"done").timestamp(clock.now()).nonce(3).build();
+    market.submit_result(1, result.clone()).unwrap();
+    assert_eq!(market.submit_result(1, result).unwrap_err().code(), "INVALID_JOB_TRANSITION");
+    clock.advance(DEFAULT_PROOF_MAX_AGE_SECS * 10);
+    market.verify_result(1).unwrap();
+    assert_eq!(market.registry.stakes.bonded("agent_001"), 5_000);
+}
+
+#[test]
+fn test_pluggable_proof_verifiers() {
+    let agent_key = generate_agent_keypair();
+    let other_key = generate_agent_keypair();
+    let mut registry = SDKeyRegistry::builder()
+        .verifier(MockPrefixVerifier)
+        .verifier_for_model("Claude-3-Opus", HashCommitmentVerifier)
+        .verifier_for_model("GPT-4-Turbo", AllOf(vec![Box::new(SignatureVerifier), Box::new(HashCommitmentVerifier)]))
+        .build();
+    let models = vec!["LLaMA-3-70B".to_string(), "Claude-3-Opus".to_string(), "GPT-4-Turbo".to_string()];
+    registry.register_agent_with_models("agent_001".to_string(), models, 5000, agent_key.verifying_key()).unwrap();
+    assert_eq!(registry.verifier_for("LLaMA-3-70B").name(), "mock-prefix");
+    assert_eq!(registry.verifier_for("Claude-3-Opus").name(), "hash-commitment");
+
+    // Whatever the backend, the agent's signature is checked first, so a
+    // foreign key fails before it can use up any of the agent's nonces
+    let foreign = ProofBuilder::new(&other_key, "agent_001".to_string(), "LLaMA-3-70B").unwrap().nonce(1).build();
+    assert_eq!(registry.verify_sdkey_proof(&foreign, 100).unwrap_err().code(), "INVALID_PROOF");
+    assert_eq!(registry.tracked_nonces("agent_001"), 0);
+    let mock_ok = ProofBuilder::new(&agent_key, "agent_001".to_string(), "LLaMA-3-70B").unwrap().nonce(1).build();
+    registry.verify_sdkey_proof(&mock_ok, 100).unwrap();
+
+    // Hash commitments must match the committed result
+    let commitment = ProofBuilder::new(&agent_key, "agent_001".to_string(), "Claude-3-Opus").unwrap().result(b"a").build();
+    registry.verify_sdkey_proof(&commitment, 100).unwrap();
+    let mut tampered = ProofBuilder::new(&agent_key, "agent_001".to_string(), "Claude-3-Opus").unwrap().result(b"a").build();
+    tampered.execution_proof = execution_commitment(&SDKeyProof { result_digest: result_digest(b"b"), ..tampered.clone() });
+    assert_eq!(registry.verify_sdkey_proof(&tampered, 100).unwrap_err().code(), "INVALID_PROOF");
+
+    // Layered verification needs both the commitment and the agent's signature
+    let forged = ProofBuilder::new(&other_key, "agent_001".to_string(), "GPT-4-Turbo").unwrap().build();
+    assert_eq!(registry.verify_sdkey_proof(&forged, 100).unwrap_err().code(), "INVALID_PROOF");
+    let genuine = ProofBuilder::new(&agent_key, "agent_001".to_string(), "GPT-4-Turbo").unwrap().build();
+    registry.verify_sdkey_proof(&genuine, 100).unwrap();
+}
+
+#[test]
+fn test_model_catalog_governance() {
+    let clock = ManualClock::new(1_000);
+    let mut registry = SDKeyRegistry::builder().clock(Arc::new(clock.clone())).build();
+    let agent_key = generate_agent_keypair();
+
+    // Models beyond the original three are first-class
+    registry.register_agent("mistral_agent".to_string(), "Mistral-Large".to_string(), 5000, agent_key.verifying_key()).unwrap();
+    assert_eq!(registry.verified_agents["mistral_agent"].models[0].max_tokens, 32768);
+    let proof = registry.proof_builder(&agent_key, "mistral_agent".to_string(), "Mistral-Large").unwrap().build();
+    registry.verify_sdkey_proof(&proof, 1000).unwrap();
+
+    // Teams can add their own models at runtime, with capacity driven by the catalog
+    registry.catalog.add_model("Qwen-2-72B", 32000, "1", "0x7177656e01", 0).unwrap();
+    registry.register_agent("qwen_agent".to_string(), "Qwen-2-72B".to_string(), 5000, agent_key.verifying_key()).unwrap();
+    let v1 = registry.proof_builder(&agent_key, "qwen_agent".to_string(), "Qwen-2-72B").unwrap().build();
+    registry.verify_sdkey_proof(&v1, 1000).unwrap();
+
+    // Capacity changes apply to agents already registered on the model
+    registry.check_eligibility("qwen_agent", "Qwen-2-72B", 20_000).unwrap();
+    registry.catalog.set_max_tokens("Qwen-2-72B", 16_000).unwrap();
+    let err = registry.check_eligibility("qwen_agent", "Qwen-2-72B", 20_000).unwrap_err();
+    assert_eq!(err.code(), "CAPACITY_EXCEEDED");
+    assert!(err.to_string().contains("16000"));
+
+    // A new build is accepted alongside the old one once effective; then the old one retires
+    registry.catalog.add_version("Qwen-2-72B", "2", "0x7177656e02", 2_000).unwrap();
+    registry.catalog.retire_version("Qwen-2-72B", "1", 3_000).unwrap();
+    clock.set(2_000);
+    let v2 = registry.proof_builder(&agent_key, "qwen_agent".to_string(), "Qwen-2-72B").unwrap().build();
+    assert_eq!(v2.model_hash, "0x7177656e02");
+    registry.verify_sdkey_proof(&v2, 1000).unwrap();
+    let old_build = registry.proof_builder(&agent_key, "qwen_agent".to_string(), "Qwen-2-72B").unwrap()
+        .model_hash("0x7177656e01")
+        .build();
+    registry.verify_sdkey_proof(&old_build, 1000).unwrap();
+    clock.set(3_000);
+    let old_build = registry.proof_builder(&agent_key, "qwen_agent".to_string(), "Qwen-2-72B").unwrap()
+        .model_hash("0x7177656e01")
+        .build();
+    assert_eq!(registry.verify_sdkey_proof(&old_build, 1000).unwrap_err().code(), "HASH_MISMATCH");
+
+    // Deprecated models are closed to new agents
+    registry.catalog.deprecate_model("Qwen-2-72B", 3_000).unwrap();
+    let err = registry.register_agent("late_agent".to_string(), "Qwen-2-72B".to_string(), 5000, agent_key.verifying_key()).unwrap_err();
+    assert_eq!(err.code(), "MODEL_DEPRECATED");
+}
+
+#[test]
+fn test_model_catalog_from_file() {
+    let path = std::env::temp_dir().join(format!("spectre_catalog_{}.toml", std::process::id()));
+    std::fs::write(&path, r#"
+        [[models]]
+        name = "Phi-3-Mini"
+        max_tokens = 4096
+
+        [[models.versions]]
+        version = "1"
+        hash = "0x9813"
+    "#).unwrap();
+    let catalog = ModelCatalog::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let mut market = SpectreMarket::with_registry(SDKeyRegistry::builder().catalog(catalog).build());
+    let agent_key = generate_agent_keypair();
+    market.ledger.mint("phi_agent", 5000).unwrap();
+    market.register_agent("phi_agent".to_string(), vec!["Phi-3-Mini".to_string()], 5000, agent_key.verifying_key()).unwrap();
+    market.ledger.mint("user_001", 100).unwrap();
+    assert_eq!(market.post_job(1, "user_001".to_string(), "LLaMA-3-70B".to_string(), 100).unwrap_err().code(), "UNKNOWN_MODEL");
+    market.post_job(1, "user_001".to_string(), "Phi-3-Mini".to_string(), 100).unwrap();
+    let proof = market.registry.proof_builder(&agent_key, "phi_agent".to_string(), "Phi-3-Mini").unwrap().job(1).build();
+    market.attempt_job_execution(1, &proof).unwrap();
+}
+
+#[test]
+fn test_stake_locking_unbonding_and_slashing() {
+    let clock = ManualClock::new(1_000);
+    let registry = SDKeyRegistry::builder().clock(Arc::new(clock.clone())).unbonding_period(100).build();
+    let mut market = SpectreMarket::with_registry(registry);
+    let agent_key = generate_agent_keypair();
+
+    // Stake is bonded from the agent's own balance
+    let err = market.register_agent("agent_001".to_string(), vec!["LLaMA-3-70B".to_string()], 2_000, agent_key.verifying_key()).unwrap_err();
+    assert_eq!(err.code(), "INSUFFICIENT_FUNDS");
+    assert!(!market.registry.verified_agents.contains_key("agent_001"));
+    market.ledger.mint("agent_001", 3_000).unwrap();
+    market.register_agent("agent_001".to_string(), vec!["LLaMA-3-70B".to_string()], 2_000, agent_key.verifying_key()).unwrap();
+    market.deposit_stake("agent_001", 1_000).unwrap();
+    assert_eq!(market.registry.stakes.bonded("agent_001"), 3_000);
+    assert_eq!(market.ledger.balance(STAKE_VAULT_ACCOUNT), 3_000);
+
+    // Claiming locks stake in proportion to the reward
+    market.ledger.mint("user_001", 10_000).unwrap();
+    market.post_job(1, "user_001".to_string(), "LLaMA-3-70B".to_string(), 4_000).unwrap();
+    market.post_job(2, "user_001".to_string(), "LLaMA-3-70B".to_string(), 4_000).unwrap();
+    market.claim_job(1, "agent_001").unwrap();
+    assert_eq!(market.registry.stakes.free("agent_001"), 1_000);
+    assert_eq!(market.claim_job(2, "agent_001").unwrap_err().code(), "INSUFFICIENT_STAKE");
+
+    // Locked stake can't be withdrawn; free stake unbonds after the delay
+    assert_eq!(market.request_unstake("agent_001", 2_000).unwrap_err().code(), "INSUFFICIENT_STAKE");
+    assert_eq!(market.request_unstake("agent_001", 500).unwrap(), 1_100);
+    assert_eq!(market.withdraw_unstaked("agent_001").unwrap(), 0);
+    clock.advance(100);
+    assert_eq!(market.withdraw_unstaked("agent_001").unwrap(), 500);
+    assert_eq!(market.ledger.balance("agent_001"), 500);
+
+    // A result the agent didn't sign is refused outright, so nobody can get it slashed
+    let forged = ProofBuilder::new(&generate_agent_keypair(), "agent_001".to_string(), "LLaMA-3-70B").unwrap().job(1).build();
+    assert_eq!(market.submit_result(1, forged).unwrap_err().code(), "INVALID_PROOF");
+    let other_job = market.registry.proof_builder(&agent_key, "agent_001".to_string(), "LLaMA-3-70B").unwrap().job(2).build();
+    assert_eq!(market.submit_result(1, other_job).unwrap_err().code(), "INVALID_PROOF");
+    assert_eq!(market.jobs[&1].status, JobStatus::Claimed);
+    assert_eq!(market.registry.stakes.bonded("agent_001"), 2_500);
+
+    // A signed proof for an unaccepted model build slashes 50% of bonded stake,
+    // half to the requester, half burned
+    let wrong_hash = market.registry.proof_builder(&agent_key, "agent_001".to_string(), "LLaMA-3-70B").unwrap()
+        .job(1)
+        .model_hash("0xdeadbeef")
+        .build();
+    market.submit_result(1, wrong_hash).unwrap();
+    assert_eq!(market.verify_result(1).unwrap_err().code(), "HASH_MISMATCH");
+    let events = market.registry.stakes.slash_events("agent_001");
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].offense, SlashOffense::HashMismatch);
+    assert_eq!((events[0].slashed, events[0].to_requester, events[0].burned), (1_250, 625, 625));
+    assert_eq!(market.registry.stakes.bonded("agent_001"), 1_250);
+    assert_eq!(market.ledger.balance(BURN_ACCOUNT), 625);
+    market.settle_job(1).unwrap();
+    assert_eq!(market.ledger.balance("user_001"), 10_000 - 4_000 + 625);
+
+    // Letting a claimed job expire costs stake too, all of it paid to the requester
+    let expiring = JobRequest::new(3, "user_001".to_string(), "LLaMA-3-70B".to_string(), 1_000).expires_at(2_000);
+    market.post_job_request(expiring).unwrap();
+    market.claim_job(3, "agent_001").unwrap();
+    assert_eq!(market.expire_jobs(2_000), ExpirySweep { expired: vec![3], failed: vec![] });
+    let missed = market.registry.stakes.slash_events("agent_001")[1].clone();
+    assert_eq!(missed.offense, SlashOffense::MissedDeadline);
+    assert_eq!((missed.slashed, missed.to_requester, missed.burned), (62, 62, 0));
+    assert_eq!(market.registry.stakes.free("agent_001"), 1_188);
+
+    // A slash the vault can't pay is reported, but the job still expires and frees its stake
+    market.registry.register_agent("agent_unbacked".to_string(), "LLaMA-3-70B".to_string(), 50_000, agent_key.verifying_key()).unwrap();
+    let expiring = JobRequest::new(4, "user_001".to_string(), "LLaMA-3-70B".to_string(), 1_000).expires_at(3_000);
+    market.post_job_request(expiring).unwrap();
+    market.claim_job(4, "agent_unbacked").unwrap();
+    let sweep = market.expire_jobs(3_000);
+    assert_eq!(sweep.expired, vec![4]);
+    assert_eq!(sweep.failed.len(), 1);
+    assert_eq!((sweep.failed[0].0, sweep.failed[0].1.code()), (4, "INSUFFICIENT_FUNDS"));
+    assert_eq!(market.jobs[&4].status, JobStatus::Expired);
+    assert_eq!(market.registry.stakes.free("agent_unbacked"), 50_000);
+    assert!(market.ledger.escrow(4).is_none());
+
+    // Registering again is refused before any more stake is bonded
+    let err = market.register_agent("agent_001".to_string(), vec!["LLaMA-3-70B".to_string()], 500, agent_key.verifying_key()).unwrap_err();
+    assert_eq!(err.code(), "AGENT_ALREADY_REGISTERED");
+    assert_eq!(market.ledger.balance("agent_001"), 500);
+
+    assert_eq!(market.ledger.balance(STAKE_VAULT_ACCOUNT), market.registry.stakes.bonded("agent_001"));
+    market.ledger.check_invariants().unwrap();
+}
+
+#[test]
+fn test_reputation_engine() {
+    let clock = ManualClock::new(1_000);
+    let mut market = SpectreMarket::with_clock(Arc::new(clock.clone()));
+    let agent_key = generate_agent_keypair();
+    market.ledger.mint("agent_001", 5000).unwrap();
+    let models = vec!["LLaMA-3-70B".to_string(), "GPT-4-Turbo".to_string()];
+    market.register_agent("agent_001".to_string(), models, 5000, agent_key.verifying_key()).unwrap();
+    market.ledger.mint("user_001", 1_000).unwrap();
+
+    // New agents start from the prior, not a perfect score
+    assert_eq!(market.registry.reputation_of("agent_001"), DEFAULT_PRIOR_MEAN);
+
+    market.post_job(1, "user_001".to_string(), "LLaMA-3-70B".to_string(), 100).unwrap();
+    market.claim_job(1, "agent_001").unwrap();
+    clock.advance(5);
+    let proof = market.registry.proof_builder(&agent_key, "agent_001".to_string(), "LLaMA-3-70B").unwrap().job(1).build();
+    market.submit_result(1, proof).unwrap();
+    market.verify_result(1).unwrap();
+    let history = market.registry.reputation.history("agent_001");
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].outcome, ReputationOutcome::Success);
+    assert_eq!(history[0].latency_ms, Some(5_000));
+    let after_success = market.registry.reputation_of("agent_001");
+    assert!(after_success > DEFAULT_PRIOR_MEAN);
+
+    // Disputes only apply to delivered results, and hurt more than failures
+    market.post_job(2, "user_001".to_string(), "GPT-4-Turbo".to_string(), 100).unwrap();
+    assert_eq!(market.dispute_job(2).unwrap_err().code(), "JOB_NOT_DISPUTABLE");
+    market.dispute_job(1).unwrap();
+    assert_eq!(market.dispute_job(1).unwrap_err().code(), "JOB_ALREADY_DISPUTED");
+    let explained = market.registry.explain_reputation("agent_001", Some("LLaMA-3-70B"));
+    assert_eq!((explained.events, explained.disputes), (2, 2.0));
+    assert!(explained.score < DEFAULT_PRIOR_MEAN);
+    assert!(explained.to_string().contains("decaying-beta"));
+
+    // Per-model: GPT-4 history is untouched
+    assert_eq!(market.registry.reputation.model_score("agent_001", "GPT-4-Turbo", clock.now()), DEFAULT_PRIOR_MEAN);
+
+    // Old outcomes fade back towards the prior
+    clock.advance(DEFAULT_HALF_LIFE_SECS * 10);
+    assert!(market.registry.explain_reputation("agent_001", Some("LLaMA-3-70B")).score > explained.score);
+
+    // A stricter policy can be plugged in
+    let strict = DecayingBetaPolicy { prior_mean: 0.5, ..DecayingBetaPolicy::default() };
+    let registry = SDKeyRegistry::builder().scoring_policy(strict).build();
+    let mut market = SpectreMarket::with_registry(registry);
+    market.ledger.mint("agent_002", 5000).unwrap();
+    market.register_agent("agent_002".to_string(), vec!["LLaMA-3-70B".to_string()], 5000, agent_key.verifying_key()).unwrap();
+    market.ledger.mint("user_001", 100).unwrap();
+    market.post_job(1, "user_001".to_string(), "LLaMA-3-70B".to_string(), 100).unwrap();
+    assert_eq!(market.claim_job(1, "agent_002").unwrap_err().code(), "LOW_REPUTATION");
+}
+
+// Services hold the chain behind the trait, so any backend can be plugged in
+async fn pay_agent(chain: &dyn PsyChain, agent_id: &str) -> SpectreResult<PsyReceipt> {
+    let tx_id = chain.submit_agent_transaction(agent_id.to_string(), "inference".to_string(), 100).await?;
+    for _ in 0..500 {
+        if chain.transaction_status(&tx_id).await?.is_some_and(|s| s.is_terminal()) {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+    Ok(chain.transaction_receipt(&tx_id).await?.expect("final transactions have receipts"))
+}
+
+fn fast_simulator() -> Arc<PsySimulator> {
+    let config = PsySimulatorConfig { block_interval_ms: 5, confirmation_depth: 2, ..PsySimulatorConfig::default() };
+    Arc::new(PsySimulator::with_config(config, Arc::new(SystemClock)).unwrap())
+}
+
+#[tokio::test]
+async fn test_psy_chain_backends() {
+    let producer = fast_simulator();
+    let blocks = producer.spawn_block_production();
+    let simulator: Arc<dyn PsyChain> = producer;
+    let mut updates = simulator.subscribe_all();
+    let receipt = pay_agent(simulator.as_ref(), "agent_001").await.unwrap();
+    assert!(receipt.status.is_terminal());
+    assert!(receipt.confirmations >= 2);
+    blocks.abort();
+
+    let submitted = updates.next().await.unwrap();
+    assert_eq!((submitted.tx_id.as_str(), &submitted.status), (receipt.tx_id.as_str(), &PsyTxStatus::Pending));
+    assert_eq!(updates.next().await.unwrap().status, PsyTxStatus::Included);
+    assert_eq!(updates.next().await.unwrap().status, receipt.status);
+    assert!(simulator.transaction_status("psy_unknown").await.unwrap().is_none());
+
+    // The JSON-RPC client surfaces transport failures as errors, not panics
+    let client = PsyClient::with_endpoint("http://127.0.0.1:9")
+        .with_retry_policy(RetryPolicy::no_retries(std::time::Duration::from_secs(1)));
+    let client: Arc<dyn PsyChain> = Arc::new(client);
+    assert_eq!(pay_agent(client.as_ref(), "agent_001").await.unwrap_err().code(), "NETWORK_FAILURE");
+}
+
+#[tokio::test]
+async fn test_psy_client_json_rpc_against_mock_server() {
+    let simulator = fast_simulator();
+    let _blocks = simulator.spawn_block_production();
+    let server = MockPsyRpcServer::start(simulator.clone()).unwrap();
+    let client = PsyClient::with_endpoint(&server.url());
+
+    // Same service code as against the simulator directly
+    let receipt = pay_agent(&client, "agent_001").await.unwrap();
+    assert_eq!(simulator.transactions().len(), 1);
+    assert_eq!(client.local_transaction(&receipt.tx_id).unwrap().status, receipt.status);
+    assert_eq!(client.network_stats().await.unwrap().parth_depth, 12);
+    assert!(client.transaction_status("psy_unknown").await.unwrap().is_none());
+
+    // A taken address is reported, not a panic
+    let taken = MockPsyRpcServer::start_on(simulator.clone(), server.addr()).err().unwrap();
+    assert_eq!(taken.code(), "NETWORK_FAILURE");
+
+    // Wire format is plain JSON-RPC 2.0
+    let http = reqwest::Client::new();
+    let response: JsonRpcResponse = http.post(server.url())
+        .json(&JsonRpcRequest::new(7, "psy_noSuchMethod", serde_json::json!([])))
+        .send().await.unwrap()
+        .json().await.unwrap();
+    assert_eq!(response.id, Some(7));
+    assert_eq!(response.error.unwrap().code, METHOD_NOT_FOUND);
+
+    let response: JsonRpcResponse = http.post(server.url()).body("{not json").send().await.unwrap().json().await.unwrap();
+    assert_eq!(response.error.unwrap().code, PARSE_ERROR);
+
+    let bad_params = JsonRpcRequest::new(8, METHOD_GET_TRANSACTION_STATUS, serde_json::json!([]));
+    let response = dispatch(simulator.as_ref(), bad_params).await;
+    assert_eq!(response.error.unwrap().into_spectre_error(METHOD_GET_TRANSACTION_STATUS).code(), "RPC_ERROR");
+
+    drop(server);
+}
+
+#[tokio::test]
+async fn test_psy_simulator_blocks_finality_and_determinism() {
+    let clock = ManualClock::new(1_000);
+    let config = PsySimulatorConfig { seed: 42, max_txs_per_block: 2, confirmation_depth: 3, failure_rate: 0.3, ..PsySimulatorConfig::default() };
+
+    // Two simulators with the same seed and inputs build the same chain
+    let run = |config: PsySimulatorConfig| {
+        let clock = clock.clone();
+        async move {
+            let sim = PsySimulator::with_config(config, Arc::new(clock)).unwrap();
+            for i in 0..5 {
+                let tx = PsyTransaction { tx_id: format!("tx_{}", i), ..PsyTransaction::new(format!("agent_{}", i % 2), "inference".to_string(), 10) };
+                sim.submit_transaction(tx).await.unwrap();
+            }
+            sim
+        }
+    };
+    let sim = run(config.clone()).await;
+    let twin = run(config.clone()).await;
+
+    // Mempool drains in arrival order, max_txs_per_block at a time
+    assert_eq!(sim.transaction_status("tx_0").await.unwrap(), Some(PsyTxStatus::Pending));
+    assert!(sim.transaction_receipt("tx_0").await.unwrap().is_none());
+    let block = sim.produce_block();
+    assert_eq!((block.height, block.tx_ids.clone()), (1, vec!["tx_0".to_string(), "tx_1".to_string()]));
+    assert_eq!(sim.mempool_len(), 3);
+
+    let receipt = sim.transaction_receipt("tx_1").await.unwrap().unwrap();
+    assert_eq!((receipt.status, receipt.block_height, receipt.tx_index, receipt.confirmations), (PsyTxStatus::Included, 1, 1, 1));
+    assert_eq!(receipt.fee_used, receipt.gas_used * block.base_fee);
+
+    // Final after confirmation_depth blocks
+    sim.produce_block();
+    assert_eq!(sim.transaction_status("tx_0").await.unwrap(), Some(PsyTxStatus::Included));
+    sim.produce_block();
+    let receipt = sim.transaction_receipt("tx_0").await.unwrap().unwrap();
+    assert!(receipt.status.is_terminal());
+    assert_eq!(receipt.confirmations, 3);
+    assert_eq!(receipt.status == PsyTxStatus::Confirmed, receipt.success);
+    assert_eq!(sim.block(3).unwrap().parent_hash, sim.block(2).unwrap().hash);
+
+    for _ in 0..3 {
+        twin.produce_block();
+    }
+    for height in 0..=3 {
+        assert_eq!(sim.block(height), twin.block(height));
+    }
+    for i in 0..5 {
+        let tx_id = format!("tx_{}", i);
+        assert_eq!(sim.transaction_receipt(&tx_id).await.unwrap(), twin.transaction_receipt(&tx_id).await.unwrap());
+    }
+}
+
+#[tokio::test]
+async fn test_psy_client_retries_idempotently() {
+    use std::time::Duration;
+
+    let simulator = Arc::new(PsySimulator::new());
+    let server = MockPsyRpcServer::start(simulator.clone()).unwrap();
+    let policy = RetryPolicy {
+        max_attempts: 3,
+        initial_backoff: Duration::from_millis(1),
+        call_timeout: Duration::from_millis(200),
+        ..RetryPolicy::default()
+    };
+    let client = PsyClient::with_endpoint(&server.url()).with_retry_policy(policy.clone());
+
+    // The node executes the submit but the first two responses are lost:
+    // the retries resubmit the same tx id and the chain holds it only once
+    server.lose_responses(2);
+    let tx = PsyTransaction::new("agent_001".to_string(), "inference".to_string(), 100);
+    let tx_id = client.submit_transaction(tx.clone()).await.unwrap();
+    assert_eq!(tx_id, tx.tx_id);
+    assert_eq!(simulator.transactions().len(), 1);
+    assert_eq!(simulator.mempool_len(), 1);
+
+    // Out of attempts: the last retryable error surfaces
+    server.lose_responses(3);
+    let err = client.transaction_status(&tx_id).await.unwrap_err();
+    assert!(is_retryable(&err));
+    assert_eq!(err.code(), "RPC_ERROR");
+
+    // Fatal errors are not retried: reusing an id for different contents
+    let conflicting = PsyTransaction { amount: 1_000_000, ..tx };
+    let err = client.submit_transaction(conflicting).await.unwrap_err();
+    assert!(!is_retryable(&err));
+    assert!(err.to_string().contains(&tx_id));
+
+    // Each attempt is bounded by the call timeout
+    server.set_latency(Duration::from_millis(500));
+    let started = std::time::Instant::now();
+    let err = client.network_stats().await.unwrap_err();
+    assert_eq!(err.code(), "NETWORK_FAILURE");
+    assert!(started.elapsed() < Duration::from_millis(1_400));
+}
+
+#[tokio::test]
+async fn test_psy_batch_submission() {
+    use std::time::Duration;
+
+    let simulator = Arc::new(PsySimulator::new());
+    let server = MockPsyRpcServer::start(simulator.clone()).unwrap();
+    let client = Arc::new(PsyClient::with_endpoint(&server.url()));
+
+    // One call, per-item results: the conflicting reuse of an id fails alone
+    let first = PsyTransaction::new("agent_001".to_string(), "inference".to_string(), 100);
+    let conflicting = PsyTransaction { amount: 999, ..first.clone() };
+    let second = PsyTransaction::new("agent_002".to_string(), "inference".to_string(), 200);
+    let results = client.submit_batch(vec![first.clone(), conflicting, second.clone()]).await.unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].as_ref().unwrap(), &first.tx_id);
+    assert!(results[1].as_ref().unwrap_err().to_string().contains(&first.tx_id));
+    assert_eq!(results[2].as_ref().unwrap(), &second.tx_id);
+    assert_eq!(simulator.mempool_len(), 2);
+    assert_eq!(client.pending_txs().len(), 2);
+    assert!(client.submit_batch(Vec::new()).await.unwrap().is_empty());
+
+    // Individual submissions are coalesced by size, then by linger
+    let batcher = BatchSubmitter::spawn(client.clone(), BatchConfig { max_batch_size: 10, linger: Duration::from_millis(20) });
+    let submissions = (0..25).map(|i| {
+        let batcher = batcher.clone();
+        tokio::spawn(async move {
+            batcher.submit_agent_transaction(format!("agent_{:03}", i), "inference".to_string(), 10).await
+        })
+    });
+    for handle in futures::future::join_all(submissions).await {
+        assert!(handle.unwrap().is_ok());
+    }
+    let metrics = batcher.metrics();
+    assert_eq!(metrics.transactions, 25);
+    assert_eq!(metrics.rejected, 0);
+    assert_eq!(metrics.largest_batch, 10);
+    assert!(metrics.batches >= 3 && metrics.batches < 25);
+    assert_eq!(metrics.round_trips_saved(), 25 - metrics.batches);
+    assert_eq!(simulator.mempool_len(), 27);
+
+    // A batch that can't be delivered fails every transaction in it
+    drop(server);
+    let offline = PsyClient::with_endpoint("http://127.0.0.1:1")
+        .with_retry_policy(RetryPolicy::no_retries(Duration::from_secs(1)));
+    let batcher = BatchSubmitter::spawn(Arc::new(offline), BatchConfig::default());
+    let err = batcher.submit_agent_transaction("agent_001".to_string(), "inference".to_string(), 1).await.unwrap_err();
+    assert_eq!(err.code(), "NETWORK_FAILURE");
+    assert_eq!(batcher.metrics().failed_batches, 1);
+}
+
+#[tokio::test]
+async fn test_psy_watch_streams_and_settlement_on_confirmation() {
+    use std::time::Duration;
+
+    // Simulator: a watch follows one transaction through to finality and ends
+    let simulator = Arc::new(PsySimulator::new());
+    let tx_id = simulator.submit_agent_transaction("agent_001".to_string(), "inference".to_string(), 100).await.unwrap();
+    let other = simulator.submit_agent_transaction("agent_002".to_string(), "inference".to_string(), 100).await.unwrap();
+    let watch = simulator.watch(&tx_id).await.unwrap();
+    let mut all = simulator.subscribe_all();
+    for _ in 0..simulator.config().confirmation_depth {
+        simulator.produce_block();
+    }
+    let statuses: Vec<PsyTxStatus> = watch.map(|update| update.status).collect().await;
+    assert_eq!(statuses.len(), 3);
+    assert_eq!(&statuses[..2], &[PsyTxStatus::Pending, PsyTxStatus::Included]);
+    assert!(statuses[2].is_terminal());
+    let first = all.next().await.unwrap();
+    assert_eq!((first.tx_id, first.status), (tx_id.clone(), PsyTxStatus::Included));
+    assert_eq!(all.next().await.unwrap().tx_id, other);
+
+    // Watching a final transaction yields its status once
+    let replay: Vec<PsyTxUpdate> = simulator.watch(&tx_id).await.unwrap().collect().await;
+    assert_eq!(replay.len(), 1);
+    assert_eq!(replay[0].status, statuses[2]);
+
+    // A subscriber too far behind to get every update catches up from the
+    // current statuses rather than missing the one it waits for
+    let config = PsySimulatorConfig { failure_rate: 0.0, confirmation_depth: 1, ..PsySimulatorConfig::default() };
+    let flooded = PsySimulator::with_config(config, Arc::new(SystemClock)).unwrap();
+    let tx_id = flooded.submit_agent_transaction("agent_001".to_string(), "inference".to_string(), 100).await.unwrap();
+    let watch = flooded.watch(&tx_id).await.unwrap();
+    let mut all = flooded.subscribe_all();
+    for i in 0..2_000 {
+        let tx = PsyTransaction { tx_id: format!("flood_{}", i), ..PsyTransaction::new("agent_002".to_string(), "inference".to_string(), 1) };
+        flooded.submit_transaction(tx).await.unwrap();
+    }
+    flooded.produce_block();
+    let statuses: Vec<PsyTxStatus> = watch.map(|update| update.status).collect().await;
+    assert_eq!(statuses.first(), Some(&PsyTxStatus::Pending));
+    assert_eq!(statuses.last(), Some(&PsyTxStatus::Confirmed));
+    let mut confirmed = false;
+    while let Ok(Some(update)) = tokio::time::timeout(Duration::from_secs(1), all.next()).await {
+        if update.tx_id == tx_id && update.status == PsyTxStatus::Confirmed {
+            confirmed = true;
+            break;
+        }
+    }
+    assert!(confirmed);
+
+    // JSON-RPC client: the status poller drives the stream without manual polling
+    let producer = fast_simulator();
+    let blocks = producer.spawn_block_production();
+    let server = MockPsyRpcServer::start(producer.clone()).unwrap();
+    let client = Arc::new(PsyClient::with_endpoint(&server.url()));
+    let poller = client.spawn_status_poller(Duration::from_millis(5));
+    let tx_id = client.submit_agent_transaction("agent_001".to_string(), "inference".to_string(), 100).await.unwrap();
+    let updates: Vec<PsyTxUpdate> = tokio::time::timeout(Duration::from_secs(5), client.watch(&tx_id).await.unwrap().collect())
+        .await
+        .expect("poller should drive the transaction to finality");
+    assert!(updates.iter().all(|update| update.tx_id == tx_id));
+    assert!(updates.last().unwrap().status.is_terminal());
+    assert!(client.pending_txs().is_empty());
+    assert!(client.local_transaction(&tx_id).unwrap().status.is_terminal());
+    assert_eq!(client.refresh_pending().await, 0);
+
+    // Market: a verified job settles when its settlement transaction confirms
+    let mut market = SpectreMarket::new();
+    let agent_key = generate_agent_keypair();
+    market.ledger.mint("agent_001", 5000).unwrap();
+    market.ledger.mint("user_001", 1_000).unwrap();
+    market.register_agent("agent_001".to_string(), vec!["LLaMA-3-70B".to_string()], 5000, agent_key.verifying_key()).unwrap();
+    market.post_job(1, "user_001".to_string(), "LLaMA-3-70B".to_string(), 500).unwrap();
+    market.claim_job(1, "agent_001").unwrap();
+    let proof = ProofBuilder::new(&agent_key, "agent_001".to_string(), "LLaMA-3-70B").unwrap().job(1).result(b"ok").build();
+    market.submit_result(1, proof).unwrap();
+    assert_eq!(market.settle_on_confirmation(1, "psy_x".to_string()).unwrap_err().code(), "INVALID_JOB_TRANSITION");
+    market.verify_result(1).unwrap();
+
+    let mut updates = producer.subscribe_all();
+    let mut settlement = None;
+    while settlement.is_none() {
+        let tx_id = producer.submit_agent_transaction("agent_001".to_string(), "settlement".to_string(), 500).await.unwrap();
+        market.settle_on_confirmation(1, tx_id).unwrap();
+        while let Some(update) = updates.next().await {
+            match market.apply_tx_update(&update) {
+                None => continue,
+                Some((job_id, Ok(paid))) => {
+                    assert_eq!(job_id, 1);
+                    settlement = Some(paid);
+                }
+                // The simulator fails a small share of transactions: try again
+                Some((_, Err(e))) => assert_eq!(e.code(), "SETTLEMENT_TX_FAILED"),
+            }
+            break;
+        }
+    }
+    assert_eq!(settlement.unwrap(), JobSettlement::Paid { agent_id: "agent_001".to_string(), payout: 495, fee: 5 });
+    assert_eq!(market.jobs[&1].status, JobStatus::Settled);
+    poller.abort();
+    blocks.abort();
+}
+
+#[tokio::test]
+async fn test_psy_terminal_statuses_and_client_archive() {
+    let config = PsySimulatorConfig {
+        mempool_capacity: 2,
+        pending_ttl_blocks: 1,
+        max_txs_per_block: 1,
+        failure_rate: 1.0,
+        confirmation_depth: 1,
+        ..PsySimulatorConfig::default()
+    };
+    let sim = PsySimulator::with_config(config, Arc::new(ManualClock::new(1_000))).unwrap();
+    let tx = |id: &str| PsyTransaction { tx_id: id.to_string(), ..PsyTransaction::new("agent_001".to_string(), "inference".to_string(), 10) };
+
+    // Replacement takes the original's place if it comes from the same sender
+    // and nonce and tips enough more; only pending transactions can be replaced
+    let bumped = |id: &str| PsyTransaction { priority_fee: 1, ..tx(id) };
+    sim.submit_transaction(tx("tx_a")).await.unwrap();
+    let err = sim.replace_transaction("tx_a", PsyTransaction { agent_id: "agent_002".to_string(), ..bumped("tx_b") }).unwrap_err();
+    assert_eq!(err.code(), "REPLACEMENT_MISMATCH");
+    let err = sim.replace_transaction("tx_a", PsyTransaction { nonce: 1, ..bumped("tx_b") }).unwrap_err();
+    assert_eq!(err.code(), "REPLACEMENT_MISMATCH");
+    let err = sim.replace_transaction("tx_a", tx("tx_b")).unwrap_err();
+    assert_eq!(err, SpectreError::ReplacementUnderpriced { tx_id: "tx_a".to_string(), min_priority_fee: 1 });
+    assert_eq!(sim.transaction_status("tx_a").await.unwrap(), Some(PsyTxStatus::Pending));
+    assert_eq!(sim.replace_transaction("tx_a", bumped("tx_b")).unwrap(), "tx_b");
+    assert_eq!(sim.transaction_status("tx_a").await.unwrap(), Some(PsyTxStatus::Replaced { by: "tx_b".to_string() }));
+    let err = sim.replace_transaction("tx_a", tx("tx_c")).unwrap_err();
+    assert_eq!(err.code(), "TRANSACTION_NOT_REPLACEABLE");
+
+    // A full mempool evicts its lowest-paying transaction for a better-paying one
+    let tipped = |id: &str| PsyTransaction { priority_fee: 2, ..tx(id) };
+    sim.submit_transaction(tipped("tx_c")).await.unwrap();
+    assert_eq!(sim.submit_transaction(tx("tx_x")).await.unwrap_err().code(), "MEMPOOL_FULL");
+    sim.submit_transaction(tipped("tx_d")).await.unwrap();
+    let dropped = sim.transaction_status("tx_b").await.unwrap().unwrap();
+    assert!(matches!(dropped, PsyTxStatus::Dropped { .. }));
+    assert!(dropped.is_terminal());
+    assert_eq!(sim.mempool_len(), 2);
+
+    // Resubmitting a dropped or replaced transaction doesn't pass for a no-op
+    assert_eq!(sim.submit_transaction(bumped("tx_b")).await.unwrap_err().code(), "TRANSACTION_DROPPED");
+    assert_eq!(sim.submit_transaction(tx("tx_a")).await.unwrap_err().code(), "TRANSACTION_DROPPED");
+    assert_eq!(sim.submit_transaction(tipped("tx_d")).await.unwrap(), "tx_d");
+
+    // Every execution reverts here, with a reason code on the status
+    sim.produce_block();
+    let reverted = sim.transaction_status("tx_c").await.unwrap().unwrap();
+    let PsyTxStatus::Reverted { code, .. } = &reverted else { panic!("expected a revert, got {:?}", reverted) };
+    assert!(!code.is_empty());
+    assert!(reverted.failure_reason().unwrap().contains(code.as_str()));
+    assert!(!sim.transaction_receipt("tx_c").await.unwrap().unwrap().success);
+
+    // tx_d waited longer than pending_ttl_blocks
+    sim.produce_block();
+    sim.produce_block();
+    assert_eq!(sim.transaction_status("tx_d").await.unwrap(), Some(PsyTxStatus::Expired));
+    assert_eq!(sim.mempool_len(), 0);
+    let err = sim.submit_transaction(tipped("tx_d")).await.unwrap_err();
+    assert_eq!(err.code(), "TRANSACTION_DROPPED");
+    assert!(err.to_string().contains("Expired"));
+    assert!(PsyTxStatus::Confirmed.failure_reason().is_none());
+
+    // The client archives final transactions, keeping only the newest
+    let producer = fast_simulator();
+    let blocks = producer.spawn_block_production();
+    let server = MockPsyRpcServer::start(producer.clone()).unwrap();
+    let client = PsyClient::with_endpoint(&server.url()).with_archive_capacity(2);
+    let mut tx_ids = Vec::new();
+    for i in 0..3 {
+        tx_ids.push(client.submit_agent_transaction(format!("agent_{:03}", i), "inference".to_string(), 10).await.unwrap());
+    }
+    assert_eq!(client.pending_txs().len(), 3);
+    for _ in 0..500 {
+        if client.refresh_pending().await == 0 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+    assert!(client.pending_txs().is_empty());
+    let archived = client.archived_txs();
+    assert_eq!(archived.len(), 2);
+    assert!(archived.iter().all(|tx| tx.status.is_terminal()));
+    assert_eq!(client.local_transaction(&tx_ids[0]).is_none(), !archived.iter().any(|tx| tx.tx_id == tx_ids[0]));
+
+    blocks.abort();
+}
+
+#[tokio::test]
+async fn test_psy_transaction_signing_and_nonces() {
+    // Signatures cover every field but the status
+    let key = generate_agent_keypair();
+    let mut tx = PsyTransaction { max_fee: 50, priority_fee: 5, ..PsyTransaction::new("agent_001".to_string(), "inference".to_string(), 100) };
+    assert!(!tx.is_signed());
+    tx.sign(&key);
+    assert_eq!(tx.verify_signature().unwrap(), key.verifying_key());
+    let tampered = PsyTransaction { priority_fee: 500, ..tx.clone() };
+    assert_eq!(tampered.verify_signature().unwrap_err().code(), "INVALID_TRANSACTION_SIGNATURE");
+
+    let config = PsySimulatorConfig { require_signatures: true, ..PsySimulatorConfig::default() };
+    let simulator = Arc::new(PsySimulator::with_config(config, Arc::new(SystemClock)).unwrap());
+    let err = simulator.submit_transaction(tampered).await.unwrap_err();
+    assert_eq!(err.code(), "INVALID_TRANSACTION_SIGNATURE");
+    let unsigned = PsyTransaction::new("agent_001".to_string(), "inference".to_string(), 100);
+    assert_eq!(simulator.submit_transaction(unsigned).await.unwrap_err().code(), "INVALID_TRANSACTION_SIGNATURE");
+
+    // Keys are registered up front, never taken from an account's first transaction
+    let err = simulator.submit_transaction(tx.clone()).await.unwrap_err();
+    assert!(err.to_string().contains("no registered key"));
+    simulator.register_account_key("agent_001", &key.verifying_key());
+
+    // The client signs for accounts in its keyring; concurrent submits get distinct nonces
+    let server = MockPsyRpcServer::start(simulator.clone()).unwrap();
+    let mut keyring = PsyKeyring::new();
+    keyring.insert("agent_001", key.clone());
+    let client = Arc::new(PsyClient::with_endpoint(&server.url()).with_keyring(keyring));
+    let submissions = (0..20).map(|_| {
+        let client = client.clone();
+        tokio::spawn(async move {
+            client.submit_agent_transaction("agent_001".to_string(), "inference".to_string(), 10).await
+        })
+    });
+    for handle in futures::future::join_all(submissions).await {
+        handle.unwrap().unwrap();
+    }
+    let mut nonces: Vec<u64> = simulator.transactions().iter().map(|tx| tx.nonce).collect();
+    nonces.sort_unstable();
+    assert_eq!(nonces, (0..20).collect::<Vec<_>>());
+    assert_eq!(client.account_nonce("agent_001").await.unwrap(), 20);
+
+    // A rejected submission hands its nonce back, leaving no gap
+    let existing = simulator.transactions()[0].tx_id.clone();
+    let conflicting = PsyTransaction { tx_id: existing, ..PsyTransaction::new("agent_001".to_string(), "other".to_string(), 1) };
+    assert!(client.submit_transaction(conflicting).await.is_err());
+    assert_eq!(client.nonces().peek("agent_001"), 20);
+    let tx_id = client.submit_agent_transaction("agent_001".to_string(), "inference".to_string(), 10).await.unwrap();
+    assert_eq!(client.local_transaction(&tx_id).unwrap().nonce, 20);
+
+    // Retrying an unsigned transaction reuses the nonce and signature it was
+    // first submitted under, even when the first response was lost
+    let retried = PsyTransaction::new("agent_001".to_string(), "inference".to_string(), 10);
+    assert_eq!(client.submit_transaction(retried.clone()).await.unwrap(), retried.tx_id);
+    assert_eq!(client.submit_transaction(retried.clone()).await.unwrap(), retried.tx_id);
+    assert_eq!(client.nonces().peek("agent_001"), 22);
+    let mut keyring = PsyKeyring::new();
+    keyring.insert("agent_001", key.clone());
+    let impatient = PsyClient::with_endpoint(&server.url())
+        .with_keyring(keyring)
+        .with_retry_policy(RetryPolicy::no_retries(std::time::Duration::from_secs(5)));
+    impatient.resync_nonce("agent_001").await.unwrap();
+    let lost = PsyTransaction::new("agent_001".to_string(), "inference".to_string(), 10);
+    server.lose_responses(1);
+    assert!(impatient.submit_transaction(lost.clone()).await.is_err());
+    assert_eq!(impatient.submit_transaction(lost.clone()).await.unwrap(), lost.tx_id);
+    assert_eq!(impatient.nonces().peek("agent_001"), 23);
+    assert_eq!(simulator.account_nonce("agent_001").await.unwrap(), 23);
+
+    // A second client for the same account collides until it resyncs with the chain
+    simulator.produce_block();
+    let mut keyring = PsyKeyring::new();
+    keyring.insert("agent_001", key);
+    let restarted = PsyClient::with_endpoint(&server.url()).with_keyring(keyring);
+    let err = restarted.submit_agent_transaction("agent_001".to_string(), "inference".to_string(), 10).await.unwrap_err();
+    assert!(err.to_string().contains("already used"));
+    assert_eq!(restarted.nonces().peek("agent_001"), 1);
+    assert_eq!(restarted.resync_nonce("agent_001").await.unwrap(), 23);
+    let tx_id = restarted.submit_agent_transaction("agent_001".to_string(), "inference".to_string(), 10).await.unwrap();
+    assert_eq!(restarted.local_transaction(&tx_id).unwrap().nonce, 23);
+
+    // Only the account's own key may sign for it
+    let mut impostor = PsyTransaction::new("agent_001".to_string(), "inference".to_string(), 10);
+    impostor.nonce = 24;
+    impostor.sign(&generate_agent_keypair());
+    assert_eq!(simulator.submit_transaction(impostor).await.unwrap_err().code(), "INVALID_TRANSACTION_SIGNATURE");
+}
+
+#[tokio::test]
+async fn test_psy_fee_market() {
+    let config = PsySimulatorConfig {
+        max_txs_per_block: 2,
+        target_txs_per_block: 1,
+        initial_base_fee: 10,
+        failure_rate: 0.0,
+        ..PsySimulatorConfig::default()
+    };
+    let simulator = Arc::new(PsySimulator::with_config(config.clone(), Arc::new(ManualClock::new(1_000))).unwrap());
+    let tx = |id: &str, priority_fee: u64, max_fee: u64| PsyTransaction {
+        tx_id: id.to_string(),
+        priority_fee,
+        max_fee,
+        ..PsyTransaction::new("agent_001".to_string(), "arb".to_string(), 10)
+    };
+
+    // Highest tips go first; a fee cap below the base fee waits
+    for (id, tip, cap) in [("cheap", 0, 0), ("fast_1", 5, 0), ("capped", 3, 5), ("fast_2", 5, 100)] {
+        simulator.submit_transaction(tx(id, tip, cap)).await.unwrap();
+    }
+    let block = simulator.produce_block();
+    assert_eq!(block.tx_ids, vec!["fast_1".to_string(), "fast_2".to_string()]);
+    assert!(block.base_fee > 5 && block.base_fee <= 10);
+    let receipt = simulator.transaction_receipt("fast_2").await.unwrap().unwrap();
+    assert_eq!(receipt.fee_used, config.gas_per_tx * (block.base_fee + 5));
+
+    // A block above target pushes the base fee up; tips are estimated from what was paid
+    let estimate = simulator.fee_estimate().await.unwrap();
+    assert!(estimate.base_fee > block.base_fee);
+    assert_eq!((estimate.normal_tip, estimate.max_tip, estimate.sample_blocks), (5, 5, 1));
+    assert_eq!(estimate.priority_fee(FeeUrgency::Instant), 6);
+
+    // Over JSON-RPC, an urgent agent outbids the waiting traffic
+    let server = MockPsyRpcServer::start(simulator.clone()).unwrap();
+    let client = PsyClient::with_endpoint(&server.url());
+    assert_eq!(client.fee_estimate().await.unwrap(), estimate);
+    let urgent = client.submit_with_urgency(tx("urgent", 0, 0), FeeUrgency::Instant).await.unwrap();
+    let submitted = client.local_transaction(&urgent).unwrap();
+    assert_eq!((submitted.priority_fee, submitted.max_fee), (6, estimate.base_fee * 2 + 6));
+    assert_eq!(simulator.produce_block().tx_ids[0], urgent);
+    assert_eq!(simulator.transaction_status("capped").await.unwrap(), Some(PsyTxStatus::Pending));
+
+    // Reusing a pending nonce with a higher tip replaces the original
+    let key = generate_agent_keypair();
+    simulator.register_account_key("agent_002", &key.verifying_key());
+    let signed = |id: &str, tip: u64| {
+        let mut tx = PsyTransaction { tx_id: id.to_string(), priority_fee: tip, ..PsyTransaction::new("agent_002".to_string(), "arb".to_string(), 10) };
+        tx.sign(&key);
+        tx
+    };
+    simulator.submit_transaction(signed("slow", 10)).await.unwrap();
+    let err = simulator.submit_transaction(signed("nudge", 10)).await.unwrap_err();
+    assert_eq!(err, SpectreError::ReplacementUnderpriced { tx_id: "slow".to_string(), min_priority_fee: 11 });
+    assert_eq!(simulator.submit_transaction(signed("bump", 11)).await.unwrap(), "bump");
+    assert_eq!(simulator.transaction_status("slow").await.unwrap(), Some(PsyTxStatus::Replaced { by: "bump".to_string() }));
+    assert_eq!(simulator.account_nonce("agent_002").await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn test_psy_network_stats_from_observed_traffic() {
+    let config = PsySimulatorConfig {
+        block_interval_ms: 100,
+        confirmation_depth: 2,
+        failure_rate: 0.0,
+        pending_ttl_blocks: 1,
+        initial_base_fee: 10,
+        ..PsySimulatorConfig::default()
+    };
+    let simulator = PsySimulator::with_config(config, Arc::new(SystemClock)).unwrap();
+    for agent in ["agent_001", "agent_002", "agent_003"] {
+        simulator.submit_agent_transaction(agent.to_string(), "inference".to_string(), 100).await.unwrap();
+    }
+    // Its fee cap never covers the base fee, so it expires
+    let mut stuck = PsyTransaction::new("agent_004".to_string(), "scrape".to_string(), 10);
+    stuck.max_fee = 5;
+    simulator.submit_transaction(stuck).await.unwrap();
+    simulator.produce_block();
+    simulator.produce_block();
+
+    // Two blocks of 100ms from submission to finality
+    let stats = simulator.network_stats().await.unwrap();
+    assert_eq!(stats.active_agents, 4);
+    assert_eq!((stats.latency_p50_ms, stats.latency_p95_ms, stats.latency_p99_ms, stats.avg_latency_ms), (200, 200, 200, 200));
+    assert_eq!(stats.tps, 15);
+    assert_eq!(stats.failure_rate, 0.25);
+
+    // A node without the stats method: the client reports what it has seen
+    let simulator = fast_simulator();
+    let _blocks = simulator.spawn_block_production();
+    let server = MockPsyRpcServer::start(simulator.clone()).unwrap();
+    server.disable_method(METHOD_GET_NETWORK_STATS);
+    let client = PsyClient::with_endpoint(&server.url());
+    assert_eq!(client.network_stats().await.unwrap(), client.observed_stats());
+    pay_agent(&client, "agent_001").await.unwrap();
+    pay_agent(&client, "agent_002").await.unwrap();
+
+    let stats = client.network_stats().await.unwrap();
+    assert_eq!(stats.active_agents, 2);
+    assert_eq!(stats.parth_depth, 0);
+    assert!(stats.tps > 0);
+    assert!(stats.latency_p50_ms <= stats.latency_p95_ms && stats.latency_p95_ms <= stats.latency_p99_ms);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_parth_market_concurrent_claims_across_shards() {
+    let market = Arc::new(ParthMarket::new());
+    let mut agents = Vec::new();
+    for i in 0..8 {
+        let agent_id = format!("agent_{:03}", i);
+        let key = generate_agent_keypair();
+        market.mint(&agent_id, 5_000).unwrap();
+        market.register_agent(agent_id.clone(), vec!["LLaMA-3-70B".to_string()], 5_000, key.verifying_key()).unwrap();
+        agents.push((agent_id, key));
+    }
+    market.mint("user_001", 10_000).unwrap();
+    for job_id in 0..200 {
+        market.post_job(job_id, "user_001".to_string(), "LLaMA-3-70B".to_string(), 10).unwrap();
+    }
+    assert_eq!(market.ledger.balance("user_001"), 8_000);
+
+    // Each agent works its own slice of jobs from its own task
+    let tasks: Vec<_> = agents.iter().enumerate().map(|(i, (agent_id, key))| {
+        let market = market.clone();
+        let agent_id = agent_id.clone();
+        let key = key.clone();
+        tokio::spawn(async move {
+            for job_id in (i as u64..200).step_by(8) {
+                let proof = ProofBuilder::new(&key, agent_id.clone(), "LLaMA-3-70B").unwrap().job(job_id).build();
+                market.attempt_job_execution(job_id, &proof).unwrap();
+            }
+        })
+    }).collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+    assert!(market.jobs().iter().all(|job| job.status == JobStatus::Claimed));
+    assert_eq!(market.job(9).unwrap().assigned_agent.as_deref(), Some("agent_001"));
+    assert_eq!(market.stake_account("agent_001").unwrap().free(), 5_000 - 25 * 5);
+
+    // Every agent races for one extra job; exactly one gets it
+    market.post_job(500, "user_001".to_string(), "LLaMA-3-70B".to_string(), 100).unwrap();
+    let racers: Vec<_> = agents.iter().map(|(agent_id, key)| {
+        let market = market.clone();
+        let proof = ProofBuilder::new(key, agent_id.clone(), "LLaMA-3-70B").unwrap().job(500).build();
+        tokio::spawn(async move { market.attempt_job_execution(500, &proof) })
+    }).collect();
+    let mut winners = 0;
+    for racer in racers {
+        match racer.await.unwrap() {
+            Ok(_) => winners += 1,
+            Err(e) => assert_eq!(e, SpectreError::JobAlreadyTaken { job_id: 500 }),
+        }
+    }
+    assert_eq!(winners, 1);
+
+    // The rest of the lifecycle moves escrow across account shards atomically
+    let winner = market.job(500).unwrap().assigned_agent.unwrap();
+    let key = &agents.iter().find(|(agent_id, _)| *agent_id == winner).unwrap().1;
+    let result = ProofBuilder::new(key, winner.clone(), "LLaMA-3-70B").unwrap().job(500).result(b"done").build();
+    market.submit_result(500, result).unwrap();
+    market.verify_result(500).unwrap();
+    assert_eq!(market.settle_job(500).unwrap(), JobSettlement::Paid { agent_id: winner.clone(), payout: 99, fee: 1 });
+    assert_eq!(market.ledger.balance(&winner), 99);
+    assert_eq!(market.ledger.balance(TREASURY_ACCOUNT), 1);
+    assert_eq!(market.ledger.total_escrowed(), 2_000);
+    assert_eq!(market.ledger.total_supply(), 50_000);
+    market.ledger.check_invariants().unwrap();
+
+    // The sharded state commits to a root like the single-lock market
+    let mut state = StateTree::new();
+    let root = state.commit_parth(&market);
+    state.prove(StateNamespace::Jobs, "500").verify_inclusion(&root, &market.job(500).unwrap()).unwrap();
+    state.prove(StateNamespace::Balances, &winner).verify_inclusion(&root, &99u64).unwrap();
+
+    // Later commits rehash only the shards that moved and land on the same root as a fresh tree
+    let untouched = market.changes_since(&ShardVersions::default()).versions;
+    assert!(market.changes_since(&untouched).jobs.is_empty());
+    market.post_job(600, "user_001".to_string(), "LLaMA-3-70B".to_string(), 10).unwrap();
+    let changes = market.changes_since(&untouched);
+    assert_eq!(changes.jobs.len(), 1);
+    assert!(changes.agents.is_empty());
+    let next_root = state.commit_parth(&market);
+    assert_ne!(next_root, root);
+    assert_eq!(next_root, StateTree::new().commit_parth(&market));
+    state.prove(StateNamespace::Jobs, "600").verify_inclusion(&next_root, &market.job(600).unwrap()).unwrap();
+
+    // Every shard logs to one log, which rebuilds the same state on a single-lock market
+    let rebuilt = SpectreMarket::from_events(SDKeyRegistry::new(), &market.events().records()).unwrap();
+    assert_eq!(StateTree::new().commit_market(&rebuilt), next_root);
+    rebuilt.ledger.check_invariants().unwrap();
+
+    // Switching markets starts over rather than mixing the two
+    let other = ParthMarket::new();
+    assert_eq!(state.commit_parth(&other), StateTree::new().commit_parth(&other));
+    assert_eq!(state.commit_parth(&market), next_root);
+}
+
+#[test]
+fn test_parth_market_shared_catalog_and_atomic_slash() {
+    let market = ParthMarket::new();
+    let key = generate_agent_keypair();
+
+    // One catalog for every shard: a model added once is open on all of them
+    market.update_catalog(|catalog| catalog.add_model("Qwen-2-72B", 32000, "1", "0x7177656e01", 0)).unwrap();
+    let err = market.update_catalog(|catalog| catalog.add_model("Qwen-2-72B", 1, "9", "0x7177656e09", 0)).unwrap_err();
+    assert_eq!(err.code(), "INVALID_CATALOG");
+    assert_eq!(market.catalog().get("Qwen-2-72B").unwrap().max_tokens, 32000);
+    for i in 0..8 {
+        let agent_id = format!("qwen_{}", i);
+        market.ledger.mint(&agent_id, 5_000).unwrap();
+        market.register_agent(agent_id, vec!["Qwen-2-72B".to_string()], 5_000, key.verifying_key()).unwrap();
+    }
+    market.ledger.mint("user_001", 1_000).unwrap();
+    for job_id in 0..8 {
+        market.post_job(job_id, "user_001".to_string(), "Qwen-2-72B".to_string(), 10).unwrap();
+    }
+    market.update_catalog(|catalog| catalog.deprecate_model("Qwen-2-72B", 0)).unwrap();
+    let err = market.post_job(8, "user_001".to_string(), "Qwen-2-72B".to_string(), 10).unwrap_err();
+    assert_eq!(err.code(), "MODEL_DEPRECATED");
+    assert!(market.catalog().get("Qwen-2-72B").unwrap().deprecated_at.is_some());
+
+    // A slash the ledger can't pay out leaves the agent's stake alone
+    market.ledger.mint("agent_001", 5_000).unwrap();
+    market.register_agent("agent_001".to_string(), vec!["LLaMA-3-70B".to_string()], 5_000, key.verifying_key()).unwrap();
+    market.post_job(100, "user_001".to_string(), "LLaMA-3-70B".to_string(), 100).unwrap();
+    market.claim_job(100, "agent_001").unwrap();
+    let wrong_hash = ProofBuilder::new(&key, "agent_001".to_string(), "LLaMA-3-70B").unwrap().job(100).model_hash("0xdeadbeef").build();
+    market.submit_result(100, wrong_hash).unwrap();
+    let vault = market.ledger.balance(STAKE_VAULT_ACCOUNT);
+    market.ledger.transfer(STAKE_VAULT_ACCOUNT, "elsewhere", vault).unwrap();
+    assert_eq!(market.verify_result(100).unwrap_err().code(), "INSUFFICIENT_FUNDS");
+    assert_eq!(market.stake_account("agent_001").unwrap().bonded, 5_000);
+    assert!(market.slash_events("agent_001").is_empty());
+    market.ledger.check_invariants().unwrap();
+}
+
+#[test]
+fn test_parth_market_expiry_dispute_unstake_and_settlement_tx() {
+    let clock = ManualClock::new(1_000);
+    let market = ParthMarket::with_clock(Arc::new(clock.clone()));
+    let key = generate_agent_keypair();
+    market.mint("agent_001", 5_000).unwrap();
+    market.register_agent("agent_001".to_string(), vec!["LLaMA-3-70B".to_string()], 5_000, key.verifying_key()).unwrap();
+    market.mint("user_001", 1_000).unwrap();
+
+    // A claimed job whose agent disappears doesn't hold escrow and stake forever
+    market.post_job_request(JobRequest::new(1, "user_001".to_string(), "LLaMA-3-70B".to_string(), 100).expires_at(2_000)).unwrap();
+    market.claim_job(1, "agent_001").unwrap();
+    assert_eq!(market.expire_jobs(1_500), ExpirySweep::default());
+    clock.set(2_000);
+    assert_eq!(market.expire_jobs(2_000), ExpirySweep { expired: vec![1], failed: vec![] });
+    assert_eq!(market.job(1).unwrap().status, JobStatus::Expired);
+    let stake = market.stake_account("agent_001").unwrap();
+    assert!(stake.locked.is_empty());
+    assert_eq!(stake.bonded, 4_750);
+    assert_eq!(market.slash_events("agent_001").len(), 1);
+    assert_eq!(market.ledger.balance("user_001"), 1_250);
+    assert_eq!(market.ledger.total_escrowed(), 0);
+
+    // Verified work can be disputed once, and settles only when its transaction confirms
+    market.post_job(2, "user_001".to_string(), "LLaMA-3-70B".to_string(), 100).unwrap();
+    market.claim_job(2, "agent_001").unwrap();
+    let result = ProofBuilder::new(&key, "agent_001".to_string(), "LLaMA-3-70B").unwrap().job(2).result(b"done").timestamp(2_000).build();
+    market.submit_result(2, result).unwrap();
+    market.verify_result(2).unwrap();
+    market.dispute_job(2).unwrap();
+    assert_eq!(market.dispute_job(2).unwrap_err().code(), "JOB_ALREADY_DISPUTED");
+    assert_eq!(market.dispute_job(1).unwrap_err().code(), "JOB_NOT_DISPUTABLE");
+    assert_eq!(market.settle_on_confirmation(1, "tx_1".to_string()).unwrap_err().code(), "INVALID_JOB_TRANSITION");
+    market.settle_on_confirmation(2, "tx_2".to_string()).unwrap();
+    let dropped = PsyTxUpdate { tx_id: "tx_2".to_string(), status: PsyTxStatus::Dropped { reason: "fee too low".to_string() } };
+    assert_eq!(market.apply_tx_update(&dropped).unwrap().1.unwrap_err().code(), "SETTLEMENT_TX_FAILED");
+    assert!(market.job(2).unwrap().settlement_tx.is_none());
+    market.settle_on_confirmation(2, "tx_3".to_string()).unwrap();
+    assert!(market.apply_tx_update(&PsyTxUpdate { tx_id: "tx_3".to_string(), status: PsyTxStatus::Pending }).is_none());
+    let confirmed = PsyTxUpdate { tx_id: "tx_3".to_string(), status: PsyTxStatus::Confirmed };
+    assert_eq!(market.apply_tx_update(&confirmed), Some((2, Ok(JobSettlement::Paid { agent_id: "agent_001".to_string(), payout: 99, fee: 1 }))));
+    assert_eq!(market.job(2).unwrap().status, JobStatus::Settled);
+
+    // Unstaked tokens come back through the ledger once unbonding ends
+    let release_at = market.request_unstake("agent_001", 1_000).unwrap();
+    assert_eq!(market.withdraw_unstaked("agent_001").unwrap(), 0);
+    clock.set(release_at);
+    assert_eq!(market.withdraw_unstaked("agent_001").unwrap(), 1_000);
+    assert_eq!(market.ledger.balance("agent_001"), 1_099);
+    assert_eq!(market.stake_account("agent_001").unwrap().bonded, 3_750);
+    assert_eq!(market.ledger.balance(STAKE_VAULT_ACCOUNT), 3_750);
+    market.ledger.check_invariants().unwrap();
+
+    // Job events land on the same log as the agents' and rebuild the market
+    let records = market.events().records();
+    assert!(records.iter().any(|record| matches!(record.event, DomainEvent::JobExpired { job_id: 1 })));
+    assert!(records.iter().any(|record| matches!(record.event, DomainEvent::SettlementTxFailed { job_id: 2, .. })));
+    assert!(records.iter().any(|record| matches!(record.event, DomainEvent::AgentSlashed { on_ledger: true, .. })));
+    let rebuilt = SpectreMarket::from_events(SDKeyRegistry::with_clock(Arc::new(clock.clone())), &records).unwrap();
+    assert_eq!(StateTree::new().commit_market(&rebuilt), StateTree::new().commit_parth(&market));
+    assert_eq!(rebuilt.registry.stakes.account("agent_001"), market.stake_account("agent_001").as_ref());
+}
+
+#[test]
+fn test_state_root_and_light_client_proofs() {
+    let mut market = SpectreMarket::new();
+    let agent_key = generate_agent_keypair();
+    market.ledger.mint("agent_001", 5_000).unwrap();
+    market.register_agent("agent_001".to_string(), vec!["LLaMA-3-70B".to_string()], 5_000, agent_key.verifying_key()).unwrap();
+    market.ledger.mint("user_001", 1_000).unwrap();
+    for job_id in 1..=20 {
+        market.post_job(job_id, "user_001".to_string(), "LLaMA-3-70B".to_string(), 10).unwrap();
+    }
+    let mut state = StateTree::new();
+    let root = state.commit_market(&market);
+    assert_eq!(state.tree(StateNamespace::Jobs).len(), 20);
+
+    // A light client holding only the root checks jobs, agents and balances
+    let job = market.jobs[&7].clone();
+    state.prove(StateNamespace::Jobs, "7").verify_inclusion(&root, &job).unwrap();
+    let agent = market.registry.verified_agents["agent_001"].clone();
+    state.prove(StateNamespace::Agents, "agent_001").verify_inclusion(&root, &agent).unwrap();
+    state.prove(StateNamespace::Balances, "user_001").verify_inclusion(&root, &800u64).unwrap();
+    state.prove(StateNamespace::Jobs, "99").verify_exclusion(&root).unwrap();
+    state.prove(StateNamespace::Agents, "agent_999").verify_exclusion(&root).unwrap();
+
+    // Proofs travel as JSON
+    let json = serde_json::to_string(&state.prove(StateNamespace::Jobs, "7")).unwrap();
+    let proof: StateProof = serde_json::from_str(&json).unwrap();
+    proof.verify_inclusion(&root, &job).unwrap();
+
+    // Wrong values, wrong keys and tampered paths are rejected
+    let mut stale = job.clone();
+    stale.reward_tokens = 1_000;
+    assert_eq!(proof.verify_inclusion(&root, &stale).unwrap_err().code(), "INVALID_STATE_PROOF");
+    assert!(proof.verify_exclusion(&root).is_err());
+    let mut moved = proof.clone();
+    moved.key = "8".to_string();
+    assert!(moved.verify(&root).is_err());
+    let mut tampered = proof.clone();
+    tampered.proof.siblings[0][0] ^= 1;
+    assert!(tampered.verify(&root).is_err());
+    let mut forged = proof.clone();
+    forged.namespace_roots[1] = [7; 32];
+    assert!(forged.verify(&root).is_err());
+
+    // A batch moves the root; old proofs no longer verify against it
+    let proof = ProofBuilder::new(&agent_key, "agent_001".to_string(), "LLaMA-3-70B").unwrap().job(7).build();
+    market.attempt_job_execution(7, &proof).unwrap();
+    market.cancel_job(20).unwrap();
+    let next_root = state.commit_market(&market);
+    assert_ne!(next_root, root);
+    assert!(state.prove(StateNamespace::Jobs, "7").verify_inclusion(&next_root, &job).is_err());
+    state.prove(StateNamespace::Jobs, "7").verify_inclusion(&next_root, &market.jobs[&7]).unwrap();
+    assert_eq!(state.commit_market(&market), next_root);
+
+    // Incremental updates land on the same root as committing from scratch
+    assert_eq!(StateTree::new().commit_market(&market), next_root);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn test_market_handle_racing_agents_stress() {
+    const AGENTS: usize = 16;
+    const JOBS: u64 = 300;
+    let market = MarketHandle::new();
+    let mut agents = Vec::new();
+    for i in 0..AGENTS {
+        let agent_id = format!("agent_{:03}", i);
+        let key = generate_agent_keypair();
+        market.market().ledger.mint(&agent_id, 5_000).unwrap();
+        market.register_agent(agent_id.clone(), vec!["LLaMA-3-70B".to_string()], 5_000, key.verifying_key()).await.unwrap();
+        agents.push((agent_id, key));
+    }
+    market.market().ledger.mint("user_001", JOBS * 10).unwrap();
+
+    // Jobs are posted from many tasks while every agent races for each one
+    let tasks: Vec<_> = (0..JOBS).flat_map(|job_id| {
+        let poster = {
+            let market = market.clone();
+            tokio::spawn(async move {
+                market.post_job(job_id, "user_001".to_string(), "LLaMA-3-70B".to_string(), 10).await.unwrap();
+                None
+            })
+        };
+        let racers = agents.iter().map(move |(agent_id, key)| (job_id, agent_id.clone(), key.clone()));
+        std::iter::once(poster).chain(racers.map(|(job_id, agent_id, key)| {
+            let market = market.clone();
+            tokio::spawn(async move {
+                let proof = ProofBuilder::new(&key, agent_id, "LLaMA-3-70B").unwrap().job(job_id).build();
+                loop {
+                    match market.attempt_job_execution(job_id, &proof).await {
+                        Err(SpectreError::JobNotFound { .. }) => tokio::task::yield_now().await,
+                        outcome => return Some((job_id, outcome)),
+                    }
+                }
+            })
+        }))
+    }).collect();
+
+    let mut winners = vec![0; JOBS as usize];
+    for task in tasks {
+        match task.await.unwrap() {
+            Some((job_id, Ok(_))) => winners[job_id as usize] += 1,
+            Some((job_id, Err(e))) => assert_eq!(e, SpectreError::JobAlreadyTaken { job_id }),
+            None => {}
+        }
+    }
+    assert!(winners.iter().all(|&count| count == 1));
+
+    let jobs = market.market().jobs();
+    assert_eq!(jobs.len(), JOBS as usize);
+    assert!(jobs.iter().all(|job| job.status == JobStatus::Claimed && job.assigned_agent.is_some()));
+    let locked: u64 = agents.iter()
+        .map(|(agent_id, _)| 5_000 - market.market().stake_account(agent_id).unwrap().free())
+        .sum();
+    assert_eq!(locked, JOBS * 5);
+    assert_eq!(market.market().ledger.total_escrowed(), JOBS * 10);
+    market.market().ledger.check_invariants().unwrap();
+}
+
+#[test]
+fn test_persistent_market_recovers_from_log_and_snapshots() {
+    let dir = tempfile::tempdir().unwrap();
+    let clock = ManualClock::new(1_700_000_000);
+    let open = || {
+        let storage = FileStorage::open(dir.path()).unwrap();
+        PersistentMarket::open_with(storage, Arc::new(clock.clone()), SDKeyRegistry::builder()).unwrap()
+    };
+    let agent_key = generate_agent_keypair();
+    let proof = |job_id: u64, nonce: u32| {
+        ProofBuilder::new(&agent_key, "agent_001".to_string(), "LLaMA-3-70B").unwrap().job(job_id).result(b"done").timestamp(clock.now()).nonce(nonce).build()
+    };
+
+    let mut market = open();
+    market.mint("agent_001", 5_000).unwrap();
+    market.register_agent("agent_001".to_string(), vec!["LLaMA-3-70B".to_string()], 5_000, agent_key.verifying_key()).unwrap();
+    market.mint("user_001", 1_000).unwrap();
+    for job_id in 1..=3 {
+        market.post_job(job_id, "user_001".to_string(), "LLaMA-3-70B".to_string(), 100).unwrap();
+    }
+    market.attempt_job_execution(1, &proof(1, 1)).unwrap();
+    clock.advance(5);
+    market.submit_result(1, proof(1, 2)).unwrap();
+    market.verify_result(1).unwrap();
+    market.settle_job(1).unwrap();
+    market.cancel_job(3).unwrap();
+    assert_eq!(market.cancel_job(99).unwrap_err().code(), "JOB_NOT_FOUND");
+    let tx = PsyTransaction::new("agent_001".to_string(), "inference".to_string(), 100);
+    market.track_tx(tx.clone()).unwrap();
+    let root = StateTree::new().commit_market(market.market());
+    let (seq, nonces) = (market.last_seq(), market.market().registry.tracked_nonces("agent_001"));
+    let events = market.market().events().records();
+    drop(market);
+
+    // Long after the proofs went stale, replay still runs them at the time they were logged
+    clock.advance(DEFAULT_PROOF_MAX_AGE_SECS * 10);
+    let mut market = open();
+    assert_eq!(market.last_seq(), seq);
+    assert_eq!(StateTree::new().commit_market(market.market()), root);
+    assert_eq!(market.market().jobs[&1].status, JobStatus::Settled);
+    assert_eq!(market.market().registry.tracked_nonces("agent_001"), nonces);
+    assert_eq!(serde_json::to_string(&market.market().events().records()).unwrap(), serde_json::to_string(&events).unwrap());
+    assert_eq!(market.market().registry.reputation.history("agent_001").len(), 1);
+    assert!(market.pending_txs().contains_key(&tx.tx_id));
+    market.market().ledger.check_invariants().unwrap();
+
+    // Compaction folds the log into a snapshot
+    market.apply_tx_update(&PsyTxUpdate { tx_id: tx.tx_id.clone(), status: PsyTxStatus::Confirmed }).unwrap();
+    market.compact().unwrap();
+    let wal = dir.path().join("wal.log");
+    assert_eq!(std::fs::metadata(&wal).unwrap().len(), 0);
+    market.post_job(4, "user_001".to_string(), "LLaMA-3-70B".to_string(), 100).unwrap();
+    let root = StateTree::new().commit_market(market.market());
+    let event_seq = market.market().events().last_seq();
+    drop(market);
+    let market = open();
+    assert_eq!(StateTree::new().commit_market(market.market()), root);
+    assert_eq!(market.market().events().last_seq(), event_seq);
+    assert_eq!(market.last_seq(), seq + 2);
+    assert!(market.pending_txs().is_empty());
+    drop(market);
+
+    // A record torn by a crash mid-append is dropped; a corrupt complete one is an error
+    let len = std::fs::metadata(&wal).unwrap().len();
+    std::fs::OpenOptions::new().append(true).open(&wal).unwrap().write_all(b"{\"seq\":7,\"at\"").unwrap();
+    let market = open();
+    assert_eq!(StateTree::new().commit_market(market.market()), root);
+    assert_eq!(std::fs::metadata(&wal).unwrap().len(), len);
+    drop(market);
+    std::fs::OpenOptions::new().append(true).open(&wal).unwrap().write_all(b"not a record\n").unwrap();
+    let storage = FileStorage::open(dir.path()).unwrap();
+    assert_eq!(PersistentMarket::open(storage).err().unwrap().code(), "STORAGE_FAILURE");
+}
+
+// Storage kept in memory whose appends can be made to fail
+#[derive(Clone, Default)]
+struct FlakyStorage {
+    state: Arc<std::sync::Mutex<(Option<MarketSnapshot>, Vec<WalRecord>)>>,
+    fail_appends: Arc<std::sync::atomic::AtomicBool>,
+}
+
+impl MarketStorage for FlakyStorage {
+    fn append(&mut self, record: &WalRecord) -> SpectreResult<()> {
+        if self.fail_appends.load(std::sync::atomic::Ordering::SeqCst) {
+            return Err(SpectreError::StorageFailure { path: "memory".to_string(), reason: "disk full".to_string() });
+        }
+        self.state.lock().unwrap().1.push(record.clone());
+        Ok(())
+    }
+
+    fn save_snapshot(&mut self, snapshot: &MarketSnapshot) -> SpectreResult<()> {
+        self.state.lock().unwrap().0 = Some(snapshot.clone());
+        Ok(())
+    }
+
+    fn load(&mut self) -> SpectreResult<(Option<MarketSnapshot>, Vec<WalRecord>)> {
+        Ok(self.state.lock().unwrap().clone())
+    }
+
+    fn compact(&mut self, through_seq: u64) -> SpectreResult<()> {
+        self.state.lock().unwrap().1.retain(|record| record.seq > through_seq);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_persistent_market_logs_only_applied_changes() {
+    let storage = FlakyStorage::default();
+    let mut catalog = ModelCatalog::builtin();
+    catalog.add_model("Qwen-2-72B", 32000, "1", "0x7177656e01", 0).unwrap();
+    let registry = SDKeyRegistry::builder().catalog(catalog).unbonding_period(60);
+    let mut market = PersistentMarket::open_with(storage.clone(), Arc::new(SystemClock), registry).unwrap();
+    let key = generate_agent_keypair();
+
+    // A failure that changed nothing leaves no record
+    market.mint("agent_001", 5_000).unwrap();
+    assert_eq!(market.transfer("agent_001", "user_001", 9_999).unwrap_err().code(), "INSUFFICIENT_FUNDS");
+    assert_eq!(market.post_job(1, "user_001".to_string(), "LLaMA-3-70B".to_string(), 10).unwrap_err().code(), "INSUFFICIENT_FUNDS");
+    market.register_agent("agent_001".to_string(), vec!["Qwen-2-72B".to_string()], 5_000, key.verifying_key()).unwrap();
+    assert_eq!(market.last_seq(), 2);
+    assert_eq!(storage.state.lock().unwrap().1.len(), 2);
+    drop(market);
+
+    // The configuration the log was written under comes back without the builder
+    let mut market = PersistentMarket::open(storage.clone()).unwrap();
+    assert!(market.market().registry.catalog.get("Qwen-2-72B").is_some());
+    assert_eq!(market.market().registry.stakes.unbonding_period_secs, 60);
+    assert_eq!(market.market().registry.stakes.bonded("agent_001"), 5_000);
+
+    // A change that couldn't be logged stops the market until it is reopened
+    storage.fail_appends.store(true, std::sync::atomic::Ordering::SeqCst);
+    assert_eq!(market.mint("user_001", 100).unwrap_err().code(), "STORAGE_FAILURE");
+    storage.fail_appends.store(false, std::sync::atomic::Ordering::SeqCst);
+    assert_eq!(market.mint("user_002", 100).unwrap_err().code(), "STORAGE_FAILURE");
+    assert_eq!(market.compact().unwrap_err().code(), "STORAGE_FAILURE");
+    drop(market);
+    let market = PersistentMarket::open(storage.clone()).unwrap();
+    assert_eq!((market.market().ledger.balance("user_001"), market.last_seq()), (0, 2));
+    drop(market);
+
+    // A record that replays differently fails the open instead of recovering the wrong state
+    storage.state.lock().unwrap().1[1].event_seq += 1;
+    assert_eq!(PersistentMarket::open(storage.clone()).err().unwrap().code(), "REPLAY_DIVERGED");
+    storage.state.lock().unwrap().1[1].op = MarketOp::RegisterAgent {
+        agent_id: "agent_002".to_string(),
+        algos: vec![],
+        stake: 0,
+        public_key: "not hex".to_string(),
+    };
+    assert_eq!(PersistentMarket::open(storage).err().unwrap().code(), "REPLAY_DIVERGED");
+}
+
+#[test]
+fn test_persistent_market_compacts_automatically() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut market = PersistentMarket::open_dir(dir.path()).unwrap().with_snapshot_every(4);
+    for i in 0..10 {
+        market.mint(&format!("user_{:03}", i), 100).unwrap();
+    }
+    let wal = std::fs::read_to_string(dir.path().join("wal.log")).unwrap();
+    assert!(wal.lines().count() <= 4);
+    drop(market);
+
+    let market = PersistentMarket::open_dir(dir.path()).unwrap();
+    assert_eq!(market.last_seq(), 10);
+    assert_eq!(market.market().ledger.total_supply(), 1_000);
+    assert_eq!(market.market().ledger.balance("user_009"), 100);
+}
+
+#[test]
+fn test_domain_events_rebuild_market_state() {
+    let clock = ManualClock::new(1_700_000_000);
+    let registry = || SDKeyRegistry::builder().clock(Arc::new(clock.clone())).unbonding_period(60).build();
+    let mut market = SpectreMarket::with_registry(registry());
+    let mut feed = market.events().subscribe();
+    let (good_key, bad_key) = (generate_agent_keypair(), generate_agent_keypair());
+    let proof = |key: &SigningKey, agent_id: &str, job_id: u64, nonce: u32| {
+        ProofBuilder::new(key, agent_id.to_string(), "LLaMA-3-70B").unwrap().job(job_id).result(b"done").timestamp(clock.now()).nonce(nonce).build()
+    };
+
+    market.mint("agent_good", 6_000).unwrap();
+    market.mint("agent_bad", 5_000).unwrap();
+    market.mint("user_001", 1_000).unwrap();
+    market.transfer("user_001", "user_002", 100).unwrap();
+    market.register_agent("agent_good".to_string(), vec!["LLaMA-3-70B".to_string()], 5_000, good_key.verifying_key()).unwrap();
+    market.register_agent("agent_bad".to_string(), vec!["LLaMA-3-70B".to_string()], 5_000, bad_key.verifying_key()).unwrap();
+    market.deposit_stake("agent_good", 1_000).unwrap();
+    for job_id in 1..=5 {
+        let request = JobRequest::new(job_id, "user_001".to_string(), "LLaMA-3-70B".to_string(), 100);
+        let request = if job_id == 4 { request.expires_at(clock.now() + 10) } else { request };
+        market.post_job_request(request).unwrap();
+    }
+
+    // Paid (and disputed later on)
+    market.attempt_job_execution(1, &proof(&good_key, "agent_good", 1, 1)).unwrap();
+    clock.advance(2);
+    market.submit_result(1, proof(&good_key, "agent_good", 1, 2)).unwrap();
+    market.verify_result(1).unwrap();
+    market.settle_job(1).unwrap();
+    market.attempt_job_execution(4, &proof(&good_key, "agent_good", 4, 3)).unwrap();
+    market.attempt_job_execution(5, &proof(&good_key, "agent_good", 5, 4)).unwrap();
+
+    // Forged model build: rejected, slashed, refunded
+    market.attempt_job_execution(2, &proof(&bad_key, "agent_bad", 2, 1)).unwrap();
+    let forged = ProofBuilder::new(&bad_key, "agent_bad".to_string(), "LLaMA-3-70B").unwrap().job(2).timestamp(clock.now()).nonce(2).model_hash("0xdeadbeef0000");
+    market.submit_result(2, forged.build()).unwrap();
+    assert_eq!(market.verify_result(2).unwrap_err().code(), "HASH_MISMATCH");
+    market.settle_job(2).unwrap();
+    market.cancel_job(3).unwrap();
+
+    // Settlement transaction fails and is detached; job 4 misses its deadline
+    market.submit_result(5, proof(&good_key, "agent_good", 5, 5)).unwrap();
+    market.verify_result(5).unwrap();
+    market.dispute_job(1).unwrap();
+    market.settle_on_confirmation(5, "tx_5".to_string()).unwrap();
+    let dropped = PsyTxUpdate { tx_id: "tx_5".to_string(), status: PsyTxStatus::Dropped { reason: "fee too low".to_string() } };
+    assert!(market.apply_tx_update(&dropped).unwrap().1.is_err());
+    clock.advance(20);
+    assert_eq!(market.expire_jobs(clock.now()).expired, vec![4]);
+    market.request_unstake("agent_good", 500).unwrap();
+    clock.advance(61);
+    assert_eq!(market.withdraw_unstaked("agent_good").unwrap(), 500);
+    market.ledger.check_invariants().unwrap();
+
+    // Bare registry calls bond nothing on the ledger, so replay mustn't either
+    market.registry.deposit_stake("agent_good", 50).unwrap();
+    market.registry.register_agent("agent_side".to_string(), "LLaMA-3-70B".to_string(), 300, bad_key.verifying_key()).unwrap();
+    assert_eq!(market.ledger.balance("agent_side"), 0);
+
+    // Sequenced without gaps, streamed live, and serializable
+    let records = market.events().records();
+    assert!(records.iter().enumerate().all(|(i, record)| record.seq == i as u64 + 1));
+    assert_eq!(market.events().last_seq(), records.len() as u64);
+    let mut streamed = 0;
+    while feed.try_recv().is_ok() {
+        streamed += 1;
+    }
+    assert_eq!(streamed, records.len());
+    let json = serde_json::to_string(&records).unwrap();
+    let types: Vec<String> = records.iter()
+        .map(|record| serde_json::to_value(record).unwrap()["event"]["type"].as_str().unwrap().to_string())
+        .collect();
+    for expected in [
+        "agent_registered", "proof_accepted", "job_claimed", "agent_rewarded", "job_disputed", "result_rejected",
+        "agent_slashed", "job_cancelled", "settlement_tx_failed", "job_expired", "stake_withdrawn",
+    ] {
+        assert!(types.iter().any(|t| t == expected), "no {} event", expected);
+    }
+
+    // Replaying the log rebuilds the same market, and the log carries on numbering
+    let decoded: Vec<EventRecord> = serde_json::from_str(&json).unwrap();
+    let mut rebuilt = SpectreMarket::from_events(registry(), &decoded).unwrap();
+    assert_eq!(StateTree::new().commit_market(&rebuilt), StateTree::new().commit_market(&market));
+    assert_eq!(serde_json::to_value(&rebuilt.ledger).unwrap(), serde_json::to_value(&market.ledger).unwrap());
+    let (replayed, live) = (rebuilt.registry.snapshot(), market.registry.snapshot());
+    assert_eq!(serde_json::to_value(&replayed.stakes).unwrap(), serde_json::to_value(&live.stakes).unwrap());
+    assert_eq!(replayed.used_nonces, live.used_nonces);
+    assert_eq!(serde_json::to_value(&replayed.verified_agents).unwrap(), serde_json::to_value(&live.verified_agents).unwrap());
+    for agent_id in ["agent_good", "agent_bad"] {
+        assert_eq!(rebuilt.registry.reputation.history(agent_id), market.registry.reputation.history(agent_id));
+    }
+    assert_eq!(serde_json::to_value(&rebuilt.jobs).unwrap(), serde_json::to_value(&market.jobs).unwrap());
+    assert_eq!(rebuilt.events().last_seq(), records.len() as u64);
+    rebuilt.mint("user_003", 1).unwrap();
+    assert_eq!(rebuilt.events().last_seq(), records.len() as u64 + 1);
+
+    // Logs keep everything unless capped, and a capped log that dropped records can't rebuild a market
+    assert_eq!(market.events().records()[0].seq, 1);
+    let capped = SDKeyRegistry::builder().clock(Arc::new(clock.clone())).event_log(EventLog::with_retention(3)).build();
+    let mut capped = SpectreMarket::from_events(capped, &decoded).unwrap();
+    capped.mint("user_003", 1).unwrap();
+    assert_eq!(capped.events().len(), 3);
+    assert_eq!(capped.events().records()[0].seq, records.len() as u64 - 1);
+    assert_eq!(capped.events().last_seq(), records.len() as u64 + 1);
+    let err = SpectreMarket::from_events(SDKeyRegistry::new(), &capped.events().records()).err().unwrap();
+    assert_eq!(err, SpectreError::EventLogIncomplete { expected_seq: 1, found_seq: records.len() as u64 - 1 });
+    let mut gapped = decoded.clone();
+    gapped.remove(2);
+    assert_eq!(SpectreMarket::from_events(SDKeyRegistry::new(), &gapped).err().unwrap().code(), "EVENT_LOG_INCOMPLETE");
+}